@@ -0,0 +1,537 @@
+use cosmwasm_std::{
+    Addr, Coin, DepsMut, Empty, Env, MessageInfo, Order, Response, StdResult, Storage,
+};
+
+use crate::denoms::ensure_denom_enabled;
+use crate::destination::validate_destination;
+use crate::events::{tx_event, TX_ADDED_EVENT, TX_SETTLED_EVENT};
+use crate::execute::{insert_pending_tx, settle_fulfilled_tx, TxOrigin, TxOwner, TxStatus};
+use crate::state::{
+    AttestationConfig, AttestationConflict, AttestedTx, State, ATTESTATION_CONFIG,
+    ATTESTATION_CONFLICTS, ATTESTERS, CONFIG, PROCESSED_ORIGINS, SETTLEMENT_ATTESTATIONS, STATE,
+    TX_ATTESTATIONS,
+};
+use crate::tokenfactory::settlement_mint;
+use crate::ContractError;
+
+// attestation_enabled returns whether txs must be attested to before being added or settled.
+pub fn attestation_enabled(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(ATTESTATION_CONFIG
+        .may_load(storage)?
+        .is_some_and(|config| config.threshold > 0))
+}
+
+pub(crate) fn load_threshold(storage: &dyn Storage) -> Result<u32, ContractError> {
+    match ATTESTATION_CONFIG.may_load(storage)? {
+        Some(config) if config.threshold > 0 => Ok(config.threshold),
+        _ => Err(ContractError::AttestationDisabled {}),
+    }
+}
+
+// clear_tx_attestations removes the attestations to an origin deposit once it funded a tx,
+// however the tx was added. A conflict, if any, keeps its own copy of them.
+pub(crate) fn clear_tx_attestations(storage: &mut dyn Storage, key: &str) -> StdResult<()> {
+    let attesters = TX_ATTESTATIONS
+        .prefix(key)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    for attester in attesters {
+        TX_ATTESTATIONS.remove(storage, (key, &attester));
+    }
+    Ok(())
+}
+
+// configure_attestation is called by the admin to replace the set of attesters and the
+// number of them that must agree. A threshold of zero disables attestation mode.
+pub fn configure_attestation(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    attesters: Vec<String>,
+    threshold: u32,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Attesters listed more than once only count once towards the threshold
+    let mut attesters = attesters
+        .iter()
+        .map(|attester| deps.api.addr_validate(attester))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    attesters.sort();
+    attesters.dedup();
+    if threshold as usize > attesters.len() {
+        return Err(ContractError::InvalidAttestationThreshold {
+            threshold,
+            attesters: attesters.len() as u32,
+        });
+    }
+
+    // Replace the previous attester set
+    let previous = ATTESTERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    for attester in previous {
+        ATTESTERS.remove(deps.storage, &attester);
+    }
+    for attester in attesters.iter() {
+        ATTESTERS.save(deps.storage, attester, &Empty {})?;
+    }
+    ATTESTATION_CONFIG.save(deps.storage, &AttestationConfig { threshold })?;
+
+    Ok(Response::new()
+        .add_attribute("action", "configure_attestation")
+        .add_attribute("attesters", attesters.len().to_string())
+        .add_attribute("threshold", threshold.to_string()))
+}
+
+// attest_tx is called by a registered attester to attest to a deposit on the origin chain.
+// Once the threshold of attesters agree on the same tx, it is added to the pending
+// transactions store. Attesters that disagree are recorded as a conflict.
+pub fn attest_tx(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    destination_addr: String,
+    output_coin: Coin,
+    origin: TxOrigin,
+    owner: Option<TxOwner>,
+) -> Result<Response, ContractError> {
+    let threshold = load_threshold(deps.storage)?;
+    if !ATTESTERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    validate_destination(deps.as_ref(), &destination_addr)?;
+    ensure_denom_enabled(deps.storage, &output_coin.denom)?;
+
+    let key = origin.key();
+    if PROCESSED_ORIGINS.has(deps.storage, &key) {
+        return Err(ContractError::OriginAlreadyProcessed { origin: key });
+    }
+    if TX_ATTESTATIONS.has(deps.storage, (&key, &info.sender)) {
+        return Err(ContractError::AlreadyAttested { key });
+    }
+
+    let attested = AttestedTx {
+        destination_addr,
+        output_coin,
+        origin,
+        owner,
+    };
+    TX_ATTESTATIONS.save(deps.storage, (&key, &info.sender), &attested)?;
+
+    // Only attestations from the current attester set count towards the threshold
+    let attestations = TX_ATTESTATIONS
+        .prefix(&key)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            item.as_ref()
+                .map_or(true, |(attester, _)| ATTESTERS.has(deps.storage, attester))
+        })
+        .collect::<StdResult<Vec<(Addr, AttestedTx)>>>()?;
+
+    if attestations.iter().any(|(_, other)| *other != attested) {
+        ATTESTATION_CONFLICTS.save(
+            deps.storage,
+            &key,
+            &AttestationConflict {
+                origin_key: key.clone(),
+                height: env.block.height,
+                attestations: attestations.clone(),
+            },
+        )?;
+    }
+
+    let matching = attestations
+        .iter()
+        .filter(|(_, other)| *other == attested)
+        .count() as u32;
+
+    let response = Response::new()
+        .add_attribute("action", "attest_tx")
+        .add_attribute("attester", info.sender)
+        .add_attribute("origin", &key)
+        .add_attribute("attestations", matching.to_string())
+        .add_attribute("threshold", threshold.to_string());
+    if matching < threshold {
+        return Ok(response);
+    }
+
+    let tx = insert_pending_tx(
+        deps.storage,
+        &env,
+        attested.destination_addr,
+        attested.output_coin,
+        Some(attested.origin),
+        attested.owner,
+    )?;
+    Ok(response
+        .add_attribute("tx_id", tx.id.to_string())
+        .add_event(tx_event(TX_ADDED_EVENT, &tx, TxStatus::Pending, None)))
+}
+
+// attest_settlement is called by a registered attester to attest that the bridged funds of
+// a fulfilled tx have cleared. Once the threshold is reached the tx is settled.
+pub fn attest_settlement(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tx_id: u64,
+) -> Result<Response, ContractError> {
+    let threshold = load_threshold(deps.storage)?;
+    if !ATTESTERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let state: State = STATE.load(deps.storage)?;
+    if !state.fulfilled_txs.iter().any(|tx| tx.id == tx_id) {
+        return Err(ContractError::TransactionNotFound { id: tx_id });
+    }
+    if SETTLEMENT_ATTESTATIONS.has(deps.storage, (tx_id, &info.sender)) {
+        return Err(ContractError::AlreadyAttested {
+            key: tx_id.to_string(),
+        });
+    }
+    SETTLEMENT_ATTESTATIONS.save(deps.storage, (tx_id, &info.sender), &Empty {})?;
+
+    let attestations = SETTLEMENT_ATTESTATIONS
+        .prefix(tx_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?
+        .into_iter()
+        .filter(|attester| ATTESTERS.has(deps.storage, attester))
+        .count() as u32;
+
+    let response = Response::new()
+        .add_attribute("action", "attest_settlement")
+        .add_attribute("attester", info.sender)
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("attestations", attestations.to_string())
+        .add_attribute("threshold", threshold.to_string());
+    if attestations < threshold {
+        return Ok(response);
+    }
+
+    let attesters = SETTLEMENT_ATTESTATIONS
+        .prefix(tx_id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<Addr>>>()?;
+    for attester in attesters {
+        SETTLEMENT_ATTESTATIONS.remove(deps.storage, (tx_id, &attester));
+    }
+
+    let tx = settle_fulfilled_tx(deps.storage, &env, tx_id)?;
+    let fulfiller = tx.fill.as_ref().map(|fill| &fill.fulfiller_addr);
+    let mint = settlement_mint(&env, &tx);
+    Ok(response.add_messages(mint).add_event(tx_event(
+        TX_SETTLED_EVENT,
+        &tx,
+        TxStatus::Settled,
+        fulfiller,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{add_pending_tx, move_pending_tx_to_fulfilled_tx};
+    use crate::state::FulfillState;
+    use crate::testing::{mock_config, mock_denom, mock_state};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, Reply, SubMsgResponse, SubMsgResult, Uint128};
+
+    fn btc_origin() -> TxOrigin {
+        TxOrigin {
+            chain: "bitcoin".to_string(),
+            tx_hash: "deadbeef".to_string(),
+            vout: 1,
+        }
+    }
+
+    fn setup(deps: DepsMut) {
+        mock_state(deps.storage);
+        mock_denom(deps.storage, "ubtc");
+        configure_attestation(
+            deps,
+            mock_env(),
+            mock_info("admin", &[]),
+            vec![
+                "attester1".to_string(),
+                "attester2".to_string(),
+                "attester3".to_string(),
+            ],
+            2,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_configure_attestation() {
+        let mut deps = mock_dependencies();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+
+        let err = configure_attestation(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            vec!["attester1".to_string()],
+            2,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidAttestationThreshold {
+                threshold: 2,
+                attesters: 1
+            }
+        ));
+
+        let err = configure_attestation(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("attester1", &[]),
+            vec!["attester1".to_string()],
+            1,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // Duplicate attesters do not help reach the threshold
+        let err = configure_attestation(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            vec!["attester1".to_string(), "attester1".to_string()],
+            2,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidAttestationThreshold {
+                threshold: 2,
+                attesters: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_attest_tx_threshold() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut());
+
+        // Posting directly is disabled while attestation is required
+        let err = add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            Some(btc_origin()),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AttestationRequired {}));
+
+        // Only registered attesters can attest
+        let err = attest_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // The first attestation does not add the tx
+        let response = attest_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester1", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap();
+        assert!(response.events.is_empty());
+        assert!(STATE
+            .load(deps.as_ref().storage)
+            .unwrap()
+            .pending_txs
+            .is_empty());
+
+        // An attester cannot attest twice
+        let err = attest_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester1", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyAttested { .. }));
+
+        // The second matching attestation reaches the threshold and adds the tx
+        let response = attest_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester2", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(response.events[0].ty, TX_ADDED_EVENT);
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs.len(), 1);
+        assert_eq!(state.pending_txs[0].origin, Some(btc_origin()));
+        assert!(TX_ATTESTATIONS
+            .prefix(&btc_origin().key())
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+
+        // The origin deposit cannot be attested to again
+        let err = attest_tx(
+            deps.as_mut(),
+            env,
+            mock_info("attester3", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::OriginAlreadyProcessed { .. }));
+    }
+
+    #[test]
+    fn test_attest_tx_conflict() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut());
+
+        attest_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester1", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap();
+        attest_tx(
+            deps.as_mut(),
+            env,
+            mock_info("attester2", &[]),
+            "other_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap();
+
+        // Neither version reached the threshold, and the disagreement is recorded
+        assert!(STATE
+            .load(deps.as_ref().storage)
+            .unwrap()
+            .pending_txs
+            .is_empty());
+        let conflict = ATTESTATION_CONFLICTS
+            .load(deps.as_ref().storage, &btc_origin().key())
+            .unwrap();
+        assert_eq!(conflict.attestations.len(), 2);
+        assert_eq!(conflict.attestations[1].0, Addr::unchecked("attester2"));
+        assert_eq!(conflict.attestations[1].1.destination_addr, "other_addr");
+
+        // A third attester settles the disagreement. The attestations are cleared while the
+        // conflict keeps its record of them.
+        attest_tx(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("attester3", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            btc_origin(),
+            None,
+        )
+        .unwrap();
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs[0].destination_addr, "destination_addr");
+        assert!(TX_ATTESTATIONS
+            .prefix(&btc_origin().key())
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+        let conflict = ATTESTATION_CONFLICTS
+            .load(deps.as_ref().storage, &btc_origin().key())
+            .unwrap();
+        assert_eq!(conflict.attestations.len(), 3);
+    }
+
+    #[test]
+    fn test_attest_settlement() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut());
+
+        for attester in ["attester1", "attester2"] {
+            attest_tx(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(attester, &[]),
+                "destination_addr".to_string(),
+                coin(100, "ubtc"),
+                btc_origin(),
+                None,
+            )
+            .unwrap();
+        }
+        let msg = Reply {
+            id: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                data: None,
+                events: vec![],
+            }),
+        };
+        move_pending_tx_to_fulfilled_tx(
+            deps.as_mut(),
+            env.clone(),
+            msg,
+            FulfillState {
+                fulfiller_addr: Addr::unchecked("market_maker"),
+                protocol_fee: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+        let response =
+            attest_settlement(deps.as_mut(), env.clone(), mock_info("attester1", &[]), 0).unwrap();
+        assert!(response.events.is_empty());
+
+        let response =
+            attest_settlement(deps.as_mut(), env, mock_info("attester3", &[]), 0).unwrap();
+        assert_eq!(response.events[0].ty, TX_SETTLED_EVENT);
+        assert!(STATE
+            .load(deps.as_ref().storage)
+            .unwrap()
+            .fulfilled_txs
+            .is_empty());
+        assert!(SETTLEMENT_ATTESTATIONS
+            .prefix(0)
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+    }
+}
@@ -0,0 +1,565 @@
+// Gas benchmarks of the order book operations. `State` holds the whole order book and is
+// rewritten on every call, so the gas of adding, filling, expiring and settling a tx grows
+// with the number of open orders. Each operation is run on the compiled contract in the
+// CosmWasm VM, which meters wasm execution the way the chain does. The KV store gas is
+// measured separately by running the contract's handlers on mock deps, with every store
+// access priced the way the Cosmos SDK charges it.
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::{env, fs, io};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    coin, coins, from_json, to_json_vec, Coin, ContractResult, DepsMut, Env, Order, OwnedDeps,
+    QuerierWrapper, Record, Reply, Response, StdError, Storage, SubMsgResponse, SubMsgResult,
+};
+use cosmwasm_vm::testing::{
+    MockApi as VmMockApi, MockQuerier as VmMockQuerier, MockStorage as VmMockStorage,
+};
+use cosmwasm_vm::{
+    call_execute_raw, call_reply_raw, Backend, Instance, InstanceOptions, Storage as VmStorage,
+    VmError,
+};
+use thiserror::Error;
+
+use crate::contract::{execute_msg, instantiate, reply};
+use crate::execute::{Tx, TxStatus};
+use crate::history::record_tx;
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::state::{DenomMetadata, SettlementMode, STATE};
+use crate::stats::record_added;
+use crate::ContractError;
+
+const ADMIN: &str = "admin";
+const MODULE_ACCOUNT: &str = "module_account";
+const MAKER: &str = "maker";
+const DESTINATION: &str = "destination";
+const DENOM: &str = "ubtc";
+const AMOUNT: u128 = 100_000;
+
+// Open order book sizes the operations are measured at
+pub const ORDER_BOOK_SIZES: [usize; 3] = [10, 1_000, 10_000];
+// Open order book sizes the operations are measured at in the VM. The chain rejects values
+// over 128 KiB, which `State` outgrows at about 700 open orders.
+pub const VM_ORDER_BOOK_SIZES: [usize; 3] = [10, 100, 500];
+
+// CosmWasm gas charged per unit of SDK gas, wasmd's `DefaultGasMultiplier`
+pub const GAS_MULTIPLIER: u64 = 140_000_000;
+// Gas limit of a measured operation, far above what any of them uses
+const VM_GAS_LIMIT: u64 = 100_000_000 * GAS_MULTIPLIER;
+// Environment variable overriding the path of the compiled contract
+pub const WASM_PATH_VAR: &str = "CATALYST_WASM";
+
+// KvGasConfig is the gas the Cosmos SDK charges for KV store access, see `KVGasConfig` in
+// the SDK's store types
+pub struct KvGasConfig {
+    pub delete_cost: u64,
+    pub read_cost_flat: u64,
+    pub read_cost_per_byte: u64,
+    pub write_cost_flat: u64,
+    pub write_cost_per_byte: u64,
+    pub iter_next_cost_flat: u64,
+}
+
+pub const KV_GAS_CONFIG: KvGasConfig = KvGasConfig {
+    delete_cost: 1_000,
+    read_cost_flat: 1_000,
+    read_cost_per_byte: 3,
+    write_cost_flat: 2_000,
+    write_cost_per_byte: 30,
+    iter_next_cost_flat: 30,
+};
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum Operation {
+    Add,
+    Fill,
+    Remove,
+    Settle,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 4] = [
+        Operation::Add,
+        Operation::Fill,
+        Operation::Remove,
+        Operation::Settle,
+    ];
+}
+
+// StorageCost is the store access of an operation and the gas charged for it. Keys are
+// counted without the prefix the chain adds for the contract's namespace.
+#[cw_serde]
+#[derive(Default)]
+pub struct StorageCost {
+    pub reads: u64,
+    pub writes: u64,
+    pub removes: u64,
+    pub iterated: u64,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub gas: u64,
+}
+
+// VmCost is the gas the CosmWasm VM charged for executing an operation on the compiled
+// contract, in CosmWasm gas and in the SDK gas the chain converts it to. Store access is
+// priced by the chain on top of it, see StorageCost.
+#[cw_serde]
+#[derive(Default)]
+pub struct VmCost {
+    pub gas: u64,
+    pub sdk_gas: u64,
+}
+
+#[derive(Error, Debug)]
+pub enum BenchError {
+    #[error("{0}")]
+    Contract(#[from] ContractError),
+
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Vm(#[from] VmError),
+
+    #[error("failed to read the contract from {path}, build it with `cargo run-script optimize`: {error}")]
+    Wasm { path: String, error: io::Error },
+
+    #[error("{operation:?} failed in the VM: {error}")]
+    Failed { operation: Operation, error: String },
+}
+
+// MeteredStorage wraps a store, accounting for every access made through it
+pub struct MeteredStorage<'a> {
+    inner: &'a mut dyn Storage,
+    cost: RefCell<StorageCost>,
+}
+
+impl<'a> MeteredStorage<'a> {
+    pub fn new(inner: &'a mut dyn Storage) -> Self {
+        MeteredStorage {
+            inner,
+            cost: RefCell::new(StorageCost::default()),
+        }
+    }
+
+    pub fn cost(&self) -> StorageCost {
+        self.cost.borrow().clone()
+    }
+
+    fn charge_read(&self, bytes: usize, flat: u64) {
+        let mut cost = self.cost.borrow_mut();
+        cost.bytes_read += bytes as u64;
+        cost.gas += flat + KV_GAS_CONFIG.read_cost_per_byte * bytes as u64;
+    }
+}
+
+impl Storage for MeteredStorage<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.inner.get(key);
+        self.cost.borrow_mut().reads += 1;
+        self.charge_read(
+            key.len() + value.as_ref().map_or(0, Vec::len),
+            KV_GAS_CONFIG.read_cost_flat,
+        );
+        value
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        Box::new(self.inner.range(start, end, order).inspect(|(key, value)| {
+            self.cost.borrow_mut().iterated += 1;
+            self.charge_read(key.len() + value.len(), KV_GAS_CONFIG.iter_next_cost_flat);
+        }))
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        let mut cost = self.cost.borrow_mut();
+        let bytes = (key.len() + value.len()) as u64;
+        cost.writes += 1;
+        cost.bytes_written += bytes;
+        cost.gas += KV_GAS_CONFIG.write_cost_flat + KV_GAS_CONFIG.write_cost_per_byte * bytes;
+        drop(cost);
+        self.inner.set(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let mut cost = self.cost.borrow_mut();
+        cost.removes += 1;
+        cost.gas += KV_GAS_CONFIG.delete_cost;
+        drop(cost);
+        self.inner.remove(key);
+    }
+}
+
+// Bench is a contract on mock deps with an order book of `open_orders` pending txs, and a
+// fulfilled tx awaiting settlement
+pub struct Bench {
+    deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: Env,
+    pending_id: u64,
+    fulfilled_id: u64,
+}
+
+impl Bench {
+    pub fn new(open_orders: usize) -> Result<Self, ContractError> {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(&account(ADMIN), &[]),
+            InstantiateMsg {
+                module_account: account(MODULE_ACCOUNT),
+                admin: None,
+                history_retention_blocks: None,
+                protocol_fee_bps: Some(100),
+                withdrawal_timelock_seconds: None,
+                foreign_destination_formats: None,
+                outbound_timeout_seconds: None,
+                destination_routes: None,
+            },
+        )?;
+        run(
+            deps.as_mut(),
+            &env,
+            &account(ADMIN),
+            ExecuteMsg::SetDenom {
+                denom: DENOM.to_string(),
+                metadata: DenomMetadata {
+                    symbol: "BTC".to_string(),
+                    decimals: 8,
+                    origin_chain: "bitcoin".to_string(),
+                    enabled: true,
+                    settlement: SettlementMode::Module,
+                },
+            },
+            &[],
+        )?;
+
+        let fulfilled_id = STATE.load(&deps.storage)?.next_id;
+        run(deps.as_mut(), &env, &account(MODULE_ACCOUNT), add_tx(), &[])?;
+        fill(deps.as_mut(), &env, fulfilled_id)?;
+
+        // Posting the orders one by one rewrites the growing book each time, so the book is
+        // seeded directly, with the same history and stats a posted order leaves
+        let mut state = STATE.load(&deps.storage)?;
+        let pending_id = state.next_id;
+        for _ in 0..open_orders {
+            let tx = Tx {
+                id: state.next_id,
+                destination_addr: account(DESTINATION),
+                coin: coin(AMOUNT, DENOM),
+                added_height: env.block.height,
+                fill: None,
+                origin: None,
+                owner: None,
+                amendments: 0,
+                settlement: SettlementMode::Module,
+                payee: None,
+            };
+            record_added(&mut deps.storage, &tx)?;
+            record_tx(&mut deps.storage, &tx, TxStatus::Pending, tx.added_height)?;
+            state.next_id += 1;
+            state.pending_txs.push(tx);
+        }
+        STATE.save(&mut deps.storage, &state)?;
+
+        Ok(Bench {
+            deps,
+            env,
+            pending_id,
+            fulfilled_id,
+        })
+    }
+
+    // measure runs the operation on the bench, returning the storage cost it incurred
+    pub fn measure(&mut self, operation: Operation) -> Result<StorageCost, ContractError> {
+        let mut storage = MeteredStorage::new(&mut self.deps.storage);
+        let deps = DepsMut {
+            storage: &mut storage,
+            api: &self.deps.api,
+            querier: QuerierWrapper::new(&self.deps.querier),
+        };
+        let env = &self.env;
+        match operation {
+            Operation::Add => run(deps, env, &account(MODULE_ACCOUNT), add_tx(), &[]),
+            Operation::Fill => fill(deps, env, self.pending_id),
+            Operation::Remove => {
+                let msg = ExecuteMsg::RemoveTx {
+                    tx_id: self.pending_id,
+                };
+                run(deps, env, &account(MODULE_ACCOUNT), msg, &[])
+            }
+            Operation::Settle => {
+                let msg = ExecuteMsg::RemoveFulfilledTx {
+                    tx_id: self.fulfilled_id,
+                };
+                run(deps, env, &account(MODULE_ACCOUNT), msg, &[])
+            }
+        }?;
+        Ok(storage.cost())
+    }
+
+    // measure_vm runs the operation on the compiled contract, loaded with the bench's store,
+    // returning the gas the VM charged for it
+    pub fn measure_vm(self, wasm: &[u8], operation: Operation) -> Result<VmCost, BenchError> {
+        let mut storage = VmMockStorage::new();
+        for (key, value) in self.deps.storage.range(None, None, Order::Ascending) {
+            storage.set(&key, &value).0.map_err(VmError::from)?;
+        }
+        let backend = Backend {
+            api: VmMockApi::default(),
+            storage,
+            querier: VmMockQuerier::new(&[]),
+        };
+        let options = InstanceOptions {
+            gas_limit: VM_GAS_LIMIT,
+        };
+        let mut instance = Instance::from_code(wasm, backend, options, None)?;
+
+        let env = &self.env;
+        let module_account = account(MODULE_ACCOUNT);
+        let before = instance.create_gas_report().used_internally;
+        match operation {
+            Operation::Add => run_vm(
+                &mut instance,
+                env,
+                &module_account,
+                add_tx(),
+                &[],
+                operation,
+            ),
+            Operation::Fill => {
+                let msg = ExecuteMsg::FulfillTx {
+                    tx_id: self.pending_id,
+                };
+                let funds = coins(AMOUNT, DENOM);
+                run_vm(&mut instance, env, &account(MAKER), msg, &funds, operation)?;
+                let reply = to_json_vec(&fill_reply(self.pending_id))?;
+                let result = call_reply_raw(&mut instance, &to_json_vec(env)?, &reply)?;
+                check_result(&result, operation)
+            }
+            Operation::Remove => {
+                let msg = ExecuteMsg::RemoveTx {
+                    tx_id: self.pending_id,
+                };
+                run_vm(&mut instance, env, &module_account, msg, &[], operation)
+            }
+            Operation::Settle => {
+                let msg = ExecuteMsg::RemoveFulfilledTx {
+                    tx_id: self.fulfilled_id,
+                };
+                run_vm(&mut instance, env, &module_account, msg, &[], operation)
+            }
+        }?;
+        let gas = instance.create_gas_report().used_internally - before;
+        Ok(VmCost {
+            gas,
+            sdk_gas: gas.div_ceil(GAS_MULTIPLIER),
+        })
+    }
+}
+
+// run_vm executes a message on the compiled contract
+fn run_vm(
+    instance: &mut Instance<VmMockApi, VmMockStorage, VmMockQuerier>,
+    env: &Env,
+    sender: &str,
+    msg: ExecuteMsg,
+    funds: &[Coin],
+    operation: Operation,
+) -> Result<(), BenchError> {
+    let result = call_execute_raw(
+        instance,
+        &to_json_vec(env)?,
+        &to_json_vec(&mock_info(sender, funds))?,
+        &to_json_vec(&msg)?,
+    )?;
+    check_result(&result, operation)
+}
+
+// check_result fails the operation if the contract returned an error
+fn check_result(result: &[u8], operation: Operation) -> Result<(), BenchError> {
+    from_json::<ContractResult<Response>>(result)?
+        .into_result()
+        .map(|_| ())
+        .map_err(|error| BenchError::Failed { operation, error })
+}
+
+// run executes a message without the invariant checks of debug builds, which would be
+// accounted for as well
+fn run(
+    deps: DepsMut,
+    env: &Env,
+    sender: &str,
+    msg: ExecuteMsg,
+    funds: &[Coin],
+) -> Result<(), ContractError> {
+    execute_msg(deps, env.clone(), mock_info(sender, funds), msg)?;
+    Ok(())
+}
+
+// fill submits a fill and replies to it as the chain does once the send succeeded
+fn fill(mut deps: DepsMut, env: &Env, tx_id: u64) -> Result<(), ContractError> {
+    let msg = ExecuteMsg::FulfillTx { tx_id };
+    run(
+        deps.branch(),
+        env,
+        &account(MAKER),
+        msg,
+        &coins(AMOUNT, DENOM),
+    )?;
+    reply(deps, env.clone(), fill_reply(tx_id))?;
+    Ok(())
+}
+
+// account is the address of a named account, valid both on mock deps and in the VM
+fn account(name: &str) -> String {
+    MockApi::default().addr_make(name).into_string()
+}
+
+fn fill_reply(tx_id: u64) -> Reply {
+    Reply {
+        id: tx_id,
+        result: SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+        }),
+    }
+}
+
+fn add_tx() -> ExecuteMsg {
+    ExecuteMsg::AddTx {
+        destination_addr: account(DESTINATION),
+        output_coin: coin(AMOUNT, DENOM),
+        origin: None,
+        owner: None,
+        signatures: None,
+    }
+}
+
+// measure returns the storage cost of an operation on an order book of `open_orders`
+pub fn measure(operation: Operation, open_orders: usize) -> Result<StorageCost, ContractError> {
+    Bench::new(open_orders)?.measure(operation)
+}
+
+// measure_vm returns the VM gas of an operation on an order book of `open_orders`
+pub fn measure_vm(
+    wasm: &[u8],
+    operation: Operation,
+    open_orders: usize,
+) -> Result<VmCost, BenchError> {
+    Bench::new(open_orders)?.measure_vm(wasm, operation)
+}
+
+// contract_wasm reads the contract as built by the rust-optimizer, or from the path in
+// WASM_PATH_VAR
+pub fn contract_wasm() -> Result<Vec<u8>, BenchError> {
+    let path = env::var_os(WASM_PATH_VAR).map_or_else(
+        || PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("artifacts/catalyst.wasm"),
+        PathBuf::from,
+    );
+    fs::read(&path).map_err(|error| BenchError::Wasm {
+        path: path.display().to_string(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CosmWasm gas allowed per operation at each of VM_ORDER_BOOK_SIZES, about a tenth above
+    // the measured cost. Lower them along with changes that make the order book cheaper.
+    const THRESHOLDS: [(Operation, [u64; 3]); 4] = [
+        (Operation::Add, [261_000_000, 1_582_000_000, 7_420_000_000]),
+        (Operation::Fill, [338_000_000, 1_678_000_000, 7_583_000_000]),
+        (
+            Operation::Remove,
+            [186_000_000, 1_036_000_000, 4_785_000_000],
+        ),
+        (
+            Operation::Settle,
+            [270_000_000, 1_595_000_000, 7_363_000_000],
+        ),
+    ];
+
+    // KV store gas allowed per operation at each of ORDER_BOOK_SIZES, kept as a secondary
+    // report of how the store access grows with the order book
+    const STORAGE_THRESHOLDS: [(Operation, [u64; 3]); 4] = [
+        (Operation::Add, [145_000, 7_120_000, 70_900_000]),
+        (Operation::Fill, [182_000, 7_160_000, 71_000_000]),
+        (Operation::Remove, [124_000, 6_520_000, 65_000_000]),
+        (Operation::Settle, [147_000, 7_120_000, 70_900_000]),
+    ];
+
+    #[test]
+    fn test_vm_gas_within_thresholds() {
+        let wasm = contract_wasm().unwrap();
+        let mut exceeded = vec![];
+        for (operation, thresholds) in THRESHOLDS {
+            for (open_orders, threshold) in VM_ORDER_BOOK_SIZES.into_iter().zip(thresholds) {
+                let cost = measure_vm(&wasm, operation, open_orders).unwrap();
+                if cost.gas > threshold {
+                    exceeded.push(format!(
+                        "{operation:?} with {open_orders} open orders used {} gas, over {threshold}",
+                        cost.gas
+                    ));
+                }
+            }
+        }
+        assert!(exceeded.is_empty(), "{}", exceeded.join("\n"));
+    }
+
+    #[test]
+    fn test_storage_gas_within_thresholds() {
+        let mut exceeded = vec![];
+        for (operation, thresholds) in STORAGE_THRESHOLDS {
+            for (open_orders, threshold) in ORDER_BOOK_SIZES.into_iter().zip(thresholds) {
+                let cost = measure(operation, open_orders).unwrap();
+                if cost.gas > threshold {
+                    exceeded.push(format!(
+                        "{operation:?} with {open_orders} open orders used {} gas, over {threshold}",
+                        cost.gas
+                    ));
+                }
+            }
+        }
+        assert!(exceeded.is_empty(), "{}", exceeded.join("\n"));
+    }
+
+    #[test]
+    fn test_metered_storage_prices_access() {
+        let mut inner = MockStorage::new();
+        let mut storage = MeteredStorage::new(&mut inner);
+        storage.set(b"key", b"value");
+        assert_eq!(storage.get(b"key"), Some(b"value".to_vec()));
+        assert_eq!(storage.get(b"missing"), None);
+        assert_eq!(storage.range(None, None, Order::Ascending).count(), 1);
+        storage.remove(b"key");
+
+        assert_eq!(
+            storage.cost(),
+            StorageCost {
+                reads: 2,
+                writes: 1,
+                removes: 1,
+                iterated: 1,
+                bytes_read: 23,
+                bytes_written: 8,
+                // One write of 8 bytes, reads of 8 and 7 bytes, one iteration over 8 bytes
+                // and a removal
+                gas: 2_000 + 30 * 8 + 1_000 + 3 * 8 + 1_000 + 3 * 7 + 30 + 3 * 8 + 1_000,
+            }
+        );
+    }
+}
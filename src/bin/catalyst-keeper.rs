@@ -0,0 +1,66 @@
+// catalyst-keeper posts origin chain deposits to the order book and closes their txs once the
+// deposits are safe, through the chain daemon's command line.
+//
+//     catalyst-keeper keeper.json
+//
+// where keeper.json holds the keeper config, the chain to reach, the name of the module
+// account or poster key, the JSON-lines file deposit events are appended to and the file
+// the keeper resumes from.
+use std::{env, fs, path::PathBuf, process, thread, time::Duration};
+
+use cosmwasm_std::{from_json, to_json_string};
+use serde::Deserialize;
+
+use catalyst::interface::CliChain;
+use catalyst::keeper::{DepositSource, JsonLinesSource, Keeper, KeeperConfig, KeeperState};
+
+#[derive(Deserialize)]
+struct Config {
+    chain: CliChain,
+    key: String,
+    poll_interval_seconds: u64,
+    deposits: PathBuf,
+    state: PathBuf,
+    keeper: KeeperConfig,
+}
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: catalyst-keeper <config.json>");
+        process::exit(2);
+    };
+    let config: Config = match fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| from_json(bytes).map_err(|err| err.to_string()))
+    {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("failed to read {path}: {err}");
+            process::exit(1);
+        }
+    };
+    let state = match KeeperState::load(&config.state) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("failed to read {}: {err}", config.state.display());
+            process::exit(1);
+        }
+    };
+
+    let mut source = JsonLinesSource::new(&config.deposits);
+    let mut keeper = Keeper::new(config.chain, config.keeper, state);
+    loop {
+        match source.poll() {
+            Ok(events) => {
+                for action in keeper.step(&config.key, events) {
+                    println!("{}", to_json_string(&action).unwrap());
+                }
+                if let Err(err) = keeper.state.save(&config.state) {
+                    eprintln!("failed to save {}: {err}", config.state.display());
+                }
+            }
+            Err(err) => eprintln!("failed to read deposits: {err}"),
+        }
+        thread::sleep(Duration::from_secs(config.poll_interval_seconds));
+    }
+}
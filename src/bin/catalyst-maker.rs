@@ -0,0 +1,52 @@
+// catalyst-maker runs the reference market maker against a node, through the chain daemon's
+// command line.
+//
+//     catalyst-maker maker.json
+//
+// where maker.json holds the maker config, the chain to reach, the name of the signing key
+// and how often to poll the order book.
+use std::{env, fs, process, thread, time::Duration};
+
+use cosmwasm_std::{from_json, to_json_string};
+use serde::Deserialize;
+
+use catalyst::interface::CliChain;
+use catalyst::maker::{Maker, MakerConfig};
+
+#[derive(Deserialize)]
+struct Config {
+    chain: CliChain,
+    key: String,
+    poll_interval_seconds: u64,
+    maker: MakerConfig,
+}
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: catalyst-maker <config.json>");
+        process::exit(2);
+    };
+    let config: Config = match fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| from_json(bytes).map_err(|err| err.to_string()))
+    {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("failed to read {path}: {err}");
+            process::exit(1);
+        }
+    };
+
+    let mut maker = Maker::new(config.chain, config.maker);
+    loop {
+        match maker.poll(&config.key) {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    println!("{}", to_json_string(&outcome).unwrap());
+                }
+            }
+            Err(err) => eprintln!("poll failed: {err}"),
+        }
+        thread::sleep(Duration::from_secs(config.poll_interval_seconds));
+    }
+}
@@ -0,0 +1,41 @@
+// catalyst-sim replays a trace of origin chain deposits through the contract in-process and
+// prints a JSON report of how the configured makers fared.
+//
+//     catalyst-sim sim.json trace.csv
+//
+// Traces ending in .csv are read as CSV with a `time,event,deposit,amount,confirmations`
+// header, anything else as JSON lines.
+use std::{env, fs, process};
+
+use cosmwasm_std::{from_json, to_json_string};
+
+use catalyst::sim::{parse_trace, simulate, SimConfig};
+
+fn fail(message: String) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let [_, config_path, trace_path] = &args[..] else {
+        eprintln!("usage: catalyst-sim <config.json> <trace.csv|trace.jsonl>");
+        process::exit(2);
+    };
+
+    let config: SimConfig = fs::read(config_path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| from_json(bytes).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| fail(format!("failed to read {config_path}: {err}")));
+    let trace = fs::read_to_string(trace_path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            parse_trace(&contents, trace_path.ends_with(".csv")).map_err(|err| err.to_string())
+        })
+        .unwrap_or_else(|err| fail(format!("failed to read {trace_path}: {err}")));
+
+    match simulate(&config, trace) {
+        Ok(report) => println!("{}", to_json_string(&report).unwrap()),
+        Err(err) => fail(format!("simulation failed: {err}")),
+    }
+}
@@ -1,19 +1,49 @@
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
-use cosmwasm_std::{Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult};
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+};
 use cw2::set_contract_version;
 
+use crate::attestation::{attest_settlement, attest_tx, configure_attestation};
+use crate::denoms::{remove_denom, set_denom};
+use crate::destination::validate_routes;
 use crate::error::ContractError;
 use crate::execute::{
-    add_pending_tx, fulfill_pending_tx, move_pending_tx_to_fulfilled_tx, remove_pending_tx,
+    add_pending_tx, amend_pending_tx, cancel_pending_tx, fulfill_pending_tx, fulfill_pending_txs,
+    move_pending_tx_to_fulfilled_tx, remove_fulfilled_tx, remove_pending_tx, update_config,
+    BPS_DENOMINATOR,
 };
+#[cfg(debug_assertions)]
+use crate::invariants::assert_invariants;
 use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use crate::query::{query_fulfilled_txs, query_pending_txs};
-use crate::state::{State, FULFILL_REPLY_STATES, STATE};
+use crate::outbound::{
+    attest_withdrawal, claim_withdrawal, confirm_withdrawal, refund_withdrawal, request_withdrawal,
+};
+use crate::query::{
+    query_attestation_config, query_attestation_conflicts, query_check_invariants, query_config,
+    query_denom, query_denoms, query_error_codes, query_fulfilled_txs, query_owner_action_digest,
+    query_pending_txs, query_pending_txs_page, query_posters, query_signed_order_digest,
+    query_signer_set, query_simulate_fulfill, query_spv_status, query_stats, query_treasury,
+    query_tx_attestations, query_txs_by_destination, query_txs_by_fulfiller, query_withdrawal,
+    query_withdrawals, query_withdrawals_by_owner,
+};
+use crate::roles::{remove_poster, set_poster};
+use crate::signatures::update_signer_set;
+use crate::snapshot::{export_state, import_state};
+use crate::spv::{configure_spv, settle_with_proof, submit_headers};
+use crate::state::{
+    Config, State, CONFIG, DEFAULT_OUTBOUND_TIMEOUT_SECONDS, FULFILL_REPLY_STATES, IMPORT_OPEN,
+    STATE,
+};
+use crate::tokenfactory::create_bridged_denom;
+use crate::treasury::{cancel_fee_withdrawal, execute_fee_withdrawal, withdraw_fees};
 
-pub const FULFILL_ID: u64 = 1u64;
 const CONTRACT_NAME: &str = "crates.io:catalyst";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+// Roughly one week of blocks at 6 second block times
+const DEFAULT_HISTORY_RETENTION_BLOCKS: u64 = 100_800;
+const DEFAULT_WITHDRAWAL_TIMELOCK_SECONDS: u64 = 86_400;
 
 /// Handling contract instantiation
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -34,6 +64,37 @@ pub fn instantiate(
             next_id: 1,
         },
     )?;
+    let protocol_fee_bps = msg.protocol_fee_bps.unwrap_or_default();
+    if protocol_fee_bps >= BPS_DENOMINATOR {
+        return Err(ContractError::InvalidProtocolFee {
+            bps: protocol_fee_bps,
+        });
+    }
+    let admin = match msg.admin {
+        Some(admin) => deps.api.addr_validate(&admin)?,
+        None => info.sender.clone(),
+    };
+    let destination_routes = msg.destination_routes.unwrap_or_default();
+    validate_routes(deps.as_ref(), &destination_routes)?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            admin,
+            history_retention_blocks: msg
+                .history_retention_blocks
+                .unwrap_or(DEFAULT_HISTORY_RETENTION_BLOCKS),
+            protocol_fee_bps,
+            withdrawal_timelock_seconds: msg
+                .withdrawal_timelock_seconds
+                .unwrap_or(DEFAULT_WITHDRAWAL_TIMELOCK_SECONDS),
+            foreign_destination_formats: msg.foreign_destination_formats.unwrap_or_default(),
+            outbound_timeout_seconds: msg
+                .outbound_timeout_seconds
+                .unwrap_or(DEFAULT_OUTBOUND_TIMEOUT_SECONDS),
+            destination_routes,
+        },
+    )?;
+    IMPORT_OPEN.save(deps.storage, &true)?;
 
     // With `Response` type, it is possible to dispatch message to invoke external logic.
     // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
@@ -46,20 +107,40 @@ pub fn instantiate(
 /// To make a contract migratable, you need
 /// - this entry_point implemented
 /// - only contract admin can migrate, so admin has to be set at contract initiation time
-/// Handling contract execution
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(_deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
     match msg {
         // Find matched incoming message variant and execute them with your custom logic.
         //
         // With `Response` type, it is possible to dispatch message to invoke external logic.
         // See: https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#dispatching-messages
+        MigrateMsg::ImportState { export } => import_state(deps, env, export),
     }
 }
 
 /// Handling contract execution
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    #[cfg(debug_assertions)]
+    let invariants_env = env.clone();
+
+    let response = execute_msg(deps.branch(), env, info, msg)?;
+
+    // Debug builds verify the core invariants still hold after every execute
+    #[cfg(debug_assertions)]
+    assert_invariants(deps.as_ref(), &invariants_env)?;
+
+    Ok(response)
+}
+
+pub(crate) fn execute_msg(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
@@ -69,37 +150,194 @@ pub fn execute(
         ExecuteMsg::AddTx {
             destination_addr,
             output_coin,
-        } => add_pending_tx(deps, env, info, destination_addr, output_coin),
+            origin,
+            owner,
+            signatures,
+        } => add_pending_tx(
+            deps,
+            env,
+            info,
+            destination_addr,
+            output_coin,
+            origin,
+            owner,
+            signatures,
+        ),
         ExecuteMsg::FulfillTx { tx_id } => fulfill_pending_tx(deps, env, info, tx_id),
+        ExecuteMsg::FulfillTxs { tx_ids } => fulfill_pending_txs(deps, env, info, tx_ids),
         ExecuteMsg::RemoveTx { tx_id } => remove_pending_tx(deps, env, info, tx_id),
+        ExecuteMsg::RemoveFulfilledTx { tx_id } => remove_fulfilled_tx(deps, env, info, tx_id),
+        ExecuteMsg::CancelTx { tx_id, signature } => {
+            cancel_pending_tx(deps, env, info, tx_id, signature)
+        }
+        ExecuteMsg::AmendTx {
+            tx_id,
+            destination_addr,
+            signature,
+        } => amend_pending_tx(deps, env, info, tx_id, destination_addr, signature),
+        ExecuteMsg::UpdateConfig {
+            admin,
+            history_retention_blocks,
+            protocol_fee_bps,
+            withdrawal_timelock_seconds,
+            foreign_destination_formats,
+            outbound_timeout_seconds,
+            destination_routes,
+        } => update_config(
+            deps,
+            env,
+            info,
+            admin,
+            history_retention_blocks,
+            protocol_fee_bps,
+            withdrawal_timelock_seconds,
+            foreign_destination_formats,
+            outbound_timeout_seconds,
+            destination_routes,
+        ),
+        ExecuteMsg::WithdrawFees { to, amount } => withdraw_fees(deps, env, info, to, amount),
+        ExecuteMsg::ExecuteFeeWithdrawal { id } => execute_fee_withdrawal(deps, env, info, id),
+        ExecuteMsg::CancelFeeWithdrawal { id } => cancel_fee_withdrawal(deps, env, info, id),
+        ExecuteMsg::SetPoster { address, scope } => set_poster(deps, env, info, address, scope),
+        ExecuteMsg::SetDenom { denom, metadata } => set_denom(deps, env, info, denom, metadata),
+        ExecuteMsg::RemoveDenom { denom } => remove_denom(deps, env, info, denom),
+        ExecuteMsg::CreateBridgedDenom { subdenom, metadata } => {
+            create_bridged_denom(deps, env, info, subdenom, metadata)
+        }
+        ExecuteMsg::RemovePoster { address } => remove_poster(deps, env, info, address),
+        ExecuteMsg::ConfigureAttestation {
+            attesters,
+            threshold,
+        } => configure_attestation(deps, env, info, attesters, threshold),
+        ExecuteMsg::AttestTx {
+            destination_addr,
+            output_coin,
+            origin,
+            owner,
+        } => attest_tx(
+            deps,
+            env,
+            info,
+            destination_addr,
+            output_coin,
+            origin,
+            owner,
+        ),
+        ExecuteMsg::UpdateSignerSet { signers, threshold } => {
+            update_signer_set(deps, env, info, signers, threshold)
+        }
+        ExecuteMsg::ConfigureSpv { config, anchor } => {
+            configure_spv(deps, env, info, config, anchor)
+        }
+        ExecuteMsg::SubmitHeaders { headers } => submit_headers(deps, env, info, headers),
+        ExecuteMsg::SettleWithProof {
+            tx_id,
+            headers,
+            block_hash,
+            raw_tx,
+            tx_index,
+            merkle_proof,
+        } => settle_with_proof(
+            deps,
+            env,
+            info,
+            tx_id,
+            headers,
+            block_hash,
+            raw_tx,
+            tx_index,
+            merkle_proof,
+        ),
+        ExecuteMsg::AttestSettlement { tx_id } => attest_settlement(deps, env, info, tx_id),
+        ExecuteMsg::RequestWithdrawal {
+            destination_chain,
+            destination_addr,
+        } => request_withdrawal(deps, env, info, destination_chain, destination_addr),
+        ExecuteMsg::ClaimWithdrawal { id, payout } => claim_withdrawal(deps, env, info, id, payout),
+        ExecuteMsg::ConfirmWithdrawal { id, maker } => {
+            confirm_withdrawal(deps, env, info, id, maker)
+        }
+        ExecuteMsg::AttestWithdrawal { id, maker } => attest_withdrawal(deps, env, info, id, maker),
+        ExecuteMsg::RefundWithdrawal { id } => refund_withdrawal(deps, env, info, id),
     }
 }
 
 /// Handling contract query
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetPendingTxs {} => query_pending_txs(deps),
         QueryMsg::GetFulfilledTxs {} => query_fulfilled_txs(deps),
+        QueryMsg::PendingTxs { start_after, limit } => {
+            query_pending_txs_page(deps, start_after, limit)
+        }
+        QueryMsg::Stats {} => query_stats(deps),
+        QueryMsg::Config {} => query_config(deps),
+        QueryMsg::Treasury {} => query_treasury(deps, env),
+        QueryMsg::CheckInvariants {} => query_check_invariants(deps, env),
+        QueryMsg::Denoms { start_after, limit } => query_denoms(deps, start_after, limit),
+        QueryMsg::Denom { denom } => query_denom(deps, denom),
+        QueryMsg::Posters { start_after, limit } => query_posters(deps, start_after, limit),
+        QueryMsg::AttestationConfig {} => query_attestation_config(deps),
+        QueryMsg::SpvStatus {} => query_spv_status(deps),
+        QueryMsg::SignerSet {} => query_signer_set(deps),
+        QueryMsg::SignedOrderDigest {
+            destination_addr,
+            output_coin,
+            origin,
+            owner,
+        } => query_signed_order_digest(deps, env, destination_addr, output_coin, origin, owner),
+        QueryMsg::OwnerActionDigest { tx_id, action } => {
+            query_owner_action_digest(deps, env, tx_id, action)
+        }
+        QueryMsg::TxAttestations { origin } => query_tx_attestations(deps, origin),
+        QueryMsg::AttestationConflicts { start_after, limit } => {
+            query_attestation_conflicts(deps, start_after, limit)
+        }
+        QueryMsg::TxsByFulfiller {
+            fulfiller,
+            start_after,
+            limit,
+        } => query_txs_by_fulfiller(deps, fulfiller, start_after, limit),
+        QueryMsg::TxsByDestination {
+            destination,
+            start_after,
+            limit,
+        } => query_txs_by_destination(deps, destination, start_after, limit),
+        QueryMsg::Withdrawal { id } => query_withdrawal(deps, id),
+        QueryMsg::ExportState { start_after, limit } => {
+            to_json_binary(&export_state(deps, &env, start_after, limit)?)
+        }
+        QueryMsg::Withdrawals { start_after, limit } => query_withdrawals(deps, start_after, limit),
+        QueryMsg::WithdrawalsByOwner {
+            owner,
+            start_after,
+            limit,
+        } => query_withdrawals_by_owner(deps, owner, start_after, limit),
+        QueryMsg::SimulateFulfill {
+            tx_ids,
+            sender,
+            funds,
+        } => query_simulate_fulfill(deps, env, tx_ids, sender, funds),
+        QueryMsg::ErrorCodes {} => query_error_codes(),
     }
 }
 
 /// Handling submessage reply.
 /// For more info on submessage and reply, see https://github.com/CosmWasm/cosmwasm/blob/main/SEMANTICS.md#submessages
-#[cfg_attr(not(feature = "imported"), entry_point)]
+#[cfg_attr(not(feature = "library"), entry_point)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     deps.api
         .debug(&format!("executing bank send reply: {msg:?}"));
 
-    // If the message is a fulfill message, then we need to move the pending tx to fulfilled tx.
-    if msg.id == FULFILL_ID {
-        let msg_clone = msg.clone();
-
-        let fulfill_reply_state = FULFILL_REPLY_STATES.load(deps.storage, msg_clone.id)?;
-        FULFILL_REPLY_STATES.remove(deps.storage, msg_clone.id);
-
-        move_pending_tx_to_fulfilled_tx(deps, env, msg, fulfill_reply_state)
-    } else {
-        Ok(Response::new())
+    // Fulfill submessages are dispatched with the tx id as their reply id, so a stored
+    // fulfill state means the bank send for that tx succeeded and it can be moved to the
+    // fulfilled txs.
+    match FULFILL_REPLY_STATES.may_load(deps.storage, msg.id)? {
+        Some(fulfill_reply_state) => {
+            FULFILL_REPLY_STATES.remove(deps.storage, msg.id);
+            move_pending_tx_to_fulfilled_tx(deps, env, msg, fulfill_reply_state)
+        }
+        None => Ok(Response::new()),
     }
 }
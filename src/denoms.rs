@@ -0,0 +1,266 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Storage};
+
+use crate::state::{DenomMetadata, SettlementMode, State, CONFIG, DENOM_REGISTRY, STATE};
+use crate::tokenfactory::contract_mints;
+use crate::ContractError;
+
+// ensure_denom_enabled checks orders are currently accepted in the denom
+pub fn ensure_denom_enabled(storage: &dyn Storage, denom: &str) -> Result<(), ContractError> {
+    match DENOM_REGISTRY.may_load(storage, denom)? {
+        Some(metadata) if metadata.enabled => Ok(()),
+        Some(_) => Err(ContractError::DenomDisabled {
+            denom: denom.to_string(),
+        }),
+        None => Err(ContractError::DenomNotRegistered {
+            denom: denom.to_string(),
+        }),
+    }
+}
+
+// settlement_mode returns how the denom is paid out, unregistered denoms being paid out by
+// the module account
+pub fn settlement_mode(
+    storage: &dyn Storage,
+    denom: &str,
+) -> Result<SettlementMode, ContractError> {
+    Ok(DENOM_REGISTRY
+        .may_load(storage, denom)?
+        .map(|metadata| metadata.settlement)
+        .unwrap_or_default())
+}
+
+// ensure_no_open_txs checks no pending or fulfilled tx is placed in the denom
+fn ensure_no_open_txs(storage: &dyn Storage, denom: &str) -> Result<(), ContractError> {
+    let state: State = STATE.load(storage)?;
+    if state
+        .pending_txs
+        .iter()
+        .chain(state.fulfilled_txs.iter())
+        .any(|tx| tx.coin.denom == denom)
+    {
+        return Err(ContractError::DenomInUse {
+            denom: denom.to_string(),
+        });
+    }
+    Ok(())
+}
+
+// set_denom is called by the admin to register a denom or update its metadata. Only
+// bridged denoms created by the contract can be settled by minting, and the settlement
+// mode can't change while txs in the denom are open.
+pub fn set_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: String,
+    metadata: DenomMetadata,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if metadata.settlement == SettlementMode::Mint && !contract_mints(&env, &denom) {
+        return Err(ContractError::DenomNotMintable { denom });
+    }
+    let previous = DENOM_REGISTRY.may_load(deps.storage, &denom)?;
+    if previous.is_some_and(|previous| previous.settlement != metadata.settlement) {
+        ensure_no_open_txs(deps.storage, &denom)?;
+    }
+
+    DENOM_REGISTRY.save(deps.storage, &denom, &metadata)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_denom")
+        .add_attribute("denom", denom)
+        .add_attribute("symbol", metadata.symbol)
+        .add_attribute("enabled", metadata.enabled.to_string()))
+}
+
+// remove_denom is called by the admin to unregister a denom once no tx placed in it is
+// still open.
+pub fn remove_denom(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    denom: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !DENOM_REGISTRY.has(deps.storage, &denom) {
+        return Err(ContractError::DenomNotRegistered { denom });
+    }
+    ensure_no_open_txs(deps.storage, &denom)?;
+    DENOM_REGISTRY.remove(deps.storage, &denom);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_denom")
+        .add_attribute("denom", denom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{add_pending_tx, remove_pending_tx};
+    use crate::testing::mock_config;
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+    fn btc_metadata(enabled: bool) -> DenomMetadata {
+        DenomMetadata {
+            symbol: "BTC".to_string(),
+            decimals: 8,
+            origin_chain: "bitcoin".to_string(),
+            enabled,
+            settlement: SettlementMode::Module,
+        }
+    }
+
+    #[test]
+    fn test_denom_registry() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        STATE
+            .save(
+                deps.as_mut().storage,
+                &State {
+                    module_account: "module_account".to_string(),
+                    pending_txs: vec![],
+                    fulfilled_txs: vec![],
+                    next_id: 0,
+                },
+            )
+            .unwrap();
+
+        let err = ensure_denom_enabled(deps.as_ref().storage, "ubtc").unwrap_err();
+        assert!(matches!(err, ContractError::DenomNotRegistered { .. }));
+
+        // Only the admin manages the registry
+        let err = set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "ubtc".to_string(),
+            btc_metadata(true),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+            btc_metadata(true),
+        )
+        .unwrap();
+        ensure_denom_enabled(deps.as_ref().storage, "ubtc").unwrap();
+
+        // Disabled denoms stay registered but reject new orders
+        set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+            btc_metadata(false),
+        )
+        .unwrap();
+        let err = ensure_denom_enabled(deps.as_ref().storage, "ubtc").unwrap_err();
+        assert!(matches!(err, ContractError::DenomDisabled { .. }));
+
+        // Only denoms the contract created can be settled by minting
+        let mint = DenomMetadata {
+            settlement: SettlementMode::Mint,
+            ..btc_metadata(true)
+        };
+        let err = set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+            mint.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomNotMintable { .. }));
+        set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            format!("factory/{}/btc", env.contract.address),
+            mint,
+        )
+        .unwrap();
+        assert_eq!(
+            DENOM_REGISTRY.load(deps.as_ref().storage, "ubtc").unwrap(),
+            btc_metadata(false)
+        );
+
+        // The settlement mode is fixed and the denom stays registered while txs are open
+        set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+            btc_metadata(true),
+        )
+        .unwrap();
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let forward = DenomMetadata {
+            settlement: SettlementMode::Forward,
+            ..btc_metadata(true)
+        };
+        let err = set_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+            forward,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomInUse { .. }));
+        let err = remove_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomInUse { .. }));
+
+        remove_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+        )
+        .unwrap();
+        remove_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+        )
+        .unwrap();
+        let err = remove_denom(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            "ubtc".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomNotRegistered { .. }));
+    }
+}
@@ -0,0 +1,208 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_binary, BankMsg, Coin, CosmosMsg, Deps, WasmMsg};
+
+use crate::state::{DestinationFormat, DestinationRoute, RouteAction, CONFIG};
+use crate::ContractError;
+
+// BridgeExecuteMsg is the message a connecting bridge contract is called with to send the
+// attached funds on to a destination on its chain
+#[cw_serde]
+pub enum BridgeExecuteMsg {
+    Transfer { destination_addr: String },
+}
+
+// Payout is the message delivering funds to a destination, and the route it takes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Payout {
+    pub route: &'static str,
+    pub msg: CosmosMsg,
+}
+
+impl DestinationFormat {
+    // matches returns whether the address is well formed in this format
+    pub fn matches(&self, address: &str) -> bool {
+        match self {
+            DestinationFormat::Bech32 { prefix } => {
+                bech32::decode(address).is_ok_and(|(hrp, _, _)| hrp.eq_ignore_ascii_case(prefix))
+            }
+            DestinationFormat::Hex { prefix, length } => {
+                address.strip_prefix(prefix.as_str()).is_some_and(|hex| {
+                    hex.len() == 2 * *length as usize && hex.chars().all(|c| c.is_ascii_hexdigit())
+                })
+            }
+        }
+    }
+}
+
+// validate_destination checks a destination is an account on this chain, or matches one
+// of the destination routes in the config. Destinations the contract has no way to pay out
+// to are rejected on intake rather than failing when a market maker fills the tx.
+pub fn validate_destination(deps: Deps, destination_addr: &str) -> Result<(), ContractError> {
+    let err = match deps.api.addr_validate(destination_addr) {
+        Ok(_) => return Ok(()),
+        Err(err) => err,
+    };
+
+    let config = CONFIG.load(deps.storage)?;
+    if config
+        .destination_routes
+        .iter()
+        .any(|route| route.format.matches(destination_addr))
+    {
+        return Ok(());
+    }
+
+    Err(ContractError::InvalidDestination {
+        address: destination_addr.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+// validate_routes checks the bridge contracts of the destination routes are accounts on this
+// chain
+pub fn validate_routes(deps: Deps, routes: &[DestinationRoute]) -> Result<(), ContractError> {
+    for route in routes {
+        let RouteAction::Bridge { contract } = &route.action;
+        deps.api.addr_validate(contract)?;
+    }
+    Ok(())
+}
+
+// payout builds the message delivering `coin` to a destination: through the first destination
+// route matching it, or else a bank send to an account on this chain. Fills and forwarded
+// expiries both deliver funds through here.
+pub fn payout(deps: Deps, destination_addr: &str, coin: Coin) -> Result<Payout, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let route = config
+        .destination_routes
+        .iter()
+        .find(|route| route.format.matches(destination_addr));
+    let Some(route) = route else {
+        deps.api.addr_validate(destination_addr).map_err(|_| {
+            ContractError::NoDestinationRoute {
+                address: destination_addr.to_string(),
+            }
+        })?;
+        return Ok(Payout {
+            route: "bank",
+            msg: BankMsg::Send {
+                to_address: destination_addr.to_string(),
+                amount: vec![coin],
+            }
+            .into(),
+        });
+    };
+    Ok(match &route.action {
+        RouteAction::Bridge { contract } => Payout {
+            route: "bridge",
+            msg: WasmMsg::Execute {
+                contract_addr: contract.clone(),
+                msg: to_json_binary(&BridgeExecuteMsg::Transfer {
+                    destination_addr: destination_addr.to_string(),
+                })?,
+                funds: vec![coin],
+            }
+            .into(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::mock_config;
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::mock_dependencies;
+
+    #[test]
+    fn test_payout_routes() {
+        let mut deps = mock_dependencies();
+        let mut config = mock_config();
+        config.destination_routes = vec![DestinationRoute {
+            format: DestinationFormat::Hex {
+                prefix: "0x".to_string(),
+                length: 2,
+            },
+            action: RouteAction::Bridge {
+                contract: "bridge".to_string(),
+            },
+        }];
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        let bank = payout(deps.as_ref(), "destination_addr", coin(10, "token")).unwrap();
+        assert_eq!(bank.route, "bank");
+
+        let bridge = payout(deps.as_ref(), "0xABCD", coin(10, "token")).unwrap();
+        assert_eq!(bridge.route, "bridge");
+        assert_eq!(
+            bridge.msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "bridge".to_string(),
+                msg: to_json_binary(&BridgeExecuteMsg::Transfer {
+                    destination_addr: "0xABCD".to_string(),
+                })
+                .unwrap(),
+                funds: vec![coin(10, "token")],
+            })
+        );
+
+        let err = payout(deps.as_ref(), "0xABCDEF", coin(10, "token")).unwrap_err();
+        assert!(matches!(err, ContractError::NoDestinationRoute { .. }));
+    }
+
+    #[test]
+    fn test_validate_destination() {
+        let mut deps = mock_dependencies();
+        let mut config = mock_config();
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        validate_destination(deps.as_ref(), "destination_addr").unwrap();
+
+        // The mock api only accepts lowercase addresses
+        let bech32 = "A12UEL5L";
+        let hex = "0xABCDEF0123456789ABCDEF0123456789ABCDEF01";
+        for address in [bech32, hex, "ab"] {
+            let err = validate_destination(deps.as_ref(), address).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidDestination { .. }));
+        }
+
+        // Foreign formats without a route are only accepted for outbound withdrawals
+        config.foreign_destination_formats = vec![DestinationFormat::Hex {
+            prefix: "0x".to_string(),
+            length: 20,
+        }];
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let err = validate_destination(deps.as_ref(), hex).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDestination { .. }));
+
+        let route = |format| DestinationRoute {
+            format,
+            action: RouteAction::Bridge {
+                contract: "bridge".to_string(),
+            },
+        };
+        config.destination_routes = vec![
+            route(DestinationFormat::Bech32 {
+                prefix: "a".to_string(),
+            }),
+            route(DestinationFormat::Hex {
+                prefix: "0x".to_string(),
+                length: 20,
+            }),
+        ];
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+
+        validate_destination(deps.as_ref(), bech32).unwrap();
+        validate_destination(deps.as_ref(), hex).unwrap();
+
+        // The bech32 checksum, the prefix and the hex length must all match
+        for address in [
+            "A12UEL5M",
+            "ABCDEF1QPZRY9X8GF2TVDW0S3JN54KHCE6MUA7LMQQQXW",
+            "0xABCDEF0123456789ABCDEF0123456789ABCDEF",
+        ] {
+            let err = validate_destination(deps.as_ref(), address).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidDestination { .. }));
+        }
+    }
+}
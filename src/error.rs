@@ -1,20 +1,348 @@
-use cosmwasm_std::StdError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdError, Timestamp, Uint128, VerificationError};
 use thiserror::Error;
 
+use crate::execute::TxStatus;
+
+// ErrorCode identifies each error with a stable number, reported at the start of its
+// message as `E<code>:` so clients can branch on it. Codes are grouped by area and never
+// reused once assigned.
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum ErrorCode {
+    Std = 100,
+    Unauthorized = 101,
+    TransactionNotFound = 102,
+    MultipleCoinError = 103,
+    WrongDenom = 104,
+    InsufficientFunds = 105,
+    ExcessFunds = 106,
+    InvalidStatus = 107,
+    FillStarted = 108,
+    InvalidDestination = 109,
+    Verification = 110,
+    NoDestinationRoute = 111,
+    FundsNotAccepted = 112,
+    InvalidProtocolFee = 200,
+    InsufficientTreasury = 201,
+    FeeWithdrawalNotFound = 202,
+    FeeWithdrawalLocked = 203,
+    PosterNotFound = 204,
+    DenomNotRegistered = 205,
+    DenomAlreadyRegistered = 206,
+    DenomDisabled = 207,
+    DenomNotMintable = 208,
+    DenomInUse = 209,
+    OriginAlreadyProcessed = 300,
+    AttestationRequired = 301,
+    AttestationDisabled = 302,
+    AlreadyAttested = 303,
+    InvalidAttestationThreshold = 304,
+    SignerSetNotConfigured = 305,
+    UnknownSigner = 306,
+    InvalidSignature = 307,
+    InvalidPubkey = 308,
+    InsufficientSignatures = 309,
+    InvalidSignerThreshold = 310,
+    OriginRequired = 311,
+    SpvNotConfigured = 400,
+    SpvAlreadyAnchored = 401,
+    InvalidBlockHeader = 402,
+    UnknownBlock = 403,
+    InsufficientConfirmations = 404,
+    InvalidMerkleProof = 405,
+    OriginMismatch = 406,
+    WithdrawalNotFound = 500,
+    WithdrawalNotOpen = 501,
+    WithdrawalExpired = 502,
+    WithdrawalNotExpired = 503,
+    WithdrawalNotClaimed = 504,
+    InvalidPayout = 505,
+    WithdrawalClaimPending = 506,
+    UnsupportedExportVersion = 600,
+    ImportConflict = 601,
+    InvariantViolation = 602,
+    ImportClosed = 603,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 53] = [
+        ErrorCode::Std,
+        ErrorCode::Unauthorized,
+        ErrorCode::TransactionNotFound,
+        ErrorCode::MultipleCoinError,
+        ErrorCode::WrongDenom,
+        ErrorCode::InsufficientFunds,
+        ErrorCode::ExcessFunds,
+        ErrorCode::InvalidStatus,
+        ErrorCode::FillStarted,
+        ErrorCode::InvalidDestination,
+        ErrorCode::Verification,
+        ErrorCode::NoDestinationRoute,
+        ErrorCode::FundsNotAccepted,
+        ErrorCode::InvalidProtocolFee,
+        ErrorCode::InsufficientTreasury,
+        ErrorCode::FeeWithdrawalNotFound,
+        ErrorCode::FeeWithdrawalLocked,
+        ErrorCode::PosterNotFound,
+        ErrorCode::DenomNotRegistered,
+        ErrorCode::DenomAlreadyRegistered,
+        ErrorCode::DenomDisabled,
+        ErrorCode::DenomNotMintable,
+        ErrorCode::DenomInUse,
+        ErrorCode::OriginAlreadyProcessed,
+        ErrorCode::AttestationRequired,
+        ErrorCode::AttestationDisabled,
+        ErrorCode::AlreadyAttested,
+        ErrorCode::InvalidAttestationThreshold,
+        ErrorCode::SignerSetNotConfigured,
+        ErrorCode::UnknownSigner,
+        ErrorCode::InvalidSignature,
+        ErrorCode::InvalidPubkey,
+        ErrorCode::InsufficientSignatures,
+        ErrorCode::InvalidSignerThreshold,
+        ErrorCode::OriginRequired,
+        ErrorCode::SpvNotConfigured,
+        ErrorCode::SpvAlreadyAnchored,
+        ErrorCode::InvalidBlockHeader,
+        ErrorCode::UnknownBlock,
+        ErrorCode::InsufficientConfirmations,
+        ErrorCode::InvalidMerkleProof,
+        ErrorCode::OriginMismatch,
+        ErrorCode::WithdrawalNotFound,
+        ErrorCode::WithdrawalNotOpen,
+        ErrorCode::WithdrawalExpired,
+        ErrorCode::WithdrawalNotExpired,
+        ErrorCode::WithdrawalNotClaimed,
+        ErrorCode::InvalidPayout,
+        ErrorCode::WithdrawalClaimPending,
+        ErrorCode::UnsupportedExportVersion,
+        ErrorCode::ImportConflict,
+        ErrorCode::InvariantViolation,
+        ErrorCode::ImportClosed,
+    ];
+}
+
 #[derive(Error, Debug)]
 pub enum ContractError {
-    #[error("{0}")]
+    #[error("E{code}: {0}", code = ErrorCode::Std as u32)]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
+    #[error("E{code}: Unauthorized", code = ErrorCode::Unauthorized as u32)]
     Unauthorized {},
 
-    #[error("Custom Error val: {val:?}")]
-    CustomError { val: String },
-
-    #[error("Transaction not found: {id:}")]
+    #[error("E{code}: Transaction not found: {id:}", code = ErrorCode::TransactionNotFound as u32)]
     TransactionNotFound { id: u64 },
 
-    #[error("Only single coin authorized, got: {num_coins:}")]
+    #[error("E{code}: Only single coin authorized, got: {num_coins:}", code = ErrorCode::MultipleCoinError as u32)]
     MultipleCoinError { num_coins: usize },
+
+    #[error("E{code}: Wrong denom, expected {expected:}, got {got:}", code = ErrorCode::WrongDenom as u32)]
+    WrongDenom { expected: String, got: String },
+
+    #[error("E{code}: Insufficient funds, {required:} required, {sent:} sent", code = ErrorCode::InsufficientFunds as u32)]
+    InsufficientFunds { required: Uint128, sent: Uint128 },
+
+    #[error("E{code}: Excess funds, {required:} required, {sent:} sent", code = ErrorCode::ExcessFunds as u32)]
+    ExcessFunds { required: Uint128, sent: Uint128 },
+
+    #[error("E{code}: Tx {id:} is {status:}", code = ErrorCode::InvalidStatus as u32)]
+    InvalidStatus { id: u64, status: TxStatus },
+
+    #[error("E{code}: Protocol fee must be below 10000 bps, got: {bps:}", code = ErrorCode::InvalidProtocolFee as u32)]
+    InvalidProtocolFee { bps: u64 },
+
+    #[error("E{code}: Insufficient treasury balance: {denom:}", code = ErrorCode::InsufficientTreasury as u32)]
+    InsufficientTreasury { denom: String },
+
+    #[error("E{code}: Fee withdrawal not found: {id:}", code = ErrorCode::FeeWithdrawalNotFound as u32)]
+    FeeWithdrawalNotFound { id: u64 },
+
+    #[error("E{code}: Fee withdrawal {id:} is locked until {release_at:}", code = ErrorCode::FeeWithdrawalLocked as u32)]
+    FeeWithdrawalLocked { id: u64, release_at: Timestamp },
+
+    #[error("E{code}: Poster not found: {address:}", code = ErrorCode::PosterNotFound as u32)]
+    PosterNotFound { address: String },
+
+    #[error("E{code}: Origin already processed: {origin:}", code = ErrorCode::OriginAlreadyProcessed as u32)]
+    OriginAlreadyProcessed { origin: String },
+
+    #[error("E{code}: Attestation is required while attestation mode is enabled", code = ErrorCode::AttestationRequired as u32)]
+    AttestationRequired {},
+
+    #[error("E{code}: Attestation mode is not enabled", code = ErrorCode::AttestationDisabled as u32)]
+    AttestationDisabled {},
+
+    #[error("E{code}: Already attested: {key:}", code = ErrorCode::AlreadyAttested as u32)]
+    AlreadyAttested { key: String },
+
+    #[error("E{code}: Attestation threshold {threshold:} exceeds the number of attesters {attesters:}", code = ErrorCode::InvalidAttestationThreshold as u32)]
+    InvalidAttestationThreshold { threshold: u32, attesters: u32 },
+
+    #[error("E{code}: Signed orders are not enabled", code = ErrorCode::SignerSetNotConfigured as u32)]
+    SignerSetNotConfigured {},
+
+    #[error("E{code}: Unknown signer: {pubkey:}", code = ErrorCode::UnknownSigner as u32)]
+    UnknownSigner { pubkey: String },
+
+    #[error("E{code}: Invalid signature from: {pubkey:}", code = ErrorCode::InvalidSignature as u32)]
+    InvalidSignature { pubkey: String },
+
+    #[error("E{code}: Invalid public key: {pubkey:}", code = ErrorCode::InvalidPubkey as u32)]
+    InvalidPubkey { pubkey: String },
+
+    #[error("E{code}: Got {signatures:} valid signatures, {threshold:} required", code = ErrorCode::InsufficientSignatures as u32)]
+    InsufficientSignatures { threshold: u32, signatures: u32 },
+
+    #[error("E{code}: Signature threshold {threshold:} exceeds the number of signers {signers:}", code = ErrorCode::InvalidSignerThreshold as u32)]
+    InvalidSignerThreshold { threshold: u32, signers: u32 },
+
+    #[error("E{code}: Signed orders must include their origin deposit", code = ErrorCode::OriginRequired as u32)]
+    OriginRequired {},
+
+    #[error("E{code}: {0}", code = ErrorCode::Verification as u32)]
+    Verification(#[from] VerificationError),
+
+    #[error("E{code}: Denom not registered: {denom:}", code = ErrorCode::DenomNotRegistered as u32)]
+    DenomNotRegistered { denom: String },
+
+    #[error("E{code}: Denom already registered: {denom:}", code = ErrorCode::DenomAlreadyRegistered as u32)]
+    DenomAlreadyRegistered { denom: String },
+
+    #[error("E{code}: Denom disabled: {denom:}", code = ErrorCode::DenomDisabled as u32)]
+    DenomDisabled { denom: String },
+
+    #[error("E{code}: Denom {denom:} is not a bridged denom the contract can mint", code = ErrorCode::DenomNotMintable as u32)]
+    DenomNotMintable { denom: String },
+
+    #[error("E{code}: Denom {denom:} has open txs", code = ErrorCode::DenomInUse as u32)]
+    DenomInUse { denom: String },
+
+    #[error("E{code}: Invalid destination {address:}: {reason:}", code = ErrorCode::InvalidDestination as u32)]
+    InvalidDestination { address: String, reason: String },
+
+    #[error("E{code}: No route delivers funds to {address:}", code = ErrorCode::NoDestinationRoute as u32)]
+    NoDestinationRoute { address: String },
+
+    #[error("E{code}: Funds are not accepted with this message", code = ErrorCode::FundsNotAccepted as u32)]
+    FundsNotAccepted {},
+
+    #[error("E{code}: Tx {id:} can no longer be changed once a fill has started", code = ErrorCode::FillStarted as u32)]
+    FillStarted { id: u64 },
+
+    #[error("E{code}: SPV settlement is not configured", code = ErrorCode::SpvNotConfigured as u32)]
+    SpvNotConfigured {},
+
+    #[error("E{code}: The SPV header chain is already anchored", code = ErrorCode::SpvAlreadyAnchored as u32)]
+    SpvAlreadyAnchored {},
+
+    #[error("E{code}: Invalid block header: {reason:}", code = ErrorCode::InvalidBlockHeader as u32)]
+    InvalidBlockHeader { reason: String },
+
+    #[error("E{code}: Block not found on the best chain: {hash:}", code = ErrorCode::UnknownBlock as u32)]
+    UnknownBlock { hash: String },
+
+    #[error("E{code}: Block has {confirmations:} confirmations, {required:} required", code = ErrorCode::InsufficientConfirmations as u32)]
+    InsufficientConfirmations { confirmations: u64, required: u32 },
+
+    #[error("E{code}: Invalid merkle proof: {reason:}", code = ErrorCode::InvalidMerkleProof as u32)]
+    InvalidMerkleProof { reason: String },
+
+    #[error("E{code}: Proof does not match the tx origin: {reason:}", code = ErrorCode::OriginMismatch as u32)]
+    OriginMismatch { reason: String },
+
+    #[error("E{code}: Withdrawal not found: {id:}", code = ErrorCode::WithdrawalNotFound as u32)]
+    WithdrawalNotFound { id: u64 },
+
+    #[error("E{code}: Withdrawal {id:} is no longer open", code = ErrorCode::WithdrawalNotOpen as u32)]
+    WithdrawalNotOpen { id: u64 },
+
+    #[error("E{code}: Withdrawal {id:} expired at {expires_at:}", code = ErrorCode::WithdrawalExpired as u32)]
+    WithdrawalExpired { id: u64, expires_at: Timestamp },
+
+    #[error("E{code}: Withdrawal {id:} cannot be refunded until {expires_at:}", code = ErrorCode::WithdrawalNotExpired as u32)]
+    WithdrawalNotExpired { id: u64, expires_at: Timestamp },
+
+    #[error("E{code}: {maker:} has not claimed withdrawal {id:}", code = ErrorCode::WithdrawalNotClaimed as u32)]
+    WithdrawalNotClaimed { id: u64, maker: String },
+
+    #[error("E{code}: Invalid payout: {reason:}", code = ErrorCode::InvalidPayout as u32)]
+    InvalidPayout { reason: String },
+
+    #[error("E{code}: Withdrawal {id:} has a pending claim and cannot be refunded until {refundable_at:}", code = ErrorCode::WithdrawalClaimPending as u32)]
+    WithdrawalClaimPending { id: u64, refundable_at: Timestamp },
+
+    #[error("E{code}: Unsupported export version: {version:}", code = ErrorCode::UnsupportedExportVersion as u32)]
+    UnsupportedExportVersion { version: u32 },
+
+    #[error("E{code}: Cannot import state: {reason:}", code = ErrorCode::ImportConflict as u32)]
+    ImportConflict { reason: String },
+
+    #[error("E{code}: Invariant violated: {reason:}", code = ErrorCode::InvariantViolation as u32)]
+    InvariantViolation { reason: String },
+
+    #[error("E{code}: Cannot import state: the contract was not freshly instantiated or its import is complete", code = ErrorCode::ImportClosed as u32)]
+    ImportClosed {},
+}
+
+impl ContractError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ContractError::Std(_) => ErrorCode::Std,
+            ContractError::Unauthorized { .. } => ErrorCode::Unauthorized,
+            ContractError::TransactionNotFound { .. } => ErrorCode::TransactionNotFound,
+            ContractError::MultipleCoinError { .. } => ErrorCode::MultipleCoinError,
+            ContractError::WrongDenom { .. } => ErrorCode::WrongDenom,
+            ContractError::InsufficientFunds { .. } => ErrorCode::InsufficientFunds,
+            ContractError::ExcessFunds { .. } => ErrorCode::ExcessFunds,
+            ContractError::InvalidStatus { .. } => ErrorCode::InvalidStatus,
+            ContractError::FillStarted { .. } => ErrorCode::FillStarted,
+            ContractError::InvalidDestination { .. } => ErrorCode::InvalidDestination,
+            ContractError::Verification(_) => ErrorCode::Verification,
+            ContractError::NoDestinationRoute { .. } => ErrorCode::NoDestinationRoute,
+            ContractError::FundsNotAccepted { .. } => ErrorCode::FundsNotAccepted,
+            ContractError::InvalidProtocolFee { .. } => ErrorCode::InvalidProtocolFee,
+            ContractError::InsufficientTreasury { .. } => ErrorCode::InsufficientTreasury,
+            ContractError::FeeWithdrawalNotFound { .. } => ErrorCode::FeeWithdrawalNotFound,
+            ContractError::FeeWithdrawalLocked { .. } => ErrorCode::FeeWithdrawalLocked,
+            ContractError::PosterNotFound { .. } => ErrorCode::PosterNotFound,
+            ContractError::DenomNotRegistered { .. } => ErrorCode::DenomNotRegistered,
+            ContractError::DenomAlreadyRegistered { .. } => ErrorCode::DenomAlreadyRegistered,
+            ContractError::DenomDisabled { .. } => ErrorCode::DenomDisabled,
+            ContractError::DenomNotMintable { .. } => ErrorCode::DenomNotMintable,
+            ContractError::DenomInUse { .. } => ErrorCode::DenomInUse,
+            ContractError::OriginAlreadyProcessed { .. } => ErrorCode::OriginAlreadyProcessed,
+            ContractError::AttestationRequired { .. } => ErrorCode::AttestationRequired,
+            ContractError::AttestationDisabled { .. } => ErrorCode::AttestationDisabled,
+            ContractError::AlreadyAttested { .. } => ErrorCode::AlreadyAttested,
+            ContractError::InvalidAttestationThreshold { .. } => {
+                ErrorCode::InvalidAttestationThreshold
+            }
+            ContractError::SignerSetNotConfigured { .. } => ErrorCode::SignerSetNotConfigured,
+            ContractError::UnknownSigner { .. } => ErrorCode::UnknownSigner,
+            ContractError::InvalidSignature { .. } => ErrorCode::InvalidSignature,
+            ContractError::InvalidPubkey { .. } => ErrorCode::InvalidPubkey,
+            ContractError::InsufficientSignatures { .. } => ErrorCode::InsufficientSignatures,
+            ContractError::InvalidSignerThreshold { .. } => ErrorCode::InvalidSignerThreshold,
+            ContractError::OriginRequired { .. } => ErrorCode::OriginRequired,
+            ContractError::SpvNotConfigured { .. } => ErrorCode::SpvNotConfigured,
+            ContractError::SpvAlreadyAnchored { .. } => ErrorCode::SpvAlreadyAnchored,
+            ContractError::InvalidBlockHeader { .. } => ErrorCode::InvalidBlockHeader,
+            ContractError::UnknownBlock { .. } => ErrorCode::UnknownBlock,
+            ContractError::InsufficientConfirmations { .. } => ErrorCode::InsufficientConfirmations,
+            ContractError::InvalidMerkleProof { .. } => ErrorCode::InvalidMerkleProof,
+            ContractError::OriginMismatch { .. } => ErrorCode::OriginMismatch,
+            ContractError::WithdrawalNotFound { .. } => ErrorCode::WithdrawalNotFound,
+            ContractError::WithdrawalNotOpen { .. } => ErrorCode::WithdrawalNotOpen,
+            ContractError::WithdrawalExpired { .. } => ErrorCode::WithdrawalExpired,
+            ContractError::WithdrawalNotExpired { .. } => ErrorCode::WithdrawalNotExpired,
+            ContractError::WithdrawalNotClaimed { .. } => ErrorCode::WithdrawalNotClaimed,
+            ContractError::InvalidPayout { .. } => ErrorCode::InvalidPayout,
+            ContractError::WithdrawalClaimPending { .. } => ErrorCode::WithdrawalClaimPending,
+            ContractError::UnsupportedExportVersion { .. } => ErrorCode::UnsupportedExportVersion,
+            ContractError::ImportConflict { .. } => ErrorCode::ImportConflict,
+            ContractError::InvariantViolation { .. } => ErrorCode::InvariantViolation,
+            ContractError::ImportClosed { .. } => ErrorCode::ImportClosed,
+        }
+    }
 }
@@ -0,0 +1,65 @@
+use cosmwasm_std::{Addr, Event};
+
+use crate::execute::{Tx, TxStatus};
+use crate::state::Withdrawal;
+
+// Every state transition of a tx emits one of these events. The chain prefixes
+// custom contract events with `wasm-`, so indexers will see e.g. `wasm-catalyst_tx_added`.
+pub const TX_ADDED_EVENT: &str = "catalyst_tx_added";
+pub const TX_FULFILL_SUBMITTED_EVENT: &str = "catalyst_tx_fulfill_submitted";
+pub const TX_FULFILLED_EVENT: &str = "catalyst_tx_fulfilled";
+pub const TX_EXPIRED_EVENT: &str = "catalyst_tx_expired";
+pub const TX_SETTLED_EVENT: &str = "catalyst_tx_settled";
+pub const TX_CANCELLED_EVENT: &str = "catalyst_tx_cancelled";
+pub const TX_AMENDED_EVENT: &str = "catalyst_tx_amended";
+
+// Outbound withdrawals emit their own set of events
+pub const WITHDRAWAL_REQUESTED_EVENT: &str = "catalyst_withdrawal_requested";
+pub const WITHDRAWAL_CLAIMED_EVENT: &str = "catalyst_withdrawal_claimed";
+pub const WITHDRAWAL_PAID_EVENT: &str = "catalyst_withdrawal_paid";
+pub const WITHDRAWAL_REFUNDED_EVENT: &str = "catalyst_withdrawal_refunded";
+
+// tx_event builds the common set of attributes describing a tx and the status it
+// transitioned into. The fulfiller is only included once a market maker is known.
+pub fn tx_event(ty: &str, tx: &Tx, status: TxStatus, fulfiller: Option<&Addr>) -> Event {
+    let event = Event::new(ty)
+        .add_attribute("tx_id", tx.id.to_string())
+        .add_attribute("denom", tx.coin.denom.clone())
+        .add_attribute("amount", tx.coin.amount.to_string())
+        .add_attribute("destination", tx.destination_addr.clone())
+        .add_attribute("status", status.to_string());
+
+    let event = match &tx.origin {
+        Some(origin) => event
+            .add_attribute("origin_chain", origin.chain.clone())
+            .add_attribute("origin_tx_hash", origin.tx_hash.clone())
+            .add_attribute("origin_vout", origin.vout.to_string()),
+        None => event,
+    };
+
+    match fulfiller {
+        Some(fulfiller) => event.add_attribute("fulfiller", fulfiller.to_string()),
+        None => event,
+    }
+}
+
+// withdrawal_event builds the common set of attributes describing an outbound withdrawal.
+// The maker and payout are only included once the withdrawal has been paid.
+pub fn withdrawal_event(ty: &str, withdrawal: &Withdrawal) -> Event {
+    let event = Event::new(ty)
+        .add_attribute("withdrawal_id", withdrawal.id.to_string())
+        .add_attribute("owner", withdrawal.owner.to_string())
+        .add_attribute("denom", withdrawal.coin.denom.clone())
+        .add_attribute("amount", withdrawal.coin.amount.to_string())
+        .add_attribute("destination_chain", withdrawal.destination_chain.clone())
+        .add_attribute("destination", withdrawal.destination_addr.clone())
+        .add_attribute("expires_at", withdrawal.expires_at.seconds().to_string());
+
+    match (&withdrawal.maker, &withdrawal.payout) {
+        (Some(maker), Some(payout)) => event
+            .add_attribute("maker", maker.to_string())
+            .add_attribute("payout_tx_hash", payout.tx_hash.clone())
+            .add_attribute("payout_vout", payout.vout.to_string()),
+        _ => event,
+    }
+}
@@ -1,8 +1,28 @@
-use crate::state::{FulfillState, State, FULFILL_REPLY_STATES, STATE};
+use crate::attestation::{attestation_enabled, clear_tx_attestations};
+use crate::denoms::{ensure_denom_enabled, settlement_mode};
+use crate::destination::{payout, validate_destination, validate_routes, Payout};
+use crate::events::{
+    tx_event, TX_ADDED_EVENT, TX_AMENDED_EVENT, TX_CANCELLED_EVENT, TX_EXPIRED_EVENT,
+    TX_FULFILLED_EVENT, TX_FULFILL_SUBMITTED_EVENT, TX_SETTLED_EVENT,
+};
+use crate::history::record_tx;
+use crate::roles::authorize_poster;
+use crate::signatures::{
+    verify_order_signatures, verify_owner_signature, OrderSignature, OwnerAction,
+};
+use crate::state::{
+    tx_records, DestinationFormat, DestinationRoute, FulfillState, SettlementMode, Signer, State,
+    CONFIG, FULFILL_REPLY_STATES, PROCESSED_ORIGINS, STATE,
+};
+use crate::stats::{record_added, record_cancelled, record_expired, record_filled, record_settled};
+use crate::tokenfactory::{expiry_mint, settlement_mint};
+use crate::treasury::{credit_treasury, load_config, set_withdrawal_timelock};
 use crate::ContractError;
 use cosmwasm_std::{
-    BankMsg, Coin, DepsMut, Env, MessageInfo, Reply, Response, SubMsg, SubMsgResult,
+    Addr, BankMsg, Binary, Coin, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, Storage,
+    SubMsg, SubMsgResult, Uint128,
 };
+use std::fmt;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -12,107 +32,446 @@ pub struct Tx {
     pub id: u64,
     pub destination_addr: String,
     pub coin: Coin,
+    // The block height at which the tx was added to the pending transactions store
+    #[serde(default)]
+    pub added_height: u64,
+    // Set once a market maker has successfully fulfilled the tx
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill: Option<Fill>,
+    // The deposit on the origin chain that funds the tx, if reported by the poster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<TxOrigin>,
+    // The depositor, who may cancel or amend the tx while it is pending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<TxOwner>,
+    // Number of times the owner changed the destination
+    #[serde(default)]
+    pub amendments: u32,
+    // How the bridged funds are paid out, as registered for the denom when the tx was added
+    #[serde(default, skip_serializing_if = "SettlementMode::is_module")]
+    pub settlement: SettlementMode,
+    // The account the cleared funds are paid to on settlement, the market maker once the tx is
+    // fulfilled. `destination_addr` keeps the end user's address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payee: Option<Addr>,
+}
+
+// TxOrigin identifies the deposit output on the origin chain that funds a tx
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TxOrigin {
+    pub chain: String,
+    pub tx_hash: String,
+    pub vout: u32,
 }
 
-// add_pending_tx is called by the module account assigned at instantiation, which
-// adds a pending incoming transaction to the contract's store.
+impl TxOrigin {
+    // key uniquely identifies the deposit output across origin chains
+    pub fn key(&self) -> String {
+        format!("{}/{}/{}", self.chain, self.tx_hash, self.vout)
+    }
+}
+
+// TxOwner identifies the depositor of a tx
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TxOwner {
+    // Where the deposit is refunded if the tx is cancelled, on this chain or the origin chain
+    pub refund_addr: String,
+    // Key authorizing changes for depositors without an account on this chain. Without it,
+    // changes must be sent by the refund address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pubkey: Option<Signer>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Fill {
+    pub fulfiller_addr: Addr,
+    // The amount the market maker provided to fill the tx
+    pub amount: Uint128,
+    pub height: u64,
+}
+
+// Protocol fees are expressed in basis points
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// TxStatus is the status a tx moves into on each state transition, as reported
+// in the emitted events.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Pending,
+    Fulfilling,
+    Fulfilled,
+    Expired,
+    Settled,
+    Cancelled,
+}
+
+impl fmt::Display for TxStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            TxStatus::Pending => "pending",
+            TxStatus::Fulfilling => "fulfilling",
+            TxStatus::Fulfilled => "fulfilled",
+            TxStatus::Expired => "expired",
+            TxStatus::Settled => "settled",
+            TxStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{status}")
+    }
+}
+
+// add_pending_tx is called by the module account assigned at instantiation, or by a
+// poster whose scope covers the tx, which adds a pending incoming transaction to the
+// contract's store.
 // This store is to be used as a pseudo order book, where market makers can
 // select one of these pending transactions to fulfill. A tx is removed
 // from this store when either a market maker fulfills it or the protocol
 // deems sufficent confirmations have passed to utilize the funds via
 // a pool swap.
+#[allow(clippy::too_many_arguments)]
 pub fn add_pending_tx(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     destination_addr: String,
     coin: Coin,
+    origin: Option<TxOrigin>,
+    owner: Option<TxOwner>,
+    signatures: Option<Vec<OrderSignature>>,
 ) -> Result<Response, ContractError> {
-    let mut state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+    validate_destination(deps.as_ref(), &destination_addr)?;
+    ensure_denom_enabled(deps.storage, &coin.denom)?;
+
+    match &signatures {
+        // Signed orders are authorized by the signer set, whoever relays them. The signer
+        // set is an alternative to attestation, so signed orders are accepted whether or not
+        // attestation is enabled.
+        Some(signatures) => {
+            let origin = origin.as_ref().ok_or(ContractError::OriginRequired {})?;
+            verify_order_signatures(
+                deps.as_ref(),
+                &env,
+                &destination_addr,
+                &coin,
+                origin,
+                owner.as_ref(),
+                signatures,
+            )?;
+        }
+        None => {
+            let state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
 
-    // The sender must be the module account that is set at time of instantiation
-    if info.sender != state.module_account {
-        return Err(ContractError::Unauthorized {});
+            // The sender must be the module account or a poster scoped to the tx
+            authorize_poster(deps.as_ref(), &state, &info.sender, &coin, origin.as_ref())?;
+
+            // While attestation is enabled, txs are only added once enough attesters agree
+            // on them
+            if attestation_enabled(deps.storage)? {
+                return Err(ContractError::AttestationRequired {});
+            }
+        }
+    }
+
+    let tx = insert_pending_tx(deps.storage, &env, destination_addr, coin, origin, owner)?;
+    Ok(Response::new()
+        .add_attribute("action", "add_tx")
+        .add_attribute("tx_id", tx.id.to_string())
+        .add_event(tx_event(TX_ADDED_EVENT, &tx, TxStatus::Pending, None)))
+}
+
+// insert_pending_tx adds a tx to the pending transactions store once its poster has been
+// authorized. A deposit on the origin chain can only ever fund a single tx.
+pub fn insert_pending_tx(
+    storage: &mut dyn Storage,
+    env: &Env,
+    destination_addr: String,
+    coin: Coin,
+    origin: Option<TxOrigin>,
+    owner: Option<TxOwner>,
+) -> Result<Tx, ContractError> {
+    let mut state: State = STATE.load(storage).map_err(ContractError::Std)?;
+
+    if let Some(origin) = &origin {
+        let key = origin.key();
+        if PROCESSED_ORIGINS.has(storage, &key) {
+            return Err(ContractError::OriginAlreadyProcessed { origin: key });
+        }
+        PROCESSED_ORIGINS.save(storage, &key, &state.next_id)?;
+        clear_tx_attestations(storage, &key)?;
     }
 
-    // Add the transaction to the pending transactions store
-    let new_id = state.next_id;
-    state.pending_txs.push(Tx {
-        id: new_id,
+    // Add the transaction to the pending transactions store. The settlement mode is fixed
+    // when the tx is added, so later registry changes can't strand it.
+    let tx = Tx {
+        id: state.next_id,
         destination_addr,
+        settlement: settlement_mode(storage, &coin.denom)?,
         coin,
-    });
+        added_height: env.block.height,
+        fill: None,
+        origin,
+        owner,
+        amendments: 0,
+        payee: None,
+    };
+    state.pending_txs.push(tx.clone());
 
     // Increment the id counter for the next transaction
     state.next_id += 1;
 
-    STATE
-        .save(deps.storage, &state)
-        .map_err(ContractError::Std)?;
-    Ok(Response::new())
+    STATE.save(storage, &state).map_err(ContractError::Std)?;
+    record_added(storage, &tx)?;
+    record_tx(storage, &tx, TxStatus::Pending, env.block.height)?;
+    Ok(tx)
 }
 
-// fulfill_pending_tx is to be called by market makers looking to fulfill a pending
-// incoming transaction. This will send the funds to the destination address.
-// In the event this send succeeds, the transaction is moved to the fulfilled
-// transactions store. From there, we will utilize this store to pay the market
-// maker with the incoming funds that originated from the tx creator.
-pub fn fulfill_pending_tx(
-    deps: DepsMut,
-    _env: Env,
-    info: MessageInfo,
-    tx_id: u64,
-) -> Result<Response, ContractError> {
-    let state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+// tx_not_found explains why a tx is missing from the store an action expected it in: it is
+// in another status, or the contract has no record of it, either because it never existed
+// or because its record has been pruned.
+pub fn tx_not_found(storage: &dyn Storage, state: &State, tx_id: u64) -> ContractError {
+    let status = if state.pending_txs.iter().any(|tx| tx.id == tx_id) {
+        Some(TxStatus::Pending)
+    } else if state.fulfilled_txs.iter().any(|tx| tx.id == tx_id) {
+        Some(TxStatus::Fulfilled)
+    } else {
+        tx_records()
+            .may_load(storage, tx_id)
+            .ok()
+            .flatten()
+            .map(|record| record.status)
+    };
+    match status {
+        Some(status) => ContractError::InvalidStatus { id: tx_id, status },
+        None => ContractError::TransactionNotFound { id: tx_id },
+    }
+}
 
-    // Find the transaction index in the pending transactions store via the tx_id
-    let tx_position = state.pending_txs.iter().position(|tx| tx.id == tx_id);
+// check_fill_funds checks a market maker sent exactly the coin of the tx it fills
+pub fn check_fill_funds(required: &Coin, sent: &Coin) -> Result<(), ContractError> {
+    if sent.denom != required.denom {
+        return Err(ContractError::WrongDenom {
+            expected: required.denom.clone(),
+            got: sent.denom.clone(),
+        });
+    }
+    if sent.amount < required.amount {
+        return Err(ContractError::InsufficientFunds {
+            required: required.amount,
+            sent: sent.amount,
+        });
+    }
+    if sent.amount > required.amount {
+        return Err(ContractError::ExcessFunds {
+            required: required.amount,
+            sent: sent.amount,
+        });
+    }
+    Ok(())
+}
 
-    // Pull the transaction from the pending transactions store
-    let tx = match tx_position {
-        Some(index) => state.pending_txs[index].clone(),
-        None => {
-            return Err(ContractError::TransactionNotFound { id: (tx_id) });
-        }
+// FillPlan is what fulfilling a pending tx does: the protocol fee kept by the contract and the
+// payout of the remainder to the destination
+pub struct FillPlan {
+    pub tx: Tx,
+    pub protocol_fee: Coin,
+    pub payout: Payout,
+}
+
+// PlannedFill is the plan of a tx in a batch fill, or the error its fill would fail with
+pub type PlannedFill = (u64, Result<FillPlan, ContractError>);
+
+// plan_fill validates a fill of a pending tx with the given funds, without changing any
+// state. It is shared by fulfill_pending_tx and the SimulateFulfill query.
+pub fn plan_fill(
+    deps: Deps,
+    state: &State,
+    tx_id: u64,
+    funds: &[Coin],
+) -> Result<FillPlan, ContractError> {
+    // Find the transaction in the pending transactions store via the tx_id
+    let tx = match state.pending_txs.iter().find(|tx| tx.id == tx_id) {
+        Some(tx) => tx.clone(),
+        None => return Err(tx_not_found(deps.storage, state, tx_id)),
     };
 
     // We only support providing a single coin
-    let coins: Vec<Coin> = info.funds;
-    if coins.len() != 1 {
+    if funds.len() != 1 {
         return Err(ContractError::MultipleCoinError {
-            num_coins: (coins.len()),
+            num_coins: (funds.len()),
         });
     }
 
     // The amount provided must match the amount specified in the pending transaction
     // TODO: Make this just refund user if too much is provided
-    if coins[0] != tx.coin {
-        return Err(ContractError::CustomError {
-            val: "Amount provided does not match amount specified in pending transaction"
-                .to_string(),
-        });
-    }
-
-    // Prepare the bank send message
-    let bank_send_msg = BankMsg::Send {
-        to_address: tx.clone().destination_addr,
-        amount: coins,
+    check_fill_funds(&tx.coin, &funds[0])?;
+
+    // The protocol fee is kept by the contract and the remainder is forwarded
+    let config = CONFIG.load(deps.storage)?;
+    let protocol_fee = Coin {
+        denom: tx.coin.denom.clone(),
+        amount: tx
+            .coin
+            .amount
+            .multiply_ratio(config.protocol_fee_bps, BPS_DENOMINATOR),
     };
 
-    // We must store the fulfill state in order to set the address of the market maker
-    // that fulfilled the transaction as the destination after we confirm the bank send
-    // was successful.
+    // Prepare the message delivering the remainder to the destination
+    let payout = payout(
+        deps,
+        &tx.destination_addr,
+        Coin {
+            denom: tx.coin.denom.clone(),
+            amount: tx.coin.amount - protocol_fee.amount,
+        },
+    )?;
+
+    Ok(FillPlan {
+        tx,
+        protocol_fee,
+        payout,
+    })
+}
+
+// plan_fills plans fills of the txs in order, each drawing its coin from what is left of the
+// funds. Planned fills are applied to the order book copy so later txs see earlier ones, and
+// txs that can't be filled take nothing. Returns the plan or error of each tx and the funds
+// left over.
+pub fn plan_fills(
+    deps: Deps,
+    state: &mut State,
+    tx_ids: &[u64],
+    funds: &[Coin],
+) -> (Vec<PlannedFill>, Vec<Coin>) {
+    let mut remaining = funds.to_vec();
+    let mut plans = vec![];
+    for &tx_id in tx_ids {
+        let tx_funds = state
+            .pending_txs
+            .iter()
+            .find(|tx| tx.id == tx_id)
+            .map_or(vec![], |tx| {
+                let available = remaining
+                    .iter()
+                    .find(|coin| coin.denom == tx.coin.denom)
+                    .map_or(Uint128::zero(), |coin| coin.amount);
+                vec![Coin {
+                    denom: tx.coin.denom.clone(),
+                    amount: available.min(tx.coin.amount),
+                }]
+            });
+
+        let plan = plan_fill(deps, state, tx_id, &tx_funds);
+        if let Ok(plan) = &plan {
+            if let Some(coin) = remaining
+                .iter_mut()
+                .find(|coin| coin.denom == plan.tx.coin.denom)
+            {
+                coin.amount -= plan.tx.coin.amount;
+            }
+            remaining.retain(|coin| !coin.amount.is_zero());
+            state.pending_txs.retain(|tx| tx.id != tx_id);
+            state.fulfilled_txs.push(plan.tx.clone());
+        }
+        plans.push((tx_id, plan));
+    }
+    (plans, remaining)
+}
+
+// submit_fill credits the protocol fee of a planned fill and returns the event and payout
+// submessage, whose reply moves the tx to the fulfilled txs
+fn submit_fill(
+    storage: &mut dyn Storage,
+    fulfiller_addr: &Addr,
+    plan: FillPlan,
+) -> Result<(Event, SubMsg), ContractError> {
+    let FillPlan {
+        tx,
+        protocol_fee,
+        payout,
+    } = plan;
+    credit_treasury(storage, &protocol_fee)?;
+
+    // We must store the fulfill state in order to record the address of the market maker
+    // that fulfilled the transaction after we confirm the bank send was successful.
     FULFILL_REPLY_STATES.save(
-        deps.storage,
+        storage,
         tx.id,
         &FulfillState {
-            fulfiller_addr: info.sender,
+            fulfiller_addr: fulfiller_addr.clone(),
+            protocol_fee: protocol_fee.amount,
         },
     )?;
 
+    let event = tx_event(
+        TX_FULFILL_SUBMITTED_EVENT,
+        &tx,
+        TxStatus::Fulfilling,
+        Some(fulfiller_addr),
+    )
+    .add_attribute("route", payout.route);
+    Ok((event, SubMsg::reply_on_success(payout.msg, tx.id)))
+}
+
+// fulfill_pending_tx is to be called by market makers looking to fulfill a pending
+// incoming transaction. This will send the funds to the destination address.
+// In the event this send succeeds, the transaction is moved to the fulfilled
+// transactions store. From there, we will utilize this store to pay the market
+// maker with the incoming funds that originated from the tx creator.
+pub fn fulfill_pending_tx(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    tx_id: u64,
+) -> Result<Response, ContractError> {
+    let state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+    let plan = plan_fill(deps.as_ref(), &state, tx_id, &info.funds)?;
+    let protocol_fee = plan.protocol_fee.clone();
+    let (event, submsg) = submit_fill(deps.storage, &info.sender, plan)?;
+
     Ok(Response::new()
         .add_attribute("action", "fulfill_tx")
-        .add_submessage(SubMsg::reply_on_success(bank_send_msg, tx.id)))
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("protocol_fee", protocol_fee.to_string())
+        .add_event(event)
+        .add_submessage(submsg))
+}
+
+// fulfill_pending_txs fulfills a batch of pending txs in order, each drawing its coin from the
+// funds. Txs that can't be filled, for instance because another market maker got to them
+// first, are skipped and the funds left over are refunded to the market maker.
+pub fn fulfill_pending_txs(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    tx_ids: Vec<u64>,
+) -> Result<Response, ContractError> {
+    let mut state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+    let (plans, refund) = plan_fills(deps.as_ref(), &mut state, &tx_ids, &info.funds);
+
+    let mut response = Response::new().add_attribute("action", "fulfill_txs");
+    for (tx_id, plan) in plans {
+        match plan {
+            Ok(plan) => {
+                let (event, submsg) = submit_fill(deps.storage, &info.sender, plan)?;
+                response = response
+                    .add_attribute("tx_id", tx_id.to_string())
+                    .add_event(event)
+                    .add_submessage(submsg);
+            }
+            Err(err) => {
+                response = response
+                    .add_attribute("skipped_tx_id", format!("{tx_id}:{}", err.code() as u32));
+            }
+        }
+    }
+
+    if !refund.is_empty() {
+        response = response.add_message(BankMsg::Send {
+            to_address: info.sender.to_string(),
+            amount: refund,
+        });
+    }
+    Ok(response)
 }
 
 // move_pending_tx_to_fulfilled_tx is called by the contract when a market maker has
@@ -121,7 +480,7 @@ pub fn fulfill_pending_tx(
 // store.
 pub fn move_pending_tx_to_fulfilled_tx(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: Reply,
     fulfill_state: FulfillState,
 ) -> Result<Response, ContractError> {
@@ -133,25 +492,43 @@ pub fn move_pending_tx_to_fulfilled_tx(
 
         // Pull the transaction from the pending transactions store
         // and move it to the fulfilled transactions store
-        match tx_position {
+        let event = match tx_position {
             Some(index) => {
                 let mut tx = state.pending_txs.remove(index);
-                tx.destination_addr = fulfill_state.fulfiller_addr.to_string();
+                let event = tx_event(
+                    TX_FULFILLED_EVENT,
+                    &tx,
+                    TxStatus::Fulfilled,
+                    Some(&fulfill_state.fulfiller_addr),
+                );
+                // The market maker forwards exactly the amount of the pending tx
+                tx.payee = Some(fulfill_state.fulfiller_addr.clone());
+                tx.fill = Some(Fill {
+                    fulfiller_addr: fulfill_state.fulfiller_addr,
+                    amount: tx.coin.amount,
+                    height: env.block.height,
+                });
+                record_filled(deps.storage, &tx, fulfill_state.protocol_fee)?;
+                record_tx(deps.storage, &tx, TxStatus::Fulfilled, env.block.height)?;
                 state.fulfilled_txs.push(tx);
+                event
             }
             None => {
                 return Err(ContractError::TransactionNotFound { id: (msg.id) });
             }
-        }
+        };
 
         STATE
             .save(deps.storage, &state)
             .map_err(ContractError::Std)?;
 
-        return Ok(Response::new());
+        return Ok(Response::new()
+            .add_attribute("action", "move_pending_tx_to_fulfilled_tx")
+            .add_attribute("tx_id", msg.id.to_string())
+            .add_event(event));
     }
 
-    return Err(ContractError::TransactionNotFound { id: (msg.id) });
+    Err(ContractError::TransactionNotFound { id: (msg.id) })
 }
 
 // remove_pending_tx is called by the contract when a pending incoming transaction
@@ -160,74 +537,313 @@ pub fn move_pending_tx_to_fulfilled_tx(
 // store.
 pub fn remove_pending_tx(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     tx_id: u64,
 ) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
 
-    // Check if the sender is the module account
-    if info.sender != state.module_account {
-        return Err(ContractError::Unauthorized {});
-    }
-
     let tx_position = state.pending_txs.iter().position(|tx| tx.id == tx_id);
-    match tx_position {
-        Some(index) => {
-            state.pending_txs.remove(index);
+    let tx = match tx_position {
+        Some(index) => state.pending_txs.remove(index),
+        None => return Err(tx_not_found(deps.storage, &state, tx_id)),
+    };
+
+    // Check if the sender is the module account or a poster scoped to the tx
+    authorize_poster(
+        deps.as_ref(),
+        &state,
+        &info.sender,
+        &tx.coin,
+        tx.origin.as_ref(),
+    )?;
+
+    // Txs settled by forwarding have the module hand over the cleared funds with the
+    // expiry, which are then delivered to the destination the same way a fill is
+    let event = tx_event(TX_EXPIRED_EVENT, &tx, TxStatus::Expired, None);
+    let (messages, event) = match tx.settlement {
+        SettlementMode::Forward => {
+            if info.funds.len() != 1 {
+                return Err(ContractError::MultipleCoinError {
+                    num_coins: info.funds.len(),
+                });
+            }
+            check_fill_funds(&tx.coin, &info.funds[0])?;
+            let payout = payout(deps.as_ref(), &tx.destination_addr, tx.coin.clone())?;
+            (vec![payout.msg], event.add_attribute("route", payout.route))
         }
-        None => {
-            return Err(ContractError::TransactionNotFound { id: (tx_id) });
+        _ => {
+            // Other denoms are delivered by the module itself, so funds sent along would be
+            // stranded in the contract
+            if !info.funds.is_empty() {
+                return Err(ContractError::FundsNotAccepted {});
+            }
+            (expiry_mint(deps.as_ref(), &env, &tx)?, event)
         }
-    }
+    };
 
     STATE
         .save(deps.storage, &state)
         .map_err(ContractError::Std)?;
+    record_expired(deps.storage, &tx)?;
+    record_tx(deps.storage, &tx, TxStatus::Expired, env.block.height)?;
 
-    Ok(Response::new())
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "remove_tx")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_event(event))
 }
 
-// remove_fulfilled_tx is called by the module account once the bridged funds arrive on Osmosis
-// and are sent to the market maker that fulfilled the pending incoming transaction.
-pub fn remove_fulfilled_tx(
+// pending_owned_tx returns the position of a pending tx once its owner has authorized the
+// action. A tx can no longer be changed once a market maker has started to fill it.
+fn pending_owned_tx(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    state: &State,
+    tx_id: u64,
+    action: OwnerAction,
+    signature: Option<Binary>,
+) -> Result<usize, ContractError> {
+    if state.fulfilled_txs.iter().any(|tx| tx.id == tx_id)
+        || FULFILL_REPLY_STATES.has(deps.storage, tx_id)
+    {
+        return Err(ContractError::FillStarted { id: tx_id });
+    }
+    let position = state
+        .pending_txs
+        .iter()
+        .position(|tx| tx.id == tx_id)
+        .ok_or_else(|| tx_not_found(deps.storage, state, tx_id))?;
+    let tx = &state.pending_txs[position];
+    let owner = tx.owner.as_ref().ok_or(ContractError::Unauthorized {})?;
+
+    match (&owner.pubkey, signature) {
+        // Owners with an account on this chain send the change themselves
+        (None, _) => {
+            if info.sender.as_str() != owner.refund_addr {
+                return Err(ContractError::Unauthorized {});
+            }
+        }
+        // Other owners sign it, and anyone may relay the signature
+        (Some(pubkey), Some(signature)) => {
+            verify_owner_signature(deps, env, tx, pubkey, action, &signature)?;
+        }
+        (Some(_), None) => return Err(ContractError::Unauthorized {}),
+    }
+    Ok(position)
+}
+
+// cancel_pending_tx is called by the owner of a pending tx to withdraw it from the order
+// book. The deposit is refunded to the owner's refund address on the origin chain.
+pub fn cancel_pending_tx(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     tx_id: u64,
+    signature: Option<Binary>,
 ) -> Result<Response, ContractError> {
     let mut state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+    let position = pending_owned_tx(
+        deps.as_ref(),
+        &env,
+        &info,
+        &state,
+        tx_id,
+        OwnerAction::Cancel {},
+        signature,
+    )?;
+    let tx = state.pending_txs.remove(position);
 
-    // Check if the sender is the module account
-    if info.sender != state.module_account {
-        return Err(ContractError::Unauthorized {});
+    STATE.save(deps.storage, &state)?;
+    record_cancelled(deps.storage, &tx)?;
+    record_tx(deps.storage, &tx, TxStatus::Cancelled, env.block.height)?;
+
+    let refund_addr = tx
+        .owner
+        .as_ref()
+        .map(|owner| owner.refund_addr.clone())
+        .unwrap_or_default();
+    Ok(Response::new()
+        .add_attribute("action", "cancel_tx")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_event(
+            tx_event(TX_CANCELLED_EVENT, &tx, TxStatus::Cancelled, None)
+                .add_attribute("refund_addr", refund_addr),
+        ))
+}
+
+// amend_pending_tx is called by the owner of a pending tx to change its destination
+pub fn amend_pending_tx(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tx_id: u64,
+    destination_addr: String,
+    signature: Option<Binary>,
+) -> Result<Response, ContractError> {
+    let mut state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+    validate_destination(deps.as_ref(), &destination_addr)?;
+    let position = pending_owned_tx(
+        deps.as_ref(),
+        &env,
+        &info,
+        &state,
+        tx_id,
+        OwnerAction::Amend {
+            destination_addr: destination_addr.clone(),
+        },
+        signature,
+    )?;
+
+    let tx = &mut state.pending_txs[position];
+    let previous_destination = std::mem::replace(&mut tx.destination_addr, destination_addr);
+    // Each amendment invalidates the signatures made for the previous ones
+    tx.amendments += 1;
+    let tx = tx.clone();
+
+    STATE.save(deps.storage, &state)?;
+    record_tx(deps.storage, &tx, TxStatus::Pending, env.block.height)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "amend_tx")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_event(
+            tx_event(TX_AMENDED_EVENT, &tx, TxStatus::Pending, None)
+                .add_attribute("previous_destination", previous_destination),
+        ))
+}
+
+// remove_fulfilled_tx is called by the module account, or a poster scoped to the tx, once the
+// bridged funds arrive on Osmosis and are sent to the market maker that fulfilled the pending
+// incoming transaction.
+pub fn remove_fulfilled_tx(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    tx_id: u64,
+) -> Result<Response, ContractError> {
+    let state: State = STATE.load(deps.storage).map_err(ContractError::Std)?;
+
+    let tx = match state.fulfilled_txs.iter().find(|tx| tx.id == tx_id) {
+        Some(tx) => tx,
+        None => return Err(tx_not_found(deps.storage, &state, tx_id)),
+    };
+
+    // Check if the sender is the module account or a poster scoped to the tx
+    authorize_poster(
+        deps.as_ref(),
+        &state,
+        &info.sender,
+        &tx.coin,
+        tx.origin.as_ref(),
+    )?;
+
+    // While attestation is enabled, txs are only settled once enough attesters agree
+    if attestation_enabled(deps.storage)? {
+        return Err(ContractError::AttestationRequired {});
     }
 
+    let tx = settle_fulfilled_tx(deps.storage, &env, tx_id)?;
+    let fulfiller = tx.fill.as_ref().map(|fill| &fill.fulfiller_addr);
+    Ok(Response::new()
+        .add_messages(settlement_mint(&env, &tx))
+        .add_attribute("action", "remove_fulfilled_tx")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_event(tx_event(
+            TX_SETTLED_EVENT,
+            &tx,
+            TxStatus::Settled,
+            fulfiller,
+        )))
+}
+
+// settle_fulfilled_tx removes a tx from the fulfilled transactions store once its
+// settlement has been authorized.
+pub fn settle_fulfilled_tx(
+    storage: &mut dyn Storage,
+    env: &Env,
+    tx_id: u64,
+) -> Result<Tx, ContractError> {
+    let mut state: State = STATE.load(storage).map_err(ContractError::Std)?;
+
     let tx_position = state.fulfilled_txs.iter().position(|tx| tx.id == tx_id);
-    match tx_position {
-        Some(index) => {
-            state.fulfilled_txs.remove(index);
-        }
-        None => {
-            return Err(ContractError::TransactionNotFound { id: (tx_id) });
-        }
+    let tx = match tx_position {
+        Some(index) => state.fulfilled_txs.remove(index),
+        None => return Err(tx_not_found(storage, &state, tx_id)),
+    };
+
+    STATE.save(storage, &state).map_err(ContractError::Std)?;
+    record_settled(storage, &tx)?;
+    record_tx(storage, &tx, TxStatus::Settled, env.block.height)?;
+    Ok(tx)
+}
+
+// update_config is called by the admin to change the contract config. Only the
+// provided fields are updated.
+#[allow(clippy::too_many_arguments)]
+pub fn update_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    admin: Option<String>,
+    history_retention_blocks: Option<u64>,
+    protocol_fee_bps: Option<u64>,
+    withdrawal_timelock_seconds: Option<u64>,
+    foreign_destination_formats: Option<Vec<DestinationFormat>>,
+    outbound_timeout_seconds: Option<u64>,
+    destination_routes: Option<Vec<DestinationRoute>>,
+) -> Result<Response, ContractError> {
+    let mut config = load_config(deps.storage, &env)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
     }
 
-    STATE
-        .save(deps.storage, &state)
-        .map_err(ContractError::Std)?;
+    if let Some(admin) = admin {
+        config.admin = deps.api.addr_validate(&admin)?;
+    }
+    if let Some(history_retention_blocks) = history_retention_blocks {
+        config.history_retention_blocks = history_retention_blocks;
+    }
+    if let Some(protocol_fee_bps) = protocol_fee_bps {
+        if protocol_fee_bps >= BPS_DENOMINATOR {
+            return Err(ContractError::InvalidProtocolFee {
+                bps: protocol_fee_bps,
+            });
+        }
+        config.protocol_fee_bps = protocol_fee_bps;
+    }
+    if let Some(withdrawal_timelock_seconds) = withdrawal_timelock_seconds {
+        set_withdrawal_timelock(deps.storage, &env, &mut config, withdrawal_timelock_seconds)?;
+    }
+    if let Some(foreign_destination_formats) = foreign_destination_formats {
+        config.foreign_destination_formats = foreign_destination_formats;
+    }
+    if let Some(outbound_timeout_seconds) = outbound_timeout_seconds {
+        config.outbound_timeout_seconds = outbound_timeout_seconds;
+    }
+    if let Some(destination_routes) = destination_routes {
+        validate_routes(deps.as_ref(), &destination_routes)?;
+        config.destination_routes = destination_routes;
+    }
+    CONFIG.save(deps.storage, &config)?;
 
-    Ok(Response::new())
+    Ok(Response::new().add_attribute("action", "update_config"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::coins;
+    use crate::signatures::{payload_digest, OwnerActionPayload};
+    use crate::state::{KeyType, CONFIG};
+    use crate::testing::{mock_config, mock_denom};
+    use crate::ErrorCode;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
     use cosmwasm_std::Binary;
     use cosmwasm_std::SubMsgResponse;
     use cosmwasm_std::Uint128;
+    use cosmwasm_std::{coin, coins};
 
     #[test]
     fn test_add_pending_tx() {
@@ -249,17 +865,40 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Call add_tx
-        add_pending_tx(
+        let response = add_pending_tx(
             deps.as_mut(),
             env,
             info,
             destination_addr.clone(),
             coin.clone(),
+            None,
+            None,
+            None,
         )
         .unwrap();
 
+        // Check the emitted event describes the new pending transaction
+        assert_eq!(response.events.len(), 1);
+        let event = &response.events[0];
+        assert_eq!(event.ty, TX_ADDED_EVENT);
+        let attr = |key: &str| {
+            event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+        };
+        assert_eq!(attr("tx_id"), Some("0".to_string()));
+        assert_eq!(attr("denom"), Some("token".to_string()));
+        assert_eq!(attr("amount"), Some("100".to_string()));
+        assert_eq!(attr("destination"), Some(destination_addr.clone()));
+        assert_eq!(attr("status"), Some("pending".to_string()));
+        assert_eq!(attr("fulfiller"), None);
+
         // Load state from storage
         let state: State = STATE.load(deps.as_ref().storage).unwrap();
 
@@ -289,22 +928,60 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Add a transaction
         add_pending_tx(
             deps.as_mut(),
             env.clone(),
-            info.clone(),
+            info,
             destination_addr,
-            coin,
+            coin.clone(),
+            None,
+            None,
+            None,
         )
         .unwrap();
 
-        // Fulfill the transaction
-        let result = fulfill_pending_tx(deps.as_mut(), env, info, 0);
+        // Fulfill the transaction as a market maker providing the exact amount
+        let maker_info = mock_info("market_maker", &[coin]);
+        let result = fulfill_pending_tx(deps.as_mut(), env, maker_info, 0);
 
         // Check if the transaction was fulfilled successfully
         assert!(result.is_ok());
+
+        // Check the fulfill event carries the market maker
+        let response = result.unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert_eq!(response.events[0].ty, TX_FULFILL_SUBMITTED_EVENT);
+        assert!(response.events[0]
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "fulfiller" && attr.value == "market_maker"));
+    }
+
+    #[test]
+    fn test_fulfill_funds_mismatch() {
+        let required = coin(100, "token");
+        assert!(check_fill_funds(&required, &coin(100, "token")).is_ok());
+
+        let err = check_fill_funds(&required, &coin(100, "other")).unwrap_err();
+        assert!(matches!(err, ContractError::WrongDenom { .. }));
+        let err = check_fill_funds(&required, &coin(99, "token")).unwrap_err();
+        assert!(matches!(err, ContractError::InsufficientFunds { .. }));
+        let err = check_fill_funds(&required, &coin(101, "token")).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::ExcessFunds);
+
+        // Messages start with the error's stable code
+        assert_eq!(
+            err.to_string(),
+            "E106: Excess funds, 100 required, 101 sent"
+        );
+        let mut codes: Vec<u32> = ErrorCode::ALL.iter().map(|code| *code as u32).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorCode::ALL.len());
     }
 
     #[test]
@@ -327,6 +1004,8 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Add a transaction
         add_pending_tx(
@@ -335,6 +1014,9 @@ mod tests {
             info.clone(),
             destination_addr,
             coin,
+            None,
+            None,
+            None,
         )
         .unwrap();
 
@@ -353,6 +1035,7 @@ mod tests {
             msg,
             FulfillState {
                 fulfiller_addr: info.sender,
+                protocol_fee: Uint128::zero(),
             },
         )
         .unwrap();
@@ -386,6 +1069,8 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Add 3 transactions
         for _ in 0..3 {
@@ -395,12 +1080,19 @@ mod tests {
                 info.clone(),
                 owner.clone(),
                 output_coin.clone(),
+                None,
+                None,
+                None,
             )
             .unwrap();
         }
 
+        // Funds are only handed over with the expiry of forwarded denoms
+        let err = remove_pending_tx(deps.as_mut(), env.clone(), info, 1).unwrap_err();
+        assert!(matches!(err, ContractError::FundsNotAccepted {}));
+
         // Remove one transaction
-        remove_pending_tx(deps.as_mut(), env, info, 1).unwrap();
+        remove_pending_tx(deps.as_mut(), env, mock_info("module_account", &[]), 1).unwrap();
 
         // Load state from storage
         let state: State = STATE.load(deps.as_ref().storage).unwrap();
@@ -410,6 +1102,179 @@ mod tests {
         assert!(!state.pending_txs.iter().any(|tx| tx.id == 1));
     }
 
+    #[test]
+    fn test_cancel_and_amend_pending_tx() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("module_account", &[]);
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        let owner = TxOwner {
+            refund_addr: "depositor".to_string(),
+            pubkey: None,
+        };
+        for _ in 0..2 {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                "destination_addr".to_string(),
+                Coin::new(100, "token"),
+                None,
+                Some(owner.clone()),
+                None,
+            )
+            .unwrap();
+        }
+
+        // Only the owner may change the tx
+        let err = amend_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+            "other_addr".to_string(),
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let res = amend_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("depositor", &[]),
+            0,
+            "other_addr".to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, TX_AMENDED_EVENT);
+        let state: State = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs[0].destination_addr, "other_addr");
+        assert_eq!(state.pending_txs[0].amendments, 1);
+
+        let res = cancel_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("depositor", &[]),
+            0,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, TX_CANCELLED_EVENT);
+        let state: State = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs.len(), 1);
+
+        // Once a fill has started the tx can no longer be changed
+        FULFILL_REPLY_STATES
+            .save(
+                deps.as_mut().storage,
+                1,
+                &FulfillState {
+                    fulfiller_addr: Addr::unchecked("market_maker"),
+                    protocol_fee: Uint128::zero(),
+                },
+            )
+            .unwrap();
+        let err = cancel_pending_tx(deps.as_mut(), env, mock_info("depositor", &[]), 1, None)
+            .unwrap_err();
+        assert!(matches!(err, ContractError::FillStarted { id: 1 }));
+    }
+
+    #[test]
+    fn test_amend_pending_tx_with_owner_signature() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        let key = ed25519_zebra::SigningKey::from([7; 32]);
+        let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&key).into();
+        let owner = TxOwner {
+            refund_addr: "bc1qdepositor".to_string(),
+            pubkey: Some(Signer {
+                key_type: KeyType::Ed25519,
+                pubkey: Binary::from(pubkey.to_vec()),
+            }),
+        };
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "destination_addr".to_string(),
+            Coin::new(100, "token"),
+            None,
+            Some(owner),
+            None,
+        )
+        .unwrap();
+
+        let sign = |amendments: u32, destination_addr: &str| {
+            let digest = payload_digest(&OwnerActionPayload {
+                chain_id: env.block.chain_id.clone(),
+                contract: env.contract.address.to_string(),
+                tx_id: 0,
+                amendments,
+                action: OwnerAction::Amend {
+                    destination_addr: destination_addr.to_string(),
+                },
+            })
+            .unwrap();
+            let signature: [u8; 64] = key.sign(&digest).into();
+            Binary::from(signature.to_vec())
+        };
+
+        // Anyone may relay a change signed by the owner
+        let signature = sign(0, "other_addr");
+        amend_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            0,
+            "other_addr".to_string(),
+            Some(signature.clone()),
+        )
+        .unwrap();
+
+        // The signature can't be replayed once the destination changed again
+        amend_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            0,
+            "destination_addr".to_string(),
+            Some(sign(1, "destination_addr")),
+        )
+        .unwrap();
+        let err = amend_pending_tx(
+            deps.as_mut(),
+            env,
+            mock_info("relayer", &[]),
+            0,
+            "other_addr".to_string(),
+            Some(signature),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature { .. }));
+    }
+
     #[test]
     fn test_remove_fulfilled_tx() {
         let mut deps = mock_dependencies();
@@ -430,13 +1295,25 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
 
         // Add a transaction to fulfilled_txs
         let mut state: State = STATE.load(deps.as_mut().storage).unwrap();
         state.fulfilled_txs.push(Tx {
             id: 0,
-            destination_addr,
+            destination_addr: destination_addr.clone(),
             coin,
+            added_height: env.block.height,
+            fill: Some(Fill {
+                fulfiller_addr: Addr::unchecked(destination_addr.clone()),
+                amount: Uint128::from(100u128),
+                height: env.block.height,
+            }),
+            origin: None,
+            owner: None,
+            amendments: 0,
+            settlement: SettlementMode::Module,
+            payee: Some(Addr::unchecked(destination_addr)),
         });
         STATE.save(deps.as_mut().storage, &state).unwrap();
 
@@ -0,0 +1,61 @@
+use cosmwasm_std::{Order, StdResult, Storage};
+use cw_storage_plus::Bound;
+
+use crate::execute::{Tx, TxStatus};
+use crate::state::{tx_records, TxRecord, CONFIG};
+
+// Maximum number of expired history records pruned per state transition, which
+// bounds the gas a single execute spends on housekeeping.
+const MAX_PRUNE_PER_CALL: usize = 10;
+
+// record_tx writes the latest status of a tx to the tx history and prunes history
+// records whose retention window has passed.
+pub fn record_tx(
+    storage: &mut dyn Storage,
+    tx: &Tx,
+    status: TxStatus,
+    height: u64,
+) -> StdResult<()> {
+    let config = CONFIG.load(storage)?;
+
+    let prune_height = match status {
+        TxStatus::Settled | TxStatus::Expired | TxStatus::Cancelled => {
+            height.saturating_add(config.history_retention_blocks)
+        }
+        _ => u64::MAX,
+    };
+    tx_records().save(
+        storage,
+        tx.id,
+        &TxRecord {
+            tx: tx.clone(),
+            status,
+            updated_height: height,
+            prune_height,
+        },
+    )?;
+
+    prune_history(storage, height)
+}
+
+// prune_history removes up to MAX_PRUNE_PER_CALL records whose prune height is
+// strictly below the current height.
+pub fn prune_history(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    let expired = tx_records()
+        .idx
+        .prune_height
+        .range(
+            storage,
+            None,
+            Some(Bound::exclusive((height, 0))),
+            Order::Ascending,
+        )
+        .take(MAX_PRUNE_PER_CALL)
+        .map(|item| item.map(|(id, _)| id))
+        .collect::<StdResult<Vec<u64>>>()?;
+
+    for id in expired {
+        tx_records().remove(storage, id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use std::process::Command;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, to_json_string, Coin, StdError};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{ContractExecutor, ContractQuerier};
+use crate::msg::{ExecuteMsg, QueryMsg};
+
+// CliChain reaches a node through the chain daemon's command line, e.g. `osmosisd`, and signs
+// with keys from its keyring. It needs no RPC client, so bots can run wherever the daemon is
+// installed.
+#[cw_serde]
+pub struct CliChain {
+    pub binary: String,
+    pub node: String,
+    pub chain_id: String,
+    #[serde(default)]
+    pub keyring_backend: Option<String>,
+    #[serde(default)]
+    pub home: Option<String>,
+    #[serde(default)]
+    pub gas_prices: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("Command failed with {status:}: {stderr:}")]
+    Command { status: String, stderr: String },
+
+    #[error("Tx {txhash:} failed with code {code:}: {raw_log:}")]
+    Tx {
+        txhash: String,
+        code: u32,
+        raw_log: String,
+    },
+}
+
+// CliTxResponse is the part of the broadcast result bots need. Txs are broadcast in sync
+// mode, so a successful response only means the tx passed CheckTx.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct CliTxResponse {
+    pub txhash: String,
+    #[serde(default)]
+    pub code: u32,
+    #[serde(default)]
+    pub raw_log: String,
+}
+
+#[derive(Deserialize)]
+struct SmartQueryResponse<T> {
+    data: T,
+}
+
+impl CliChain {
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+        command.args(["--node", &self.node, "--output", "json"]);
+        if let Some(home) = &self.home {
+            command.args(["--home", home]);
+        }
+        command
+    }
+
+    fn run(mut command: Command) -> Result<Vec<u8>, CliError> {
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(CliError::Command {
+                status: output.status.to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl ContractQuerier for CliChain {
+    type Error = CliError;
+
+    fn query_contract<T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &QueryMsg,
+    ) -> Result<T, CliError> {
+        let mut command = self.command();
+        command.args(["query", "wasm", "contract-state", "smart", contract]);
+        command.arg(to_json_string(msg)?);
+        let response: SmartQueryResponse<T> = from_json(Self::run(command)?)?;
+        Ok(response.data)
+    }
+}
+
+impl ContractExecutor for CliChain {
+    // The name of the signing key in the keyring
+    type Sender = str;
+    type Response = CliTxResponse;
+
+    fn execute_contract(
+        &mut self,
+        sender: &str,
+        contract: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> Result<CliTxResponse, CliError> {
+        let mut command = self.command();
+        command.args(["tx", "wasm", "execute", contract]);
+        command.arg(to_json_string(msg)?);
+        command.args(["--from", sender, "--chain-id", &self.chain_id]);
+        command.args(["--gas", "auto", "--gas-adjustment", "1.3"]);
+        command.args(["--broadcast-mode", "sync", "--yes"]);
+        if !funds.is_empty() {
+            let amount: Vec<String> = funds.iter().map(Coin::to_string).collect();
+            command.args(["--amount", &amount.join(",")]);
+        }
+        if let Some(gas_prices) = &self.gas_prices {
+            command.args(["--gas-prices", gas_prices]);
+        }
+        if let Some(keyring_backend) = &self.keyring_backend {
+            command.args(["--keyring-backend", keyring_backend]);
+        }
+
+        let response: CliTxResponse = from_json(Self::run(command)?)?;
+        if response.code != 0 {
+            return Err(CliError::Tx {
+                txhash: response.txhash,
+                code: response.code,
+                raw_log: response.raw_log,
+            });
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_daemon_output() {
+        let response: CliTxResponse = from_json(
+            br#"{"height":"0","txhash":"ABCD","codespace":"","code":0,"data":"","raw_log":"[]","logs":[],"info":"","gas_wanted":"0","gas_used":"0","tx":null,"timestamp":"","events":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.txhash, "ABCD");
+        assert_eq!(response.code, 0);
+
+        let response: SmartQueryResponse<crate::msg::GetTxsResponse> =
+            from_json(br#"{"data":{"txs":[]}}"#).unwrap();
+        assert!(response.data.txs.is_empty());
+    }
+}
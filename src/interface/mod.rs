@@ -0,0 +1,265 @@
+// Typed client for the contract. The client is generic over the chain it talks to, so the
+// same code drives the contract in cw-multi-test, in test-tube and from bots implementing
+// `ContractQuerier` and `ContractExecutor` over their own RPC connection.
+#[cfg(any(test, feature = "bots"))]
+mod cli;
+#[cfg(any(test, feature = "multitest"))]
+mod multitest;
+#[cfg(feature = "test-tube")]
+mod test_tube;
+
+#[cfg(any(test, feature = "bots"))]
+pub use cli::{CliChain, CliError, CliTxResponse};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Uint128};
+use serde::de::DeserializeOwned;
+
+use crate::execute::{Tx, TxOrigin, TxOwner, BPS_DENOMINATOR};
+use crate::msg::{
+    DenomInfo, ExecuteMsg, GetTxsResponse, QueryMsg, SimulateFulfillResponse, StatsResponse,
+    TxRecordsResponse,
+};
+use crate::state::Config;
+
+// ContractQuerier runs smart queries against a contract
+pub trait ContractQuerier {
+    type Error;
+
+    fn query_contract<T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &QueryMsg,
+    ) -> Result<T, Self::Error>;
+}
+
+// ContractExecutor executes messages on a contract, signed by a sender
+pub trait ContractExecutor: ContractQuerier {
+    type Sender: ?Sized;
+    type Response;
+
+    fn execute_contract(
+        &mut self,
+        sender: &Self::Sender,
+        contract: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> Result<Self::Response, Self::Error>;
+}
+
+impl<C: ContractQuerier + ?Sized> ContractQuerier for &mut C {
+    type Error = C::Error;
+
+    fn query_contract<T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &QueryMsg,
+    ) -> Result<T, Self::Error> {
+        (**self).query_contract(contract, msg)
+    }
+}
+
+impl<C: ContractExecutor + ?Sized> ContractExecutor for &mut C {
+    type Sender = C::Sender;
+    type Response = C::Response;
+
+    fn execute_contract(
+        &mut self,
+        sender: &Self::Sender,
+        contract: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> Result<Self::Response, Self::Error> {
+        (**self).execute_contract(sender, contract, msg, funds)
+    }
+}
+
+// Page selects the txs after `start_after`, up to `limit` of them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Page {
+    pub start_after: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+// FillQuote is what a market maker sends to fulfill a tx, and how it is split between the
+// destination and the protocol fee
+#[cw_serde]
+pub struct FillQuote {
+    pub tx_id: u64,
+    pub funds: Coin,
+    pub protocol_fee: Coin,
+    pub destination_amount: Uint128,
+}
+
+// CatalystClient wraps a chain and the address of the contract on it
+pub struct CatalystClient<C> {
+    pub chain: C,
+    pub contract: String,
+}
+
+impl<C> CatalystClient<C> {
+    pub fn new(chain: C, contract: impl Into<String>) -> Self {
+        CatalystClient {
+            chain,
+            contract: contract.into(),
+        }
+    }
+}
+
+impl<C: ContractQuerier> CatalystClient<C> {
+    pub fn query<T: DeserializeOwned>(&self, msg: &QueryMsg) -> Result<T, C::Error> {
+        self.chain.query_contract(&self.contract, msg)
+    }
+
+    pub fn config(&self) -> Result<Config, C::Error> {
+        self.query(&QueryMsg::Config {})
+    }
+
+    pub fn pending_txs(&self, page: Page) -> Result<Vec<Tx>, C::Error> {
+        self.query(&QueryMsg::PendingTxs {
+            start_after: page.start_after,
+            limit: page.limit,
+        })
+        .map(|response: GetTxsResponse| response.txs)
+    }
+
+    pub fn fulfilled_txs(&self) -> Result<Vec<Tx>, C::Error> {
+        self.query(&QueryMsg::GetFulfilledTxs {})
+            .map(|response: GetTxsResponse| response.txs)
+    }
+
+    pub fn pending_tx(&self, tx_id: u64) -> Result<Option<Tx>, C::Error> {
+        let page = Page {
+            start_after: tx_id.checked_sub(1),
+            limit: Some(1),
+        };
+        Ok(self
+            .pending_txs(page)?
+            .into_iter()
+            .find(|tx| tx.id == tx_id))
+    }
+
+    pub fn denom(&self, denom: impl Into<String>) -> Result<DenomInfo, C::Error> {
+        self.query(&QueryMsg::Denom {
+            denom: denom.into(),
+        })
+    }
+
+    pub fn stats(&self) -> Result<StatsResponse, C::Error> {
+        self.query(&QueryMsg::Stats {})
+    }
+
+    pub fn txs_by_fulfiller(
+        &self,
+        fulfiller: impl Into<String>,
+        page: Page,
+    ) -> Result<TxRecordsResponse, C::Error> {
+        self.query(&QueryMsg::TxsByFulfiller {
+            fulfiller: fulfiller.into(),
+            start_after: page.start_after,
+            limit: page.limit,
+        })
+    }
+
+    // quote_fill returns what fulfilling a pending tx costs under the current protocol fee,
+    // or None if the tx is not pending
+    pub fn quote_fill(&self, tx_id: u64) -> Result<Option<FillQuote>, C::Error> {
+        let Some(tx) = self.pending_tx(tx_id)? else {
+            return Ok(None);
+        };
+        let config = self.config()?;
+        let fee = tx
+            .coin
+            .amount
+            .multiply_ratio(config.protocol_fee_bps, BPS_DENOMINATOR);
+        Ok(Some(FillQuote {
+            tx_id,
+            protocol_fee: Coin::new(fee.u128(), tx.coin.denom.clone()),
+            destination_amount: tx.coin.amount - fee,
+            funds: tx.coin,
+        }))
+    }
+
+    // simulate_fulfill dry-runs fulfilling the txs with the funds, without changing state
+    pub fn simulate_fulfill(
+        &self,
+        tx_ids: Vec<u64>,
+        sender: impl Into<String>,
+        funds: Vec<Coin>,
+    ) -> Result<SimulateFulfillResponse, C::Error> {
+        self.query(&QueryMsg::SimulateFulfill {
+            tx_ids,
+            sender: sender.into(),
+            funds,
+        })
+    }
+}
+
+impl<C: ContractExecutor> CatalystClient<C> {
+    pub fn execute(
+        &mut self,
+        sender: &C::Sender,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> Result<C::Response, C::Error> {
+        self.chain
+            .execute_contract(sender, &self.contract, msg, funds)
+    }
+
+    pub fn add_tx(
+        &mut self,
+        sender: &C::Sender,
+        destination_addr: impl Into<String>,
+        output_coin: Coin,
+        origin: Option<TxOrigin>,
+        owner: Option<TxOwner>,
+    ) -> Result<C::Response, C::Error> {
+        let msg = ExecuteMsg::AddTx {
+            destination_addr: destination_addr.into(),
+            output_coin,
+            origin,
+            owner,
+            signatures: None,
+        };
+        self.execute(sender, &msg, &[])
+    }
+
+    pub fn fulfill(
+        &mut self,
+        sender: &C::Sender,
+        tx_id: u64,
+        funds: &[Coin],
+    ) -> Result<C::Response, C::Error> {
+        self.execute(sender, &ExecuteMsg::FulfillTx { tx_id }, funds)
+    }
+
+    // fulfill_batch fills the txs in order, skipping those that can't be filled and
+    // refunding what is left of the funds
+    pub fn fulfill_batch(
+        &mut self,
+        sender: &C::Sender,
+        tx_ids: Vec<u64>,
+        funds: &[Coin],
+    ) -> Result<C::Response, C::Error> {
+        self.execute(sender, &ExecuteMsg::FulfillTxs { tx_ids }, funds)
+    }
+
+    pub fn expire(&mut self, sender: &C::Sender, tx_id: u64) -> Result<C::Response, C::Error> {
+        self.execute(sender, &ExecuteMsg::RemoveTx { tx_id }, &[])
+    }
+
+    // forward expires a tx of a denom settled by forwarding, handing over its cleared funds
+    // for the contract to deliver to the destination
+    pub fn forward(
+        &mut self,
+        sender: &C::Sender,
+        tx_id: u64,
+        funds: Coin,
+    ) -> Result<C::Response, C::Error> {
+        self.execute(sender, &ExecuteMsg::RemoveTx { tx_id }, &[funds])
+    }
+
+    pub fn settle(&mut self, sender: &C::Sender, tx_id: u64) -> Result<C::Response, C::Error> {
+        self.execute(sender, &ExecuteMsg::RemoveFulfilledTx { tx_id }, &[])
+    }
+}
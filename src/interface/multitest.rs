@@ -0,0 +1,30 @@
+use anyhow::Result as AnyResult;
+use cosmwasm_std::{Addr, Coin};
+use cw_multi_test::{App, AppResponse, Executor};
+use serde::de::DeserializeOwned;
+
+use super::{ContractExecutor, ContractQuerier};
+use crate::msg::{ExecuteMsg, QueryMsg};
+
+impl ContractQuerier for App {
+    type Error = anyhow::Error;
+
+    fn query_contract<T: DeserializeOwned>(&self, contract: &str, msg: &QueryMsg) -> AnyResult<T> {
+        Ok(self.wrap().query_wasm_smart(contract, msg)?)
+    }
+}
+
+impl ContractExecutor for App {
+    type Sender = Addr;
+    type Response = AppResponse;
+
+    fn execute_contract(
+        &mut self,
+        sender: &Addr,
+        contract: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        Executor::execute_contract(self, sender.clone(), Addr::unchecked(contract), msg, funds)
+    }
+}
@@ -0,0 +1,36 @@
+use cosmwasm_std::Coin;
+use osmosis_std::types::cosmwasm::wasm::v1::MsgExecuteContractResponse;
+use osmosis_test_tube::{
+    ExecuteResponse, Module, OsmosisTestApp, RunnerError, RunnerResult, SigningAccount, Wasm,
+};
+use serde::de::DeserializeOwned;
+
+use super::{ContractExecutor, ContractQuerier};
+use crate::msg::{ExecuteMsg, QueryMsg};
+
+impl ContractQuerier for OsmosisTestApp {
+    type Error = RunnerError;
+
+    fn query_contract<T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &QueryMsg,
+    ) -> RunnerResult<T> {
+        Wasm::new(self).query(contract, msg)
+    }
+}
+
+impl ContractExecutor for OsmosisTestApp {
+    type Sender = SigningAccount;
+    type Response = ExecuteResponse<MsgExecuteContractResponse>;
+
+    fn execute_contract(
+        &mut self,
+        sender: &SigningAccount,
+        contract: &str,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> RunnerResult<Self::Response> {
+        Wasm::new(&*self).execute(contract, msg, funds, sender)
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use cosmwasm_std::{Deps, Env, Order, StdResult};
+
+use crate::msg::{CheckInvariantsResponse, DenomObligations};
+use crate::outbound::open_withdrawals;
+use crate::state::{SettlementMode, State, FEE_WITHDRAWALS, STATE, TREASURY};
+use crate::tokenfactory::contract_mints;
+use crate::ContractError;
+
+fn obligations_for<'a>(
+    obligations: &'a mut BTreeMap<String, DenomObligations>,
+    denom: &str,
+) -> &'a mut DenomObligations {
+    obligations
+        .entry(denom.to_string())
+        .or_insert_with(|| DenomObligations {
+            denom: denom.to_string(),
+            ..Default::default()
+        })
+}
+
+// outstanding_obligations sums, per denom, what the contract owes others. Fills are
+// forwarded within the same tx, so at rest the contract only custodies protocol fees and
+// the funds of open outbound withdrawals, whether still locked or awaiting their refund.
+// The settlements owed to market makers are reported alongside.
+pub fn outstanding_obligations(
+    deps: Deps,
+    env: &Env,
+) -> StdResult<BTreeMap<String, DenomObligations>> {
+    let mut obligations = BTreeMap::new();
+
+    for item in TREASURY.range(deps.storage, None, None, Order::Ascending) {
+        let (denom, amount) = item?;
+        obligations_for(&mut obligations, &denom).treasury += amount;
+    }
+    for item in FEE_WITHDRAWALS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, withdrawal) = item?;
+        obligations_for(&mut obligations, &withdrawal.amount.denom).treasury +=
+            withdrawal.amount.amount;
+    }
+    for withdrawal in open_withdrawals(deps)? {
+        let denom_obligations = obligations_for(&mut obligations, &withdrawal.coin.denom);
+        if env.block.time >= withdrawal.expires_at {
+            denom_obligations.pending_refunds += withdrawal.coin.amount;
+        } else {
+            denom_obligations.locked_withdrawals += withdrawal.coin.amount;
+        }
+    }
+    let state: State = STATE.load(deps.storage)?;
+    for tx in state.fulfilled_txs {
+        obligations_for(&mut obligations, &tx.coin.denom).settlements_owed += tx.coin.amount;
+    }
+
+    Ok(obligations)
+}
+
+// order_book_violations checks the structural invariants of the order book: tx ids are
+// unique and were issued by the contract, no tx is both pending and fulfilled, and every
+// fulfilled tx records its fill.
+fn order_book_violations(state: &State) -> Vec<String> {
+    let mut violations = vec![];
+    let mut seen = BTreeSet::new();
+
+    for tx in state.pending_txs.iter().chain(state.fulfilled_txs.iter()) {
+        if !seen.insert(tx.id) {
+            violations.push(format!("tx {} appears more than once", tx.id));
+        }
+        if tx.id >= state.next_id {
+            violations.push(format!(
+                "tx {} is not below the next id {}",
+                tx.id, state.next_id
+            ));
+        }
+    }
+    for tx in state.fulfilled_txs.iter() {
+        if tx.fill.is_none() {
+            violations.push(format!("fulfilled tx {} has no fill", tx.id));
+        }
+    }
+
+    violations
+}
+
+// settlement_violations checks that the contract can pay the settlements it owes: fulfilled
+// txs settled by minting must be in a denom the contract is the tokenfactory admin of.
+fn settlement_violations(state: &State, env: &Env) -> Vec<String> {
+    state
+        .fulfilled_txs
+        .iter()
+        .filter(|tx| tx.settlement == SettlementMode::Mint && !contract_mints(env, &tx.coin.denom))
+        .map(|tx| {
+            format!(
+                "fulfilled tx {} is settled by minting {}, which the contract can't mint",
+                tx.id, tx.coin.denom
+            )
+        })
+        .collect()
+}
+
+// check_invariants compares the outstanding obligations against the contract's bank
+// balances and checks the order book, reporting every discrepancy found.
+pub fn check_invariants(deps: Deps, env: &Env) -> StdResult<CheckInvariantsResponse> {
+    let state: State = STATE.load(deps.storage)?;
+    let mut violations = order_book_violations(&state);
+    violations.extend(settlement_violations(&state, env));
+
+    let mut balances = vec![];
+    for (denom, mut obligations) in outstanding_obligations(deps, env)? {
+        obligations.total =
+            obligations.treasury + obligations.locked_withdrawals + obligations.pending_refunds;
+        obligations.bank_balance = deps
+            .querier
+            .query_balance(&env.contract.address, &denom)?
+            .amount;
+        if obligations.bank_balance < obligations.total {
+            violations.push(format!(
+                "{denom} balance {} is below outstanding obligations {}",
+                obligations.bank_balance, obligations.total
+            ));
+        }
+        balances.push(obligations);
+    }
+
+    Ok(CheckInvariantsResponse {
+        ok: violations.is_empty(),
+        balances,
+        violations,
+    })
+}
+
+// assert_invariants fails with the first violation found. It is run at the end of each
+// execute in debug builds.
+pub fn assert_invariants(deps: Deps, env: &Env) -> Result<(), ContractError> {
+    let report = check_invariants(deps, env)?;
+    match report.violations.into_iter().next() {
+        Some(reason) => Err(ContractError::InvariantViolation { reason }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{Fill, Tx};
+    use crate::state::{withdrawals, Withdrawal, WithdrawalStatus};
+    use crate::treasury::credit_treasury;
+    use cosmwasm_std::testing::{mock_dependencies_with_balance, mock_env};
+    use cosmwasm_std::{coin, coins, Addr, Uint128};
+
+    fn mock_tx(id: u64, fill: Option<Fill>) -> Tx {
+        Tx {
+            id,
+            destination_addr: "destination_addr".to_string(),
+            coin: coin(100, "token"),
+            added_height: 1,
+            fill,
+            origin: None,
+            owner: None,
+            amendments: 0,
+            settlement: SettlementMode::Module,
+            payee: None,
+        }
+    }
+
+    #[test]
+    fn test_check_invariants_ok() {
+        let mut deps = mock_dependencies_with_balance(&coins(17, "token"));
+        let env = mock_env();
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![mock_tx(0, None)],
+            fulfilled_txs: vec![mock_tx(
+                1,
+                Some(Fill {
+                    fulfiller_addr: Addr::unchecked("market_maker"),
+                    amount: Uint128::from(100u128),
+                    height: 2,
+                }),
+            )],
+            next_id: 2,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        credit_treasury(deps.as_mut().storage, &coin(10, "token")).unwrap();
+
+        // One withdrawal is still locked and the other expired, awaiting its refund
+        for (id, expires_at, amount) in [(0, 1, 3), (1, 0, 4)] {
+            let expires_at = env.block.time.plus_seconds(expires_at);
+            let withdrawal = Withdrawal {
+                id,
+                owner: Addr::unchecked("owner"),
+                coin: coin(amount, "token"),
+                destination_chain: "bitcoin".to_string(),
+                destination_addr: "bc1q".to_string(),
+                created_at: env.block.time,
+                expires_at,
+                status: WithdrawalStatus::Open,
+                maker: None,
+                payout: None,
+            };
+            withdrawals()
+                .save(deps.as_mut().storage, id, &withdrawal)
+                .unwrap();
+        }
+
+        let report = check_invariants(deps.as_ref(), &env).unwrap();
+        assert!(report.ok);
+        assert_eq!(report.balances.len(), 1);
+        assert_eq!(report.balances[0].locked_withdrawals, Uint128::from(3u128));
+        assert_eq!(report.balances[0].pending_refunds, Uint128::from(4u128));
+        assert_eq!(report.balances[0].settlements_owed, Uint128::from(100u128));
+        assert_eq!(report.balances[0].total, Uint128::from(17u128));
+        assert_eq!(report.balances[0].bank_balance, Uint128::from(17u128));
+    }
+
+    #[test]
+    fn test_check_invariants_reports_violations() {
+        let mut deps = mock_dependencies_with_balance(&coins(5, "token"));
+        let env = mock_env();
+
+        // The same tx is both pending and fulfilled, and the fulfilled copy has no fill and
+        // is settled by minting a denom the contract can't mint
+        let mut minted = mock_tx(0, None);
+        minted.settlement = SettlementMode::Mint;
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![mock_tx(0, None)],
+            fulfilled_txs: vec![minted],
+            next_id: 1,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        credit_treasury(deps.as_mut().storage, &coin(10, "token")).unwrap();
+
+        let report = check_invariants(deps.as_ref(), &env).unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.violations.len(), 4);
+
+        let err = assert_invariants(deps.as_ref(), &env).unwrap_err();
+        assert!(matches!(err, ContractError::InvariantViolation { .. }));
+    }
+}
@@ -0,0 +1,378 @@
+// Keeper for the module account side of the lifecycle. It follows deposits on the origin
+// chain, posts each as a pending tx once it has enough confirmations to be listed, and closes
+// the tx once the deposit is safe: pending txs are expired and fulfilled txs are settled.
+//
+// Deposits are tracked by their origin, which can only ever fund a single tx, so replaying
+// deposit events after a restart never posts a deposit twice.
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{from_json, to_json_vec, Coin, StdError};
+use thiserror::Error;
+
+use crate::execute::{TxOrigin, TxOwner};
+use crate::interface::{CatalystClient, ContractExecutor};
+use crate::msg::{QueryMsg, TxAttestationsResponse};
+use crate::state::SettlementMode;
+
+#[cw_serde]
+pub struct KeeperConfig {
+    pub contract: String,
+    // Confirmations after which a deposit is posted to the order book
+    pub post_confirmations: u32,
+    // Confirmations after which a deposit is final, and its tx expired or settled
+    pub safe_confirmations: u32,
+}
+
+// DepositEvent reports the latest state of a deposit on the origin chain
+#[cw_serde]
+pub struct DepositEvent {
+    pub origin: TxOrigin,
+    pub destination_addr: String,
+    pub coin: Coin,
+    // Zero once a reorg dropped the deposit from the origin chain
+    pub confirmations: u32,
+    #[serde(default)]
+    pub owner: Option<TxOwner>,
+}
+
+#[cw_serde]
+pub enum DepositStatus {
+    Seen,
+    Posted,
+    // A reorg dropped the deposit after it was posted
+    Reorged,
+    Closed,
+}
+
+#[cw_serde]
+pub struct TrackedDeposit {
+    pub deposit: DepositEvent,
+    pub status: DepositStatus,
+    pub tx_id: Option<u64>,
+}
+
+// KeeperState is everything the keeper needs to resume, keyed by `TxOrigin::key`
+#[cw_serde]
+#[derive(Default)]
+pub struct KeeperState {
+    pub deposits: BTreeMap<String, TrackedDeposit>,
+}
+
+#[cw_serde]
+pub enum KeeperAction {
+    Posted { origin: String, tx_id: u64 },
+    Expired { origin: String, tx_id: u64 },
+    Settled { origin: String, tx_id: u64 },
+    // The tx was closed by someone else, e.g. cancelled by its owner
+    Closed { origin: String, tx_id: u64 },
+    // The deposit funding the tx was dropped by a reorg, so the tx is neither expired nor
+    // settled until the deposit is confirmed again
+    Reorged { origin: String, tx_id: u64 },
+    Failed { origin: String, error: String },
+}
+
+#[derive(Error, Debug)]
+pub enum KeeperError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+
+    #[error("{0}")]
+    Std(#[from] StdError),
+}
+
+impl KeeperState {
+    // load reads the state file, starting afresh if there is none
+    pub fn load(path: &Path) -> Result<Self, KeeperError> {
+        match fs::read(path) {
+            Ok(bytes) => Ok(from_json(bytes)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(KeeperState::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // save replaces the state file atomically, so a crash never leaves it half written
+    pub fn save(&self, path: &Path) -> Result<(), KeeperError> {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, to_json_vec(self)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+// DepositSource yields the deposit events observed since the last poll
+pub trait DepositSource {
+    type Error;
+
+    fn poll(&mut self) -> Result<Vec<DepositEvent>, Self::Error>;
+}
+
+// JsonLinesSource follows a file of deposit events, one JSON object per line, as appended
+// by an origin chain watcher. A trailing line without a newline is left for the next poll.
+pub struct JsonLinesSource {
+    path: PathBuf,
+    offset: u64,
+}
+
+impl JsonLinesSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonLinesSource {
+            path: path.into(),
+            offset: 0,
+        }
+    }
+}
+
+impl DepositSource for JsonLinesSource {
+    type Error = KeeperError;
+
+    fn poll(&mut self) -> Result<Vec<DepositEvent>, KeeperError> {
+        let mut file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut bytes = vec![];
+        file.read_to_end(&mut bytes)?;
+
+        let Some(end) = bytes.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(vec![]);
+        };
+        let events = bytes[..end]
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.iter().all(u8::is_ascii_whitespace))
+            .map(from_json)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.offset += end as u64 + 1;
+        Ok(events)
+    }
+}
+
+// MockSource hands out queued batches of deposit events, one batch per poll
+#[derive(Default)]
+pub struct MockSource {
+    pub batches: VecDeque<Vec<DepositEvent>>,
+}
+
+impl DepositSource for MockSource {
+    type Error = Infallible;
+
+    fn poll(&mut self) -> Result<Vec<DepositEvent>, Infallible> {
+        Ok(self.batches.pop_front().unwrap_or_default())
+    }
+}
+
+pub struct Keeper<C> {
+    pub client: CatalystClient<C>,
+    pub config: KeeperConfig,
+    pub state: KeeperState,
+}
+
+impl<C: ContractExecutor> Keeper<C>
+where
+    C::Error: Display,
+{
+    pub fn new(chain: C, config: KeeperConfig, state: KeeperState) -> Self {
+        Keeper {
+            client: CatalystClient::new(chain, config.contract.clone()),
+            config,
+            state,
+        }
+    }
+
+    // posted_tx returns the tx funded by an origin deposit, if it was already posted
+    fn posted_tx(&self, origin: &TxOrigin) -> Result<Option<u64>, C::Error> {
+        self.client
+            .query(&QueryMsg::TxAttestations {
+                origin: origin.clone(),
+            })
+            .map(|response: TxAttestationsResponse| response.tx_id)
+    }
+
+    // is_open returns whether a tx is still pending or fulfilled
+    fn is_open(&self, tx_id: u64) -> Result<bool, C::Error> {
+        Ok(self.client.pending_tx(tx_id)?.is_some()
+            || self.client.fulfilled_txs()?.iter().any(|tx| tx.id == tx_id))
+    }
+
+    // step records the latest deposit events and moves every open deposit forward. Failures
+    // are reported and retried on the next step.
+    pub fn step(&mut self, sender: &C::Sender, events: Vec<DepositEvent>) -> Vec<KeeperAction> {
+        for event in events {
+            self.state
+                .deposits
+                .entry(event.origin.key())
+                .and_modify(|tracked| tracked.deposit.confirmations = event.confirmations)
+                .or_insert(TrackedDeposit {
+                    deposit: event,
+                    status: DepositStatus::Seen,
+                    tx_id: None,
+                });
+        }
+
+        let keys: Vec<String> = self.state.deposits.keys().cloned().collect();
+        let mut actions = vec![];
+        for key in keys {
+            if let Err(error) = self.advance(sender, &key, &mut actions) {
+                actions.push(KeeperAction::Failed {
+                    origin: key,
+                    error: error.to_string(),
+                });
+            }
+        }
+        actions
+    }
+
+    fn advance(
+        &mut self,
+        sender: &C::Sender,
+        key: &str,
+        actions: &mut Vec<KeeperAction>,
+    ) -> Result<(), C::Error> {
+        let mut tracked = self.state.deposits[key].clone();
+        let confirmations = tracked.deposit.confirmations;
+
+        // A deposit without confirmations is not on the origin chain anymore. Its tx must not
+        // be expired or settled, as neither the depositor nor the market maker has paid for it.
+        if confirmations == 0 {
+            if tracked.status == DepositStatus::Posted {
+                actions.push(KeeperAction::Reorged {
+                    origin: key.to_string(),
+                    tx_id: tracked.tx_id.unwrap_or_default(),
+                });
+                tracked.status = DepositStatus::Reorged;
+                self.state.deposits.insert(key.to_string(), tracked);
+            }
+            return Ok(());
+        }
+        if tracked.status == DepositStatus::Reorged {
+            tracked.status = DepositStatus::Posted;
+            self.state.deposits.insert(key.to_string(), tracked.clone());
+        }
+
+        if tracked.status == DepositStatus::Seen && confirmations >= self.config.post_confirmations
+        {
+            let tx_id = match self.posted_tx(&tracked.deposit.origin)? {
+                Some(tx_id) => tx_id,
+                None => {
+                    let deposit = &tracked.deposit;
+                    self.client.add_tx(
+                        sender,
+                        deposit.destination_addr.clone(),
+                        deposit.coin.clone(),
+                        Some(deposit.origin.clone()),
+                        deposit.owner.clone(),
+                    )?;
+                    let tx_id = self.posted_tx(&deposit.origin)?;
+                    // Chains that broadcast without waiting for the tx to be included report
+                    // the tx on a later step
+                    let Some(tx_id) = tx_id else {
+                        return Ok(());
+                    };
+                    actions.push(KeeperAction::Posted {
+                        origin: key.to_string(),
+                        tx_id,
+                    });
+                    tx_id
+                }
+            };
+            tracked.status = DepositStatus::Posted;
+            tracked.tx_id = Some(tx_id);
+            self.state.deposits.insert(key.to_string(), tracked.clone());
+        }
+
+        if tracked.status == DepositStatus::Posted
+            && confirmations >= self.config.safe_confirmations
+        {
+            let tx_id = tracked.tx_id.unwrap_or_default();
+            let origin = key.to_string();
+            let action = if let Some(tx) = self.client.pending_tx(tx_id)? {
+                // Txs settled by forwarding take the cleared funds along with the expiry
+                if tx.settlement == SettlementMode::Forward {
+                    self.client.forward(sender, tx_id, tx.coin)?;
+                } else {
+                    self.client.expire(sender, tx_id)?;
+                }
+                KeeperAction::Expired { origin, tx_id }
+            } else if self.client.fulfilled_txs()?.iter().any(|tx| tx.id == tx_id) {
+                self.client.settle(sender, tx_id)?;
+                KeeperAction::Settled { origin, tx_id }
+            } else {
+                KeeperAction::Closed { origin, tx_id }
+            };
+            // Chains that broadcast without waiting for the tx to be included report the tx as
+            // closed on a later step, and a close that failed on inclusion is sent again
+            if !matches!(action, KeeperAction::Closed { .. }) && self.is_open(tx_id)? {
+                return Ok(());
+            }
+            actions.push(action);
+            tracked.status = DepositStatus::Closed;
+            self.state.deposits.insert(key.to_string(), tracked);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coin;
+    use std::io::Write;
+
+    fn event(vout: u32, confirmations: u32) -> DepositEvent {
+        DepositEvent {
+            origin: TxOrigin {
+                chain: "bitcoin".to_string(),
+                tx_hash: "ab".to_string(),
+                vout,
+            },
+            destination_addr: "dest".to_string(),
+            coin: coin(1_000, "ubtc"),
+            confirmations,
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_json_lines_source_follows_appends() {
+        let dir = std::env::temp_dir().join(format!("catalyst-keeper-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("deposits.jsonl");
+        let mut file = fs::File::create(&path).unwrap();
+        let mut source = JsonLinesSource::new(&path);
+
+        let line = |event: &DepositEvent| String::from_utf8(to_json_vec(event).unwrap()).unwrap();
+        write!(file, "{}\n\n{}", line(&event(0, 1)), line(&event(1, 1))).unwrap();
+        assert_eq!(source.poll().unwrap(), vec![event(0, 1)]);
+
+        // The partial line is read once it is terminated
+        writeln!(file).unwrap();
+        assert_eq!(source.poll().unwrap(), vec![event(1, 1)]);
+        assert_eq!(source.poll().unwrap(), vec![]);
+
+        let state_path = dir.join("state.json");
+        assert_eq!(
+            KeeperState::load(&state_path).unwrap(),
+            KeeperState::default()
+        );
+        let mut state = KeeperState::default();
+        state.deposits.insert(
+            event(0, 1).origin.key(),
+            TrackedDeposit {
+                deposit: event(0, 1),
+                status: DepositStatus::Posted,
+                tx_id: Some(1),
+            },
+        );
+        state.save(&state_path).unwrap();
+        assert_eq!(KeeperState::load(&state_path).unwrap(), state);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,12 +1,39 @@
+pub mod attestation;
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod contract;
+pub mod denoms;
+pub mod destination;
 mod error;
+pub mod events;
 pub mod execute;
 pub mod helpers;
+pub mod history;
+pub mod interface;
+pub mod invariants;
+#[cfg(any(test, feature = "bots"))]
+pub mod keeper;
+#[cfg(any(test, feature = "bots"))]
+pub mod maker;
 pub mod msg;
+pub mod outbound;
 pub mod query;
+pub mod roles;
+pub mod signatures;
+#[cfg(any(test, feature = "sim"))]
+pub mod sim;
+pub mod snapshot;
+pub mod spv;
 pub mod state;
+pub mod stats;
+pub mod tokenfactory;
+pub mod treasury;
 
-pub use crate::error::ContractError;
+pub use crate::error::{ContractError, ErrorCode};
 
 #[cfg(test)]
+mod multitest;
+#[cfg(all(test, feature = "test-tube"))]
 mod test_tube;
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,295 @@
+// Reference market maker. Each poll walks the order book, applies the maker's rules for the
+// tx's denom and fills the txs that pass them. The maker fronts the full tx amount and is
+// repaid once the tx settles, so its exposure is the amount of its fills awaiting
+// settlement or execution.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Coin, Decimal, Uint128};
+
+use crate::execute::{Tx, BPS_DENOMINATOR};
+use crate::interface::{CatalystClient, ContractExecutor, Page};
+
+#[cw_serde]
+pub struct MakerConfig {
+    pub contract: String,
+    // The maker's address, whose fills count towards its exposure
+    pub address: String,
+    pub rules: Vec<DenomRule>,
+    // Txs requested per page of the order book
+    #[serde(default)]
+    pub page_limit: Option<u32>,
+    // Polls a submitted fill is awaited before it is assumed to have failed and the tx is
+    // filled again. Defaults to DEFAULT_IN_FLIGHT_POLLS.
+    #[serde(default)]
+    pub in_flight_polls: Option<u32>,
+}
+
+pub const DEFAULT_IN_FLIGHT_POLLS: u32 = 10;
+
+// DenomRule is what the maker is willing to fill in a denom. Denoms without a rule are
+// never filled.
+#[cw_serde]
+pub struct DenomRule {
+    pub denom: String,
+    pub min_amount: Uint128,
+    pub max_amount: Uint128,
+    // Maximum amount of the maker's fills awaiting settlement
+    pub max_exposure: Uint128,
+    // Only fill txs whose origin deposit was reported by the poster
+    #[serde(default)]
+    pub require_origin: bool,
+    #[serde(default)]
+    pub pricing: Option<Pricing>,
+}
+
+// Pricing is what a fill in a denom is worth to the maker. The maker is repaid the amount it
+// fronts at par, so a fill costs it the fee of the fill tx, which must stay within a share
+// of the value of the fill.
+#[cw_serde]
+pub struct Pricing {
+    // Value of one unit of the denom, in units of the denom fees are paid in
+    pub price: Decimal,
+    // Fee paid for a fill, in units of the denom fees are paid in
+    pub fill_cost: Uint128,
+    // Maximum share of the value of a fill spent on its fee, in basis points
+    pub max_cost_bps: u64,
+}
+
+#[cw_serde]
+pub enum SkipReason {
+    NoRule,
+    BelowMinimum,
+    AboveMaximum,
+    MissingOrigin,
+    Unprofitable,
+    ExposureLimit,
+}
+
+#[cw_serde]
+pub enum Outcome {
+    Filled { tx_id: u64, coin: Coin },
+    Skipped { tx_id: u64, reason: SkipReason },
+    Failed { tx_id: u64, error: String },
+}
+
+// decide returns why a tx should not be filled, or None if it should, given the maker's
+// current exposure in the tx's denom
+pub fn decide(rules: &[DenomRule], tx: &Tx, exposure: Uint128) -> Option<SkipReason> {
+    let Some(rule) = rules.iter().find(|rule| rule.denom == tx.coin.denom) else {
+        return Some(SkipReason::NoRule);
+    };
+    if tx.coin.amount < rule.min_amount {
+        return Some(SkipReason::BelowMinimum);
+    }
+    if tx.coin.amount > rule.max_amount {
+        return Some(SkipReason::AboveMaximum);
+    }
+    if rule.require_origin && tx.origin.is_none() {
+        return Some(SkipReason::MissingOrigin);
+    }
+    if let Some(pricing) = &rule.pricing {
+        let value = tx.coin.amount * pricing.price;
+        if pricing.fill_cost > value.multiply_ratio(pricing.max_cost_bps, BPS_DENOMINATOR) {
+            return Some(SkipReason::Unprofitable);
+        }
+    }
+    if exposure + tx.coin.amount > rule.max_exposure {
+        return Some(SkipReason::ExposureLimit);
+    }
+    None
+}
+
+// InFlight is a fill submitted for a tx that was still pending at the last poll. Broadcasts
+// may return before the fill is executed, so it is not submitted again until it times out.
+struct InFlight {
+    coin: Coin,
+    polls: u32,
+}
+
+pub struct Maker<C> {
+    pub client: CatalystClient<C>,
+    pub config: MakerConfig,
+    in_flight: BTreeMap<u64, InFlight>,
+}
+
+impl<C: ContractExecutor> Maker<C>
+where
+    C::Error: Display,
+{
+    pub fn new(chain: C, config: MakerConfig) -> Self {
+        Maker {
+            client: CatalystClient::new(chain, config.contract.clone()),
+            config,
+            in_flight: BTreeMap::new(),
+        }
+    }
+
+    // exposure sums the maker's fills awaiting settlement, per denom
+    pub fn exposure(&self) -> Result<BTreeMap<String, Uint128>, C::Error> {
+        let mut exposure: BTreeMap<String, Uint128> = BTreeMap::new();
+        for tx in self.client.fulfilled_txs()? {
+            let Some(fill) = &tx.fill else {
+                continue;
+            };
+            if fill.fulfiller_addr.as_str() == self.config.address {
+                *exposure.entry(tx.coin.denom).or_default() += fill.amount;
+            }
+        }
+        Ok(exposure)
+    }
+
+    fn pending_txs(&self) -> Result<Vec<Tx>, C::Error> {
+        let mut txs = vec![];
+        let mut start_after = None;
+        loop {
+            let page = self.client.pending_txs(Page {
+                start_after,
+                limit: self.config.page_limit,
+            })?;
+            let Some(last) = page.last() else {
+                return Ok(txs);
+            };
+            start_after = Some(last.id);
+            txs.extend(page);
+        }
+    }
+
+    // poll makes one pass over the order book. Query errors abort the pass, while a failed
+    // fill is reported and the pass moves on to the next tx.
+    pub fn poll(&mut self, sender: &C::Sender) -> Result<Vec<Outcome>, C::Error> {
+        // The order book is read first, so a fill executed in between counts twice rather
+        // than not at all
+        let pending = self.pending_txs()?;
+        let mut exposure = self.exposure()?;
+
+        // Fills that failed on execution leave their tx pending, and are given up on after
+        // the configured number of polls so the tx is filled again
+        let timeout = self
+            .config
+            .in_flight_polls
+            .unwrap_or(DEFAULT_IN_FLIGHT_POLLS);
+        self.in_flight.retain(|id, fill| {
+            fill.polls += 1;
+            fill.polls <= timeout && pending.iter().any(|tx| tx.id == *id)
+        });
+        for fill in self.in_flight.values() {
+            *exposure.entry(fill.coin.denom.clone()).or_default() += fill.coin.amount;
+        }
+
+        let mut outcomes = vec![];
+        for tx in pending {
+            if self.in_flight.contains_key(&tx.id) {
+                continue;
+            }
+            let denom_exposure = exposure.entry(tx.coin.denom.clone()).or_default();
+            if let Some(reason) = decide(&self.config.rules, &tx, *denom_exposure) {
+                outcomes.push(Outcome::Skipped {
+                    tx_id: tx.id,
+                    reason,
+                });
+                continue;
+            }
+            match self
+                .client
+                .fulfill(sender, tx.id, std::slice::from_ref(&tx.coin))
+            {
+                Ok(_) => {
+                    *denom_exposure += tx.coin.amount;
+                    self.in_flight.insert(
+                        tx.id,
+                        InFlight {
+                            coin: tx.coin.clone(),
+                            polls: 0,
+                        },
+                    );
+                    outcomes.push(Outcome::Filled {
+                        tx_id: tx.id,
+                        coin: tx.coin,
+                    });
+                }
+                Err(err) => outcomes.push(Outcome::Failed {
+                    tx_id: tx.id,
+                    error: err.to_string(),
+                }),
+            }
+        }
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::TxOrigin;
+    use crate::state::SettlementMode;
+    use cosmwasm_std::coin;
+
+    fn tx(amount: u128, origin: bool) -> Tx {
+        Tx {
+            id: 1,
+            destination_addr: "dest".to_string(),
+            coin: coin(amount, "ubtc"),
+            added_height: 0,
+            fill: None,
+            origin: origin.then(|| TxOrigin {
+                chain: "bitcoin".to_string(),
+                tx_hash: "ab".to_string(),
+                vout: 0,
+            }),
+            owner: None,
+            amendments: 0,
+            settlement: SettlementMode::Module,
+            payee: None,
+        }
+    }
+
+    #[test]
+    fn test_decide_applies_rules() {
+        let rules = vec![DenomRule {
+            denom: "ubtc".to_string(),
+            min_amount: Uint128::new(100),
+            max_amount: Uint128::new(1_000),
+            max_exposure: Uint128::new(1_500),
+            require_origin: true,
+            pricing: None,
+        }];
+        let zero = Uint128::zero();
+
+        assert_eq!(decide(&rules, &tx(500, true), zero), None);
+        assert_eq!(
+            decide(&rules, &tx(99, true), zero),
+            Some(SkipReason::BelowMinimum)
+        );
+        assert_eq!(
+            decide(&rules, &tx(1_001, true), zero),
+            Some(SkipReason::AboveMaximum)
+        );
+        assert_eq!(
+            decide(&rules, &tx(500, false), zero),
+            Some(SkipReason::MissingOrigin)
+        );
+        assert_eq!(
+            decide(&rules, &tx(500, true), Uint128::new(1_001)),
+            Some(SkipReason::ExposureLimit)
+        );
+        assert_eq!(decide(&rules, &tx(500, true), Uint128::new(1_000)), None);
+        assert_eq!(decide(&[], &tx(500, true), zero), Some(SkipReason::NoRule));
+
+        // At 0.5 per unit a fill of 400 is worth 200, of which a fee of 10 is 5%
+        let rules = vec![DenomRule {
+            pricing: Some(Pricing {
+                price: Decimal::percent(50),
+                fill_cost: Uint128::new(10),
+                max_cost_bps: 500,
+            }),
+            ..rules[0].clone()
+        }];
+        assert_eq!(decide(&rules, &tx(400, true), zero), None);
+        assert_eq!(
+            decide(&rules, &tx(399, true), zero),
+            Some(SkipReason::Unprofitable)
+        );
+    }
+}
@@ -1,11 +1,35 @@
-use crate::execute::Tx;
+use crate::error::ErrorCode;
+use crate::execute::{Tx, TxOrigin, TxOwner, TxStatus};
+use crate::signatures::{OrderSignature, OwnerAction, OwnerActionPayload, SignedOrderPayload};
+use crate::snapshot::{ExportCursor, StateExport};
+use crate::spv::SpvAnchor;
+use crate::state::{
+    AttestationConflict, AttestedTx, DenomMetadata, DenomStats, DestinationFormat,
+    DestinationRoute, FeeWithdrawal, MakerStats, PendingTimelock, PosterScope, Signer, SignerSet,
+    SpvConfig, TxRecord, Withdrawal,
+};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Coin;
+use cosmwasm_std::{Addr, Binary, Coin, CosmosMsg, Decimal, HexBinary, Uint128};
 
 /// Message type for `instantiate` entry_point
 #[cw_serde]
 pub struct InstantiateMsg {
     pub module_account: String,
+    // Defaults to the instantiator
+    pub admin: Option<String>,
+    // Number of blocks settled and expired txs are kept in the tx history,
+    // defaults to roughly one week of blocks
+    pub history_retention_blocks: Option<u64>,
+    // Defaults to no protocol fee
+    pub protocol_fee_bps: Option<u64>,
+    // Defaults to one day
+    pub withdrawal_timelock_seconds: Option<u64>,
+    // Defaults to only accepting accounts on this chain as destinations
+    pub foreign_destination_formats: Option<Vec<DestinationFormat>>,
+    // Defaults to one day
+    pub outbound_timeout_seconds: Option<u64>,
+    // Defaults to no routes, so funds are only delivered to accounts on this chain
+    pub destination_routes: Option<Vec<DestinationRoute>>,
 }
 
 /// Message type for `execute` entry_point
@@ -14,18 +38,164 @@ pub enum ExecuteMsg {
     AddTx {
         destination_addr: String,
         output_coin: Coin,
+        origin: Option<TxOrigin>,
+        // The depositor, who may cancel the tx or change its destination while it is pending
+        owner: Option<TxOwner>,
+        // Signatures from the signer set over the order. When set, anyone may relay the
+        // order and the origin is required. Signed orders don't need attestations, even
+        // while attestation is enabled.
+        signatures: Option<Vec<OrderSignature>>,
     },
     FulfillTx {
         tx_id: u64,
     },
+    // Fulfills the txs in order, each drawing its coin from the funds. Txs that can't be
+    // filled are skipped and the funds left over are refunded to the sender.
+    FulfillTxs {
+        tx_ids: Vec<u64>,
+    },
     RemoveTx {
         tx_id: u64,
     },
+    RemoveFulfilledTx {
+        tx_id: u64,
+    },
+    // Cancels a pending tx, sent by its owner or relayed with the owner's signature
+    CancelTx {
+        tx_id: u64,
+        signature: Option<Binary>,
+    },
+    // Changes the destination of a pending tx, sent by its owner or relayed with the
+    // owner's signature
+    AmendTx {
+        tx_id: u64,
+        destination_addr: String,
+        signature: Option<Binary>,
+    },
+    UpdateConfig {
+        admin: Option<String>,
+        history_retention_blocks: Option<u64>,
+        protocol_fee_bps: Option<u64>,
+        withdrawal_timelock_seconds: Option<u64>,
+        // Replaces the accepted foreign destination formats
+        foreign_destination_formats: Option<Vec<DestinationFormat>>,
+        outbound_timeout_seconds: Option<u64>,
+        // Replaces the routes delivering funds to foreign destinations
+        destination_routes: Option<Vec<DestinationRoute>>,
+    },
+    // Queue a withdrawal of protocol fees, which can be executed once the timelock has passed
+    WithdrawFees {
+        to: String,
+        amount: Coin,
+    },
+    ExecuteFeeWithdrawal {
+        id: u64,
+    },
+    CancelFeeWithdrawal {
+        id: u64,
+    },
+    // Register a poster, or replace its scope, allowing it to add, expire and settle txs
+    SetPoster {
+        address: String,
+        scope: PosterScope,
+    },
+    // Registers a denom orders may be placed in, or updates its metadata
+    SetDenom {
+        denom: String,
+        metadata: DenomMetadata,
+    },
+    RemoveDenom {
+        denom: String,
+    },
+    // Creates the tokenfactory denom factory/{contract}/{subdenom}, which the contract mints
+    // bridged funds in on settlement
+    CreateBridgedDenom {
+        subdenom: String,
+        metadata: DenomMetadata,
+    },
+    RemovePoster {
+        address: String,
+    },
+    // Replace the attester set and threshold, a threshold of zero disables attestation mode
+    ConfigureAttestation {
+        attesters: Vec<String>,
+        threshold: u32,
+    },
+    // Attest to a deposit on the origin chain, the tx is added once the threshold agrees
+    AttestTx {
+        destination_addr: String,
+        output_coin: Coin,
+        origin: TxOrigin,
+        owner: Option<TxOwner>,
+    },
+    // Attest that the bridged funds of a fulfilled tx have cleared, the tx is settled once
+    // the threshold agrees
+    // Replaces the signer set authorizing signed orders, a threshold of zero disables them
+    UpdateSignerSet {
+        signers: Vec<Signer>,
+        threshold: u32,
+    },
+    // Enables SPV settlement, the anchor is required the first time
+    ConfigureSpv {
+        config: SpvConfig,
+        anchor: Option<SpvAnchor>,
+    },
+    // Extends the SPV header chain, callable by anyone
+    SubmitHeaders {
+        headers: Vec<HexBinary>,
+    },
+    // Settles a fulfilled tx by proving its origin deposit was confirmed on the origin chain.
+    // Headers are stored before the proof is checked, so the confirmations can be relayed
+    // in the same message.
+    SettleWithProof {
+        tx_id: u64,
+        headers: Vec<HexBinary>,
+        // Hash of the block containing the deposit, in internal byte order
+        block_hash: HexBinary,
+        // The deposit tx serialized without witness data
+        raw_tx: HexBinary,
+        tx_index: u32,
+        merkle_proof: Vec<HexBinary>,
+    },
+    AttestSettlement {
+        tx_id: u64,
+    },
+    // Locks the single coin sent until a market maker delivers it to the destination on
+    // the external chain, or the withdrawal times out and is refunded
+    RequestWithdrawal {
+        destination_chain: String,
+        destination_addr: String,
+    },
+    // Records the payout a market maker made on the destination chain to deliver a withdrawal
+    ClaimWithdrawal {
+        id: u64,
+        payout: TxOrigin,
+    },
+    // Confirms the maker's payout, releasing the locked funds to it
+    ConfirmWithdrawal {
+        id: u64,
+        maker: String,
+    },
+    // Attest that the maker paid out the withdrawal, the locked funds are released once the
+    // threshold agrees
+    AttestWithdrawal {
+        id: u64,
+        maker: String,
+    },
+    // Returns the locked funds of an expired withdrawal to its owner, callable by anyone
+    RefundWithdrawal {
+        id: u64,
+    },
 }
 
 /// Message type for `migrate` entry_point
 #[cw_serde]
-pub enum MigrateMsg {}
+pub enum MigrateMsg {
+    // Seeds the contract with a page of another deployment's `ExportState`, large exports
+    // are imported over several migrations. The contract must already hold the funds
+    // backing the imported fees and withdrawals.
+    ImportState { export: StateExport },
+}
 
 /// Message type for `query` entry_point
 #[cw_serde]
@@ -36,9 +206,340 @@ pub enum QueryMsg {
 
     #[returns(GetTxsResponse)]
     GetFulfilledTxs {},
+
+    // Pending txs ordered by id, a page at a time
+    #[returns(GetTxsResponse)]
+    PendingTxs {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(StatsResponse)]
+    Stats {},
+
+    #[returns(crate::state::Config)]
+    Config {},
+
+    // Protocol fees accounted for by the contract, reconciled against its bank balances
+    #[returns(TreasuryResponse)]
+    Treasury {},
+
+    // Outstanding obligations per denom compared with the contract's bank balances,
+    // along with any order book invariant violations
+    #[returns(CheckInvariantsResponse)]
+    CheckInvariants {},
+
+    // The denoms orders may be placed in
+    #[returns(DenomsResponse)]
+    Denoms {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(DenomInfo)]
+    Denom { denom: String },
+
+    // The module account and the registered posters with their scopes
+    #[returns(PostersResponse)]
+    Posters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    #[returns(AttestationConfigResponse)]
+    AttestationConfig {},
+
+    #[returns(SpvStatusResponse)]
+    SpvStatus {},
+
+    #[returns(SignerSetResponse)]
+    SignerSet {},
+
+    // The digest the signer set signs to authorize an order with the current signer set
+    #[returns(SignedOrderDigestResponse)]
+    SignedOrderDigest {
+        destination_addr: String,
+        output_coin: Coin,
+        origin: TxOrigin,
+        owner: Option<TxOwner>,
+    },
+
+    // The digest the owner of a pending tx signs to authorize a change to it
+    #[returns(OwnerActionDigestResponse)]
+    OwnerActionDigest { tx_id: u64, action: OwnerAction },
+
+    // Attestations submitted for an origin deposit
+    #[returns(TxAttestationsResponse)]
+    TxAttestations { origin: TxOrigin },
+
+    // Origin deposits attesters disagreed on, keyed by `TxOrigin::key`
+    #[returns(AttestationConflictsResponse)]
+    AttestationConflicts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+
+    // Txs fulfilled by the given market maker, including settled txs still in the history
+    #[returns(TxRecordsResponse)]
+    TxsByFulfiller {
+        fulfiller: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // Txs bound for the given destination, including settled and expired txs still in the history
+    #[returns(TxRecordsResponse)]
+    TxsByDestination {
+        destination: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // An outbound withdrawal along with the payouts makers claimed for it
+    #[returns(WithdrawalResponse)]
+    Withdrawal { id: u64 },
+
+    // A page of the canonical snapshot of the contract's state, to reconcile against
+    // off-chain records or seed a new deployment with `ImportState`
+    #[returns(StateExport)]
+    ExportState {
+        start_after: Option<ExportCursor>,
+        limit: Option<u32>,
+    },
+
+    #[returns(WithdrawalsResponse)]
+    Withdrawals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    #[returns(WithdrawalsResponse)]
+    WithdrawalsByOwner {
+        owner: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+
+    // Dry run of fulfilling the given txs in order, as `sender` with `funds`, reporting what
+    // each fill would do or the error it would fail with. A single tx is checked exactly as
+    // `FulfillTx` would check it, while a batch is run as `FulfillTxs`.
+    #[returns(SimulateFulfillResponse)]
+    SimulateFulfill {
+        tx_ids: Vec<u64>,
+        sender: String,
+        funds: Vec<Coin>,
+    },
+
+    // The stable code of every error, as reported at the start of error messages
+    #[returns(ErrorCodesResponse)]
+    ErrorCodes {},
 }
 
 #[cw_serde]
 pub struct GetTxsResponse {
     pub txs: Vec<Tx>,
 }
+
+#[cw_serde]
+pub struct TreasuryResponse {
+    pub balances: Vec<TreasuryBalance>,
+    pub withdrawals: Vec<FeeWithdrawal>,
+    // A shorter withdrawal timelock waiting for the current one to pass
+    pub pending_timelock: Option<PendingTimelock>,
+}
+
+#[cw_serde]
+pub struct TreasuryBalance {
+    pub denom: String,
+    // Fees available for withdrawal
+    pub available: Uint128,
+    // Fees reserved by queued withdrawals
+    pub queued: Uint128,
+    pub bank_balance: Uint128,
+    // Amount by which the bank balance exceeds or falls short of the accounted fees
+    pub surplus: Uint128,
+    pub deficit: Uint128,
+}
+
+#[cw_serde]
+pub struct AttestationConfigResponse {
+    pub threshold: u32,
+    pub attesters: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct SpvStatusResponse {
+    pub config: Option<SpvConfig>,
+    // Hash of the best known block, in the reversed hex encoding used by explorers
+    pub tip_hash: Option<String>,
+    pub tip_height: Option<u64>,
+}
+
+#[cw_serde]
+pub struct SignerSetResponse {
+    // None while signed orders are disabled
+    pub signer_set: Option<SignerSet>,
+}
+
+#[cw_serde]
+pub struct SignedOrderDigestResponse {
+    pub payload: SignedOrderPayload,
+    pub digest: Binary,
+}
+
+#[cw_serde]
+pub struct OwnerActionDigestResponse {
+    pub payload: OwnerActionPayload,
+    pub digest: Binary,
+}
+
+#[cw_serde]
+pub struct TxAttestationsResponse {
+    pub attestations: Vec<(Addr, AttestedTx)>,
+    // The tx the origin deposit funds, once the threshold has been reached
+    pub tx_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct AttestationConflictsResponse {
+    pub conflicts: Vec<AttestationConflict>,
+}
+
+#[cw_serde]
+pub struct PostersResponse {
+    pub module_account: String,
+    pub posters: Vec<PosterInfo>,
+}
+
+#[cw_serde]
+pub struct DenomsResponse {
+    pub denoms: Vec<DenomInfo>,
+}
+
+#[cw_serde]
+pub struct DenomInfo {
+    pub denom: String,
+    pub metadata: DenomMetadata,
+}
+
+#[cw_serde]
+pub struct PosterInfo {
+    pub address: Addr,
+    pub scope: PosterScope,
+}
+
+#[cw_serde]
+pub struct CheckInvariantsResponse {
+    pub ok: bool,
+    pub balances: Vec<DenomObligations>,
+    pub violations: Vec<String>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct DenomObligations {
+    pub denom: String,
+    // Protocol fees, including queued withdrawals
+    pub treasury: Uint128,
+    // Funds locked by open outbound withdrawals that have not expired
+    pub locked_withdrawals: Uint128,
+    // Funds of expired withdrawals not refunded yet, owed to their owner or to a maker whose
+    // claim is still confirmed
+    pub pending_refunds: Uint128,
+    // Fulfilled txs awaiting settlement, owed to their market makers. Those of mint denoms
+    // are minted by the contract and the others paid by the module account, so they are
+    // not part of the total the contract's balance must cover.
+    pub settlements_owed: Uint128,
+    pub total: Uint128,
+    pub bank_balance: Uint128,
+}
+
+#[cw_serde]
+pub struct WithdrawalResponse {
+    pub withdrawal: Withdrawal,
+    pub claims: Vec<WithdrawalClaim>,
+}
+
+#[cw_serde]
+pub struct WithdrawalClaim {
+    pub maker: Addr,
+    pub payout: TxOrigin,
+}
+
+#[cw_serde]
+pub struct WithdrawalsResponse {
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+#[cw_serde]
+pub struct TxRecordsResponse {
+    pub records: Vec<TxRecord>,
+}
+
+#[cw_serde]
+pub struct StatsResponse {
+    pub denoms: Vec<DenomStatsResponse>,
+    pub makers: Vec<MakerStatsResponse>,
+    pub status_counts: StatusCounts,
+}
+
+#[cw_serde]
+pub struct DenomStatsResponse {
+    pub denom: String,
+    pub stats: DenomStats,
+    pub average_fill_blocks: Option<Decimal>,
+}
+
+#[cw_serde]
+pub struct MakerStatsResponse {
+    pub maker: Addr,
+    pub denom: String,
+    pub stats: MakerStats,
+}
+
+// StatusCounts holds the number of txs currently open in each store, as well as
+// the number of txs that have ever reached a terminal status.
+#[cw_serde]
+pub struct StatusCounts {
+    pub pending: u64,
+    pub fulfilled: u64,
+    pub settled: u64,
+    pub expired: u64,
+    pub cancelled: u64,
+}
+
+#[cw_serde]
+pub struct ErrorCodesResponse {
+    pub codes: Vec<ErrorCodeInfo>,
+}
+
+#[cw_serde]
+pub struct ErrorCodeInfo {
+    pub code: u32,
+    pub error: ErrorCode,
+}
+
+#[cw_serde]
+pub struct SimulateFulfillResponse {
+    pub fills: Vec<SimulatedFill>,
+    // Funds `FulfillTxs` would refund once the successful fills took their coin. Always
+    // empty for a single tx, as `FulfillTx` rejects funds that don't match the tx.
+    pub refund: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct SimulatedFill {
+    pub tx_id: u64,
+    // The status the tx would end up in, or its current status if the fill would fail
+    pub status: Option<TxStatus>,
+    pub funds: Option<Coin>,
+    pub protocol_fee: Option<Coin>,
+    pub messages: Vec<CosmosMsg>,
+    pub error: Option<SimulatedError>,
+}
+
+#[cw_serde]
+pub struct SimulatedError {
+    pub code: ErrorCode,
+    pub message: String,
+}
@@ -0,0 +1,3 @@
+mod model;
+mod suite;
+mod tests;
@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use cosmwasm_std::{Addr, Binary, Empty, Response, StdError, StdResult, Uint128};
+use cw_multi_test::{ContractWrapper, Executor};
+use proptest::prelude::*;
+
+use super::suite::{Suite, DENOM};
+use crate::destination::BridgeExecuteMsg;
+use crate::msg::{CheckInvariantsResponse, ExecuteMsg, QueryMsg, StatsResponse, TreasuryResponse};
+use crate::state::{DestinationFormat, DestinationRoute, RouteAction};
+
+const MAKERS: usize = 3;
+// Tx ids are drawn from a slightly larger range than can be created, so operations also
+// target unknown ids
+const MAX_TX_ID: u64 = 12;
+// The suite deploys the contract with a 1% protocol fee
+const PROTOCOL_FEE_BPS: u128 = 100;
+// Foreign destination routed to a bridge that rejects every transfer
+const BRIDGED_DESTINATION: &str = "0x00112233445566778899aabbccddeeff00112233";
+
+#[derive(Clone, Debug)]
+enum Op {
+    Add {
+        amount: u128,
+    },
+    // A tx to BRIDGED_DESTINATION, whose fills fail on the payout to the bridge
+    AddBridged {
+        amount: u128,
+    },
+    // An inexact fill sends one more than the tx amount and is rejected. A maker without
+    // the funds fails the transfer, reverting the fill before any reply.
+    Fulfill {
+        tx_id: u64,
+        maker: usize,
+        exact: bool,
+    },
+    Remove {
+        tx_id: u64,
+    },
+    Settle {
+        tx_id: u64,
+    },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1u128..5_000).prop_map(|amount| Op::Add { amount }),
+        (1u128..5_000).prop_map(|amount| Op::AddBridged { amount }),
+        (1..MAX_TX_ID, 0..MAKERS, prop::bool::weighted(0.9)).prop_map(|(tx_id, maker, exact)| {
+            Op::Fulfill {
+                tx_id,
+                maker,
+                exact,
+            }
+        }),
+        (1..MAX_TX_ID).prop_map(|tx_id| Op::Remove { tx_id }),
+        (1..MAX_TX_ID).prop_map(|tx_id| Op::Settle { tx_id }),
+    ]
+}
+
+// Model is the reference implementation of the order book and the bank balances it moves
+#[derive(Debug, Default)]
+struct Model {
+    next_id: u64,
+    pending: BTreeMap<u64, u128>,
+    fulfilled: BTreeMap<u64, u128>,
+    bridged: BTreeSet<u64>,
+    balances: BTreeMap<Addr, u128>,
+    // Protocol fees credited to the treasury and fills recorded in the stats
+    fees: u128,
+    fills: u64,
+}
+
+impl Model {
+    fn balance(&mut self, address: &Addr) -> &mut u128 {
+        self.balances.entry(address.clone()).or_default()
+    }
+
+    // apply returns whether the contract should accept the operation, updating the model
+    // if so
+    fn apply(&mut self, op: &Op, makers: &[Addr], dest: &Addr, contract: &Addr) -> bool {
+        match *op {
+            Op::Add { amount } => {
+                self.pending.insert(self.next_id, amount);
+                self.next_id += 1;
+                true
+            }
+            Op::AddBridged { amount } => {
+                self.bridged.insert(self.next_id);
+                self.apply(&Op::Add { amount }, makers, dest, contract)
+            }
+            Op::Fulfill {
+                tx_id,
+                maker,
+                exact,
+            } => {
+                let Some(&amount) = self.pending.get(&tx_id) else {
+                    return false;
+                };
+                let maker = &makers[maker];
+                if !exact || *self.balance(maker) < amount || self.bridged.contains(&tx_id) {
+                    return false;
+                }
+                let fee = amount * PROTOCOL_FEE_BPS / 10_000;
+                *self.balance(maker) -= amount;
+                *self.balance(dest) += amount - fee;
+                *self.balance(contract) += fee;
+                self.pending.remove(&tx_id);
+                self.fulfilled.insert(tx_id, amount);
+                self.fees += fee;
+                self.fills += 1;
+                true
+            }
+            Op::Remove { tx_id } => self.pending.remove(&tx_id).is_some(),
+            Op::Settle { tx_id } => self.fulfilled.remove(&tx_id).is_some(),
+        }
+    }
+}
+
+fn run(ops: Vec<Op>, maker_balances: Vec<u128>) -> Result<(), TestCaseError> {
+    let mut suite = Suite::new();
+    let dest = suite.destination("dest");
+    let bridged_dest = suite.destination(BRIDGED_DESTINATION);
+    let contract = suite.contract.clone();
+    let admin = suite.admin.clone();
+
+    let bridge_code = suite.app.store_code(Box::new(ContractWrapper::new(
+        |_, _, _, _: BridgeExecuteMsg| -> StdResult<Response> {
+            Err(StdError::generic_err("transfer rejected"))
+        },
+        |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::new()) },
+        |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+    )));
+    let bridge = suite
+        .app
+        .instantiate_contract(bridge_code, admin.clone(), &Empty {}, &[], "bridge", None)
+        .unwrap();
+    suite
+        .execute(
+            &admin,
+            &ExecuteMsg::UpdateConfig {
+                admin: None,
+                history_retention_blocks: None,
+                protocol_fee_bps: None,
+                withdrawal_timelock_seconds: None,
+                foreign_destination_formats: None,
+                outbound_timeout_seconds: None,
+                destination_routes: Some(vec![DestinationRoute {
+                    format: DestinationFormat::Hex {
+                        prefix: "0x".to_string(),
+                        length: 20,
+                    },
+                    action: RouteAction::Bridge {
+                        contract: bridge.to_string(),
+                    },
+                }]),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let mut model = Model {
+        next_id: 1,
+        ..Default::default()
+    };
+
+    let mut makers = vec![];
+    for (i, balance) in maker_balances.into_iter().enumerate() {
+        let maker = suite.maker(&format!("maker{i}"), balance);
+        *model.balance(&maker) = balance;
+        makers.push(maker);
+    }
+    let accounts: Vec<Addr> = makers
+        .iter()
+        .chain([&dest, &bridged_dest, &bridge, &contract])
+        .cloned()
+        .collect();
+    let supply: u128 = model.balances.values().sum();
+
+    for op in ops {
+        let result = match op {
+            Op::Add { amount } => suite.add_tx(&dest, amount).map(|_| ()),
+            Op::AddBridged { amount } => suite.add_tx(&bridged_dest, amount).map(|_| ()),
+            Op::Fulfill {
+                tx_id,
+                maker,
+                exact,
+            } => {
+                let amount = suite
+                    .pending_txs()
+                    .iter()
+                    .find(|tx| tx.id == tx_id)
+                    .map_or(1, |tx| tx.coin.amount.u128());
+                let sent = if exact { amount } else { amount + 1 };
+                suite
+                    .fulfill(&makers[maker].clone(), tx_id, sent)
+                    .map(|_| ())
+            }
+            Op::Remove { tx_id } => suite.expire(tx_id).map(|_| ()),
+            Op::Settle { tx_id } => suite.settle(tx_id).map(|_| ()),
+        };
+        let expected = model.apply(&op, &makers, &dest, &contract);
+        prop_assert_eq!(result.is_ok(), expected, "{:?}: {:?}", op, result);
+
+        // Every tx is in exactly one store, and ids are never reused
+        let pending: Vec<u64> = suite.pending_txs().iter().map(|tx| tx.id).collect();
+        let fulfilled: Vec<u64> = suite.fulfilled_txs().iter().map(|tx| tx.id).collect();
+        let ids: BTreeSet<u64> = pending.iter().chain(fulfilled.iter()).copied().collect();
+        prop_assert_eq!(ids.len(), pending.len() + fulfilled.len());
+        prop_assert!(ids.iter().all(|id| *id < model.next_id));
+        prop_assert_eq!(
+            pending.into_iter().collect::<BTreeSet<_>>(),
+            model.pending.keys().copied().collect::<BTreeSet<_>>()
+        );
+        prop_assert_eq!(
+            fulfilled.into_iter().collect::<BTreeSet<_>>(),
+            model.fulfilled.keys().copied().collect::<BTreeSet<_>>()
+        );
+
+        // Funds only move between the makers, the destination and the contract
+        let mut total = 0;
+        for account in accounts.iter() {
+            let balance = suite.balance(account);
+            prop_assert_eq!(
+                balance,
+                Uint128::new(*model.balance(account)),
+                "{}",
+                account
+            );
+            total += balance.u128();
+        }
+        prop_assert_eq!(total, supply);
+
+        // A failed payout reverts the fill along with its fee
+        let treasury: TreasuryResponse = suite.query(&QueryMsg::Treasury {});
+        let fees = treasury
+            .balances
+            .iter()
+            .find(|balance| balance.denom == DENOM)
+            .map_or(0, |balance| balance.available.u128());
+        prop_assert_eq!(fees, model.fees);
+        let stats: StatsResponse = suite.query(&QueryMsg::Stats {});
+        let fills: u64 = stats
+            .denoms
+            .iter()
+            .map(|denom| denom.stats.filled_count)
+            .sum();
+        prop_assert_eq!(fills, model.fills);
+
+        let report: CheckInvariantsResponse = suite.query(&QueryMsg::CheckInvariants {});
+        prop_assert!(report.ok, "{:?}", report.violations);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_order_book_matches_model(
+        ops in prop::collection::vec(op(), 1..40),
+        maker_balances in prop::collection::vec(0u128..10_000, MAKERS),
+    ) {
+        run(ops, maker_balances)?;
+    }
+}
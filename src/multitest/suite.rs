@@ -0,0 +1,216 @@
+use anyhow::Result as AnyResult;
+use cosmwasm_std::{coins, Addr, Coin, Uint128};
+use cw_multi_test::{App, AppBuilder, AppResponse, ContractWrapper, Executor};
+
+use crate::contract::{execute, instantiate, migrate, query, reply};
+use crate::execute::{Tx, TxOrigin, TxOwner};
+use crate::msg::{ExecuteMsg, GetTxsResponse, InstantiateMsg, QueryMsg};
+use crate::state::{DenomMetadata, DestinationFormat, SettlementMode};
+use crate::ContractError;
+
+pub const DENOM: &str = "ubtc";
+pub const ADMIN: &str = "admin";
+pub const MODULE_ACCOUNT: &str = "module_account";
+
+// Suite wraps a multi-test app running the contract, with an admin, a module account and
+// DENOM registered. Contract code is stored once, so further deployments can be added to
+// the same app with `deploy`.
+pub struct Suite {
+    pub app: App,
+    pub code_id: u64,
+    pub contract: Addr,
+    pub admin: Addr,
+    pub module_account: Addr,
+}
+
+impl Suite {
+    pub fn new() -> Self {
+        let mut app = AppBuilder::new().build(|_, _, _| {});
+        let code = ContractWrapper::new(execute, instantiate, query)
+            .with_reply(reply)
+            .with_migrate(migrate);
+        let code_id = app.store_code(Box::new(code));
+
+        let mut suite = Suite {
+            app,
+            code_id,
+            contract: Addr::unchecked(""),
+            admin: Addr::unchecked(ADMIN),
+            module_account: Addr::unchecked(MODULE_ACCOUNT),
+        };
+        suite.contract = suite.deploy("catalyst");
+        suite
+    }
+
+    // deploy instantiates a new contract with the suite's admin and module account, and
+    // registers DENOM in it.
+    pub fn deploy(&mut self, label: &str) -> Addr {
+        let contract = self
+            .app
+            .instantiate_contract(
+                self.code_id,
+                self.admin.clone(),
+                &InstantiateMsg {
+                    module_account: self.module_account.to_string(),
+                    admin: None,
+                    history_retention_blocks: None,
+                    protocol_fee_bps: Some(100),
+                    withdrawal_timelock_seconds: None,
+                    foreign_destination_formats: Some(vec![DestinationFormat::Hex {
+                        prefix: "0x".to_string(),
+                        length: 20,
+                    }]),
+                    outbound_timeout_seconds: None,
+                    destination_routes: None,
+                },
+                &[],
+                label,
+                Some(self.admin.to_string()),
+            )
+            .unwrap();
+        self.app
+            .execute_contract(
+                self.admin.clone(),
+                contract.clone(),
+                &ExecuteMsg::SetDenom {
+                    denom: DENOM.to_string(),
+                    metadata: DenomMetadata {
+                        symbol: "BTC".to_string(),
+                        decimals: 8,
+                        origin_chain: "bitcoin".to_string(),
+                        enabled: true,
+                        settlement: SettlementMode::Module,
+                    },
+                },
+                &[],
+            )
+            .unwrap();
+        contract
+    }
+
+    // maker returns an account funded with `amount` of DENOM
+    pub fn maker(&mut self, name: &str, amount: u128) -> Addr {
+        let maker = Addr::unchecked(name);
+        self.app
+            .init_modules(|router, _, storage| {
+                router
+                    .bank
+                    .init_balance(storage, &maker, coins(amount, DENOM))
+            })
+            .unwrap();
+        maker
+    }
+
+    // destination returns an empty account txs can be bridged to
+    pub fn destination(&self, name: &str) -> Addr {
+        Addr::unchecked(name)
+    }
+
+    pub fn balance(&self, address: &Addr) -> Uint128 {
+        self.app
+            .wrap()
+            .query_balance(address, DENOM)
+            .unwrap()
+            .amount
+    }
+
+    pub fn execute(
+        &mut self,
+        sender: &Addr,
+        msg: &ExecuteMsg,
+        funds: &[Coin],
+    ) -> AnyResult<AppResponse> {
+        self.app
+            .execute_contract(sender.clone(), self.contract.clone(), msg, funds)
+    }
+
+    // add_tx posts a tx from the module account, returning its id
+    pub fn add_tx(&mut self, destination: &Addr, amount: u128) -> AnyResult<u64> {
+        self.add_tx_with(destination, amount, None, None)
+    }
+
+    pub fn add_tx_with(
+        &mut self,
+        destination: &Addr,
+        amount: u128,
+        origin: Option<TxOrigin>,
+        owner: Option<TxOwner>,
+    ) -> AnyResult<u64> {
+        let module_account = self.module_account.clone();
+        let response = self.execute(
+            &module_account,
+            &ExecuteMsg::AddTx {
+                destination_addr: destination.to_string(),
+                output_coin: Coin::new(amount, DENOM),
+                origin,
+                owner,
+                signatures: None,
+            },
+            &[],
+        )?;
+        Ok(wasm_attribute(&response, "tx_id").parse()?)
+    }
+
+    pub fn fulfill(&mut self, maker: &Addr, tx_id: u64, amount: u128) -> AnyResult<AppResponse> {
+        self.execute(
+            maker,
+            &ExecuteMsg::FulfillTx { tx_id },
+            &coins(amount, DENOM),
+        )
+    }
+
+    pub fn expire(&mut self, tx_id: u64) -> AnyResult<AppResponse> {
+        let module_account = self.module_account.clone();
+        self.execute(&module_account, &ExecuteMsg::RemoveTx { tx_id }, &[])
+    }
+
+    pub fn settle(&mut self, tx_id: u64) -> AnyResult<AppResponse> {
+        let module_account = self.module_account.clone();
+        self.execute(
+            &module_account,
+            &ExecuteMsg::RemoveFulfilledTx { tx_id },
+            &[],
+        )
+    }
+
+    pub fn query<T: serde::de::DeserializeOwned>(&self, msg: &QueryMsg) -> T {
+        self.app
+            .wrap()
+            .query_wasm_smart(self.contract.clone(), msg)
+            .unwrap()
+    }
+
+    pub fn pending_txs(&self) -> Vec<Tx> {
+        self.query::<GetTxsResponse>(&QueryMsg::GetPendingTxs {})
+            .txs
+    }
+
+    pub fn fulfilled_txs(&self) -> Vec<Tx> {
+        self.query::<GetTxsResponse>(&QueryMsg::GetFulfilledTxs {})
+            .txs
+    }
+
+    pub fn advance_seconds(&mut self, seconds: u64) {
+        self.app.update_block(|block| {
+            block.height += seconds / 5;
+            block.time = block.time.plus_seconds(seconds);
+        });
+    }
+}
+
+// wasm_attribute returns the first attribute with the given key emitted by the contract
+pub fn wasm_attribute(response: &AppResponse, key: &str) -> String {
+    response
+        .events
+        .iter()
+        .filter(|event| event.ty == "wasm")
+        .flat_map(|event| event.attributes.iter())
+        .find(|attribute| attribute.key == key)
+        .map(|attribute| attribute.value.clone())
+        .unwrap_or_else(|| panic!("no wasm attribute {key}"))
+}
+
+// contract_error extracts the contract error an execute failed with
+pub fn contract_error(err: anyhow::Error) -> ContractError {
+    err.downcast().unwrap()
+}
@@ -0,0 +1,848 @@
+use cosmwasm_std::{
+    coin, coins, Addr, BankMsg, Binary, Coin, Decimal, Empty, Response, StdResult, Uint128,
+};
+use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+use serde::de::DeserializeOwned;
+
+use super::suite::{contract_error, wasm_attribute, Suite, DENOM, MODULE_ACCOUNT};
+use crate::destination::BridgeExecuteMsg;
+use crate::execute::{TxOrigin, TxOwner, TxStatus};
+use crate::interface::{CatalystClient, ContractExecutor, ContractQuerier, Page};
+use crate::keeper::{
+    DepositEvent, DepositSource, DepositStatus, Keeper, KeeperAction, KeeperConfig, KeeperState,
+    MockSource,
+};
+use crate::maker::{DenomRule, Maker, MakerConfig, Outcome, Pricing, SkipReason};
+use crate::msg::{
+    CheckInvariantsResponse, DenomInfo, ExecuteMsg, MigrateMsg, QueryMsg, TreasuryResponse,
+    WithdrawalResponse,
+};
+use crate::snapshot::StateExport;
+use crate::state::{
+    DenomMetadata, DestinationFormat, DestinationRoute, RouteAction, SettlementMode,
+    WithdrawalStatus,
+};
+use crate::{ContractError, ErrorCode};
+
+const EVM_DESTINATION: &str = "0xabcdef0123456789abcdef0123456789abcdef01";
+
+#[test]
+fn test_fulfill_lifecycle() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+
+    // Txs are numbered from one
+    for amount in [1_000, 2_000, 3_000] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+    assert_eq!(
+        suite
+            .pending_txs()
+            .iter()
+            .map(|tx| tx.id)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 3]
+    );
+
+    // The fill must match the tx exactly
+    let err = suite.fulfill(&maker, 2, 1_999).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::InsufficientFunds { .. }
+    ));
+
+    // Any tx can be fulfilled, not only the first. The reply moves it to the fulfilled
+    // txs once the bank send to the destination succeeded.
+    suite.fulfill(&maker, 2, 2_000).unwrap();
+    let fulfilled = suite.fulfilled_txs();
+    assert_eq!(fulfilled.len(), 1);
+    assert_eq!(fulfilled[0].id, 2);
+    assert_eq!(fulfilled[0].fill.as_ref().unwrap().fulfiller_addr, maker);
+    // Settlement pays the market maker, while the destination stays the end user's
+    assert_eq!(fulfilled[0].payee.as_ref(), Some(&maker));
+    assert_eq!(fulfilled[0].destination_addr, dest.to_string());
+    assert_eq!(suite.pending_txs().len(), 2);
+
+    // The destination receives the fill less the 1% protocol fee, kept by the contract
+    assert_eq!(suite.balance(&maker), Uint128::new(8_000));
+    assert_eq!(suite.balance(&dest), Uint128::new(1_980));
+    assert_eq!(suite.balance(&suite.contract.clone()), Uint128::new(20));
+    let treasury: TreasuryResponse = suite.query(&QueryMsg::Treasury {});
+    assert_eq!(treasury.balances[0].available, Uint128::new(20));
+    assert_eq!(treasury.balances[0].deficit, Uint128::zero());
+
+    suite.fulfill(&maker, 3, 3_000).unwrap();
+    assert_eq!(suite.fulfilled_txs().len(), 2);
+
+    // A fulfilled tx cannot be fulfilled again
+    let err = suite.fulfill(&maker, 2, 2_000).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::InvalidStatus {
+            id: 2,
+            status: TxStatus::Fulfilled
+        }
+    ));
+
+    // Settling removes the tx from the fulfilled txs
+    suite.settle(2).unwrap();
+    assert_eq!(
+        suite
+            .fulfilled_txs()
+            .iter()
+            .map(|tx| tx.id)
+            .collect::<Vec<_>>(),
+        vec![3]
+    );
+
+    let report: CheckInvariantsResponse = suite.query(&QueryMsg::CheckInvariants {});
+    assert!(report.ok, "{:?}", report.violations);
+}
+
+#[test]
+fn test_client_pages_quotes_and_fulfills() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    for amount in [1_000, 2_000, 3_000] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+
+    let contract = suite.contract.clone();
+    let mut client = CatalystClient::new(&mut suite.app, contract);
+    let page = client
+        .pending_txs(Page {
+            start_after: Some(1),
+            limit: Some(1),
+        })
+        .unwrap();
+    assert_eq!(page.iter().map(|tx| tx.id).collect::<Vec<_>>(), vec![2]);
+
+    // The quote matches the split the contract applies on fulfillment
+    let quote = client.quote_fill(2).unwrap().unwrap();
+    assert_eq!(quote.funds, coin(2_000, DENOM));
+    assert_eq!(quote.protocol_fee, coin(20, DENOM));
+    assert_eq!(quote.destination_amount, Uint128::new(1_980));
+    client
+        .fulfill(&maker, quote.tx_id, std::slice::from_ref(&quote.funds))
+        .unwrap();
+    assert!(client.quote_fill(2).unwrap().is_none());
+    assert_eq!(client.fulfilled_txs().unwrap()[0].id, 2);
+
+    assert_eq!(suite.balance(&dest), quote.destination_amount);
+}
+
+#[test]
+fn test_simulate_fulfill_matches_execution() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    for amount in [1_000, 2_000, 3_000] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+    suite.fulfill(&maker, 3, 3_000).unwrap();
+
+    let contract = suite.contract.clone();
+    let client = CatalystClient::new(&mut suite.app, contract);
+
+    // A single fill returns the send to the destination less the protocol fee
+    let simulated = client
+        .simulate_fulfill(vec![1], maker.as_str(), coins(1_000, DENOM))
+        .unwrap();
+    assert!(simulated.refund.is_empty());
+    let fill = &simulated.fills[0];
+    assert_eq!(fill.status, Some(TxStatus::Fulfilled));
+    assert_eq!(fill.protocol_fee, Some(coin(10, DENOM)));
+    assert_eq!(
+        fill.messages,
+        vec![BankMsg::Send {
+            to_address: dest.to_string(),
+            amount: coins(990, DENOM),
+        }
+        .into()]
+    );
+
+    let simulated = client
+        .simulate_fulfill(vec![1], maker.as_str(), coins(999, DENOM))
+        .unwrap();
+    let error = simulated.fills[0].error.as_ref().unwrap();
+    assert_eq!(simulated.fills[0].status, Some(TxStatus::Pending));
+    assert_eq!(error.code, ErrorCode::InsufficientFunds);
+
+    // A batch draws each fill from the funds and refunds what is left. Txs already
+    // fulfilled, including earlier in the batch, fail without taking funds.
+    let simulated = client
+        .simulate_fulfill(vec![1, 2, 1, 3, 9], maker.as_str(), coins(3_500, DENOM))
+        .unwrap();
+    let statuses: Vec<_> = simulated.fills.iter().map(|fill| fill.status).collect();
+    assert_eq!(
+        statuses,
+        vec![
+            Some(TxStatus::Fulfilled),
+            Some(TxStatus::Fulfilled),
+            Some(TxStatus::Fulfilled),
+            Some(TxStatus::Fulfilled),
+            None
+        ]
+    );
+    let errors: Vec<_> = simulated
+        .fills
+        .iter()
+        .map(|fill| fill.error.as_ref().map(|error| error.code))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            None,
+            None,
+            Some(ErrorCode::InvalidStatus),
+            Some(ErrorCode::InvalidStatus),
+            Some(ErrorCode::TransactionNotFound)
+        ]
+    );
+    assert_eq!(simulated.refund, coins(500, DENOM));
+
+    // Nothing was changed by the simulations
+    assert_eq!(client.pending_txs(Page::default()).unwrap().len(), 2);
+    assert_eq!(suite.balance(&maker), Uint128::new(7_000));
+
+    // Executing the batch fills the same txs and refunds the same funds
+    let tx_ids = vec![1, 2, 1, 3, 9];
+    suite
+        .execute(
+            &maker,
+            &ExecuteMsg::FulfillTxs { tx_ids },
+            &coins(3_500, DENOM),
+        )
+        .unwrap();
+    assert!(suite.pending_txs().is_empty());
+    assert_eq!(suite.fulfilled_txs().len(), 3);
+    assert_eq!(suite.balance(&maker), Uint128::new(4_000));
+    assert_eq!(suite.balance(&dest), Uint128::new(5_940));
+}
+
+#[test]
+fn test_maker_fills_within_its_exposure() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    for amount in [1_000, 50, 2_000, 1_500] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+
+    let config = MakerConfig {
+        contract: suite.contract.to_string(),
+        address: maker.to_string(),
+        rules: vec![DenomRule {
+            denom: DENOM.to_string(),
+            min_amount: Uint128::new(100),
+            max_amount: Uint128::new(5_000),
+            max_exposure: Uint128::new(3_000),
+            require_origin: false,
+            pricing: None,
+        }],
+        page_limit: Some(2),
+        in_flight_polls: None,
+    };
+    let mut bot = Maker::new(&mut suite.app, config.clone());
+    assert_eq!(
+        bot.poll(&maker).unwrap(),
+        vec![
+            Outcome::Filled {
+                tx_id: 1,
+                coin: coin(1_000, DENOM)
+            },
+            Outcome::Skipped {
+                tx_id: 2,
+                reason: SkipReason::BelowMinimum
+            },
+            Outcome::Filled {
+                tx_id: 3,
+                coin: coin(2_000, DENOM)
+            },
+            Outcome::Skipped {
+                tx_id: 4,
+                reason: SkipReason::ExposureLimit
+            },
+        ]
+    );
+
+    // Settlement frees up exposure for the remaining tx
+    drop(bot);
+    suite.settle(3).unwrap();
+    let mut bot = Maker::new(&mut suite.app, config);
+    let outcomes = bot.poll(&maker).unwrap();
+    assert!(outcomes.contains(&Outcome::Filled {
+        tx_id: 4,
+        coin: coin(1_500, DENOM)
+    }));
+    assert_eq!(suite.balance(&maker), Uint128::new(5_500));
+}
+
+// DroppedFills is a chain on which broadcast fills are never executed, as happens to a fill
+// that fails once included
+struct DroppedFills<'a>(&'a mut App);
+
+impl ContractQuerier for DroppedFills<'_> {
+    type Error = anyhow::Error;
+
+    fn query_contract<T: DeserializeOwned>(
+        &self,
+        contract: &str,
+        msg: &QueryMsg,
+    ) -> anyhow::Result<T> {
+        self.0.query_contract(contract, msg)
+    }
+}
+
+impl ContractExecutor for DroppedFills<'_> {
+    type Sender = Addr;
+    type Response = ();
+
+    fn execute_contract(
+        &mut self,
+        _sender: &Addr,
+        _contract: &str,
+        _msg: &ExecuteMsg,
+        _funds: &[Coin],
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_maker_awaits_in_flight_fills() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    for amount in [1_000, 2_000, 1_500, 100] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+
+    // At 0.1 per unit, a fee of 5 is within 5% of the value of all but the smallest tx
+    let config = MakerConfig {
+        contract: suite.contract.to_string(),
+        address: maker.to_string(),
+        rules: vec![DenomRule {
+            denom: DENOM.to_string(),
+            min_amount: Uint128::new(100),
+            max_amount: Uint128::new(5_000),
+            max_exposure: Uint128::new(3_000),
+            require_origin: false,
+            pricing: Some(Pricing {
+                price: Decimal::percent(10),
+                fill_cost: Uint128::new(5),
+                max_cost_bps: 500,
+            }),
+        }],
+        page_limit: None,
+        in_flight_polls: Some(1),
+    };
+    let mut bot = Maker::new(DroppedFills(&mut suite.app), config);
+    let filled = |tx_id, amount| Outcome::Filled {
+        tx_id,
+        coin: coin(amount, DENOM),
+    };
+    let skipped = |tx_id, reason| Outcome::Skipped { tx_id, reason };
+    assert_eq!(
+        bot.poll(&maker).unwrap(),
+        vec![
+            filled(1, 1_000),
+            filled(2, 2_000),
+            skipped(3, SkipReason::ExposureLimit),
+            skipped(4, SkipReason::Unprofitable),
+        ]
+    );
+
+    // Fills awaiting execution count towards the exposure and are not submitted again
+    assert_eq!(
+        bot.poll(&maker).unwrap(),
+        vec![
+            skipped(3, SkipReason::ExposureLimit),
+            skipped(4, SkipReason::Unprofitable),
+        ]
+    );
+
+    // Once they time out, their txs are filled again
+    assert_eq!(
+        bot.poll(&maker).unwrap(),
+        vec![
+            filled(1, 1_000),
+            filled(2, 2_000),
+            skipped(3, SkipReason::ExposureLimit),
+            skipped(4, SkipReason::Unprofitable),
+        ]
+    );
+    drop(bot);
+    assert_eq!(suite.pending_txs().len(), 4);
+    assert_eq!(suite.balance(&maker), Uint128::new(10_000));
+}
+
+#[test]
+fn test_keeper_posts_and_closes_deposits() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    let module_account = suite.module_account.clone();
+    let deposit = |vout: u32, confirmations: u32| DepositEvent {
+        origin: TxOrigin {
+            chain: "bitcoin".to_string(),
+            tx_hash: "ab".to_string(),
+            vout,
+        },
+        destination_addr: dest.to_string(),
+        coin: coin(1_000, DENOM),
+        confirmations,
+        owner: None,
+    };
+    let origin = |vout: u32| deposit(vout, 0).origin.key();
+    let config = KeeperConfig {
+        contract: suite.contract.to_string(),
+        post_confirmations: 1,
+        safe_confirmations: 6,
+    };
+
+    let mut source = MockSource::default();
+    source
+        .batches
+        .push_back(vec![deposit(0, 1), deposit(1, 1), deposit(2, 0)]);
+    let mut keeper = Keeper::new(&mut suite.app, config.clone(), KeeperState::default());
+    let actions = keeper.step(&module_account, source.poll().unwrap());
+    assert_eq!(
+        actions,
+        vec![
+            KeeperAction::Posted {
+                origin: origin(0),
+                tx_id: 1
+            },
+            KeeperAction::Posted {
+                origin: origin(1),
+                tx_id: 2
+            },
+        ]
+    );
+    let state = keeper.state.clone();
+    drop(keeper);
+
+    // Restarting from a state file missing the last deposit finds its tx instead of posting
+    // the deposit again
+    suite.fulfill(&maker, 1, 1_000).unwrap();
+    let mut stale = state.clone();
+    stale.deposits.remove(&origin(1));
+    let mut keeper = Keeper::new(&mut suite.app, config.clone(), stale);
+    let actions = keeper.step(
+        &module_account,
+        vec![deposit(0, 6), deposit(1, 6), deposit(2, 1)],
+    );
+    assert_eq!(
+        actions,
+        vec![
+            KeeperAction::Settled {
+                origin: origin(0),
+                tx_id: 1
+            },
+            KeeperAction::Expired {
+                origin: origin(1),
+                tx_id: 2
+            },
+            KeeperAction::Posted {
+                origin: origin(2),
+                tx_id: 3
+            },
+        ]
+    );
+    assert!(keeper.step(&module_account, vec![deposit(0, 7)]).is_empty());
+
+    // A reorg dropping a posted deposit is reported once, and its tx is left open even past
+    // the safe confirmations until the deposit is confirmed again
+    assert_eq!(
+        keeper.step(&module_account, vec![deposit(2, 0)]),
+        vec![KeeperAction::Reorged {
+            origin: origin(2),
+            tx_id: 3
+        }]
+    );
+    assert!(keeper.step(&module_account, vec![]).is_empty());
+    assert_eq!(
+        keeper.state.deposits[&origin(2)].status,
+        DepositStatus::Reorged
+    );
+    let state = keeper.state.clone();
+    drop(keeper);
+
+    assert!(suite.fulfilled_txs().is_empty());
+    assert_eq!(
+        suite
+            .pending_txs()
+            .iter()
+            .map(|tx| tx.id)
+            .collect::<Vec<_>>(),
+        vec![3]
+    );
+
+    let mut keeper = Keeper::new(&mut suite.app, config, state);
+    assert_eq!(
+        keeper.step(&module_account, vec![deposit(2, 6)]),
+        vec![KeeperAction::Expired {
+            origin: origin(2),
+            tx_id: 3
+        }]
+    );
+    drop(keeper);
+    assert!(suite.pending_txs().is_empty());
+}
+
+#[test]
+fn test_expire_and_cancel() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    let depositor = Addr::unchecked("depositor");
+
+    let expired = suite.add_tx(&dest, 1_000).unwrap();
+    let owned = suite
+        .add_tx_with(
+            &dest,
+            1_000,
+            None,
+            Some(TxOwner {
+                refund_addr: depositor.to_string(),
+                pubkey: None,
+            }),
+        )
+        .unwrap();
+
+    // Only the module account or a poster can expire txs
+    let err = suite
+        .execute(&maker, &ExecuteMsg::RemoveTx { tx_id: expired }, &[])
+        .unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::Unauthorized {}
+    ));
+    suite.expire(expired).unwrap();
+
+    // Only the owner can cancel its tx
+    let cancel = ExecuteMsg::CancelTx {
+        tx_id: owned,
+        signature: None,
+    };
+    let err = suite.execute(&maker, &cancel, &[]).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::Unauthorized {}
+    ));
+    suite.execute(&depositor, &cancel, &[]).unwrap();
+
+    // Neither can be fulfilled anymore, and no funds moved
+    for (tx_id, status) in [(expired, TxStatus::Expired), (owned, TxStatus::Cancelled)] {
+        let err = suite.fulfill(&maker, tx_id, 1_000).unwrap_err();
+        assert_eq!(
+            contract_error(err).to_string(),
+            ContractError::InvalidStatus { id: tx_id, status }.to_string()
+        );
+    }
+    let err = suite.fulfill(&maker, 42, 1_000).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::TransactionNotFound { id: 42 }
+    ));
+    assert!(suite.pending_txs().is_empty());
+    assert_eq!(suite.balance(&maker), Uint128::new(10_000));
+    assert_eq!(suite.balance(&dest), Uint128::zero());
+}
+
+#[test]
+fn test_expired_txs_are_forwarded() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let module_account = suite.maker(MODULE_ACCOUNT, 10_000);
+    let admin = suite.admin.clone();
+    let dest = suite.destination("dest");
+    let evm_dest = suite.destination(EVM_DESTINATION);
+
+    // The connecting bridge accepts the transfer and keeps the funds it sends on
+    let bridge_code = suite.app.store_code(Box::new(ContractWrapper::new(
+        |_, _, _, _: BridgeExecuteMsg| -> StdResult<Response> { Ok(Response::new()) },
+        |_, _, _, _: Empty| -> StdResult<Response> { Ok(Response::new()) },
+        |_, _, _: Empty| -> StdResult<Binary> { Ok(Binary::default()) },
+    )));
+    let bridge = suite
+        .app
+        .instantiate_contract(bridge_code, admin.clone(), &Empty {}, &[], "bridge", None)
+        .unwrap();
+
+    let update_routes = |routes| ExecuteMsg::UpdateConfig {
+        admin: None,
+        history_retention_blocks: None,
+        protocol_fee_bps: None,
+        withdrawal_timelock_seconds: None,
+        foreign_destination_formats: None,
+        outbound_timeout_seconds: None,
+        destination_routes: Some(routes),
+    };
+    let metadata: DenomInfo = suite.query(&QueryMsg::Denom {
+        denom: DENOM.to_string(),
+    });
+    suite
+        .execute(
+            &admin,
+            &ExecuteMsg::SetDenom {
+                denom: DENOM.to_string(),
+                metadata: DenomMetadata {
+                    settlement: SettlementMode::Forward,
+                    ..metadata.metadata
+                },
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute(
+            &admin,
+            &update_routes(vec![DestinationRoute {
+                format: DestinationFormat::Hex {
+                    prefix: "0x".to_string(),
+                    length: 20,
+                },
+                action: RouteAction::Bridge {
+                    contract: bridge.to_string(),
+                },
+            }]),
+            &[],
+        )
+        .unwrap();
+
+    let local = suite.add_tx(&dest, 1_000).unwrap();
+    let foreign = suite.add_tx(&evm_dest, 2_000).unwrap();
+    let filled = suite.add_tx(&evm_dest, 3_000).unwrap();
+
+    // The expiry must hand over exactly the cleared funds of the tx
+    let err = suite.expire(local).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::MultipleCoinError { num_coins: 0 }
+    ));
+    let err = suite
+        .execute(
+            &module_account,
+            &ExecuteMsg::RemoveTx { tx_id: local },
+            &coins(999, DENOM),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::InsufficientFunds { .. }
+    ));
+
+    // Funds reach the destination through the route a fill takes, without a protocol fee
+    let route = |response: &AppResponse| {
+        response
+            .events
+            .iter()
+            .find(|event| event.ty == "wasm-catalyst_tx_expired")
+            .and_then(|event| event.attributes.iter().find(|attr| attr.key == "route"))
+            .map(|attr| attr.value.clone())
+    };
+    for (tx_id, amount, expected) in [(local, 1_000, "bank"), (foreign, 2_000, "bridge")] {
+        let response = suite
+            .execute(
+                &module_account,
+                &ExecuteMsg::RemoveTx { tx_id },
+                &coins(amount, DENOM),
+            )
+            .unwrap();
+        assert_eq!(route(&response).as_deref(), Some(expected));
+    }
+    assert_eq!(suite.balance(&dest), Uint128::new(1_000));
+    assert_eq!(suite.balance(&bridge), Uint128::new(2_000));
+    assert_eq!(suite.balance(&module_account), Uint128::new(7_000));
+
+    let response = suite.fulfill(&maker, filled, 3_000).unwrap();
+    assert_eq!(
+        response
+            .events
+            .iter()
+            .find(|event| event.ty == "wasm-catalyst_tx_fulfill_submitted")
+            .and_then(|event| event.attributes.iter().find(|attr| attr.key == "route"))
+            .map(|attr| attr.value.as_str()),
+        Some("bridge")
+    );
+    assert_eq!(suite.balance(&bridge), Uint128::new(4_970));
+
+    // Foreign destinations without a route are rejected on intake, and txs whose route was
+    // removed can be neither filled nor forwarded. The mock api only accepts lowercase
+    // accounts, so this one is never taken as local.
+    let unrouted_dest = suite.destination("0xABCDEF0123456789ABCDEF0123456789ABCDEF01");
+    let unrouted = suite.add_tx(&unrouted_dest, 1_000).unwrap();
+    suite.execute(&admin, &update_routes(vec![]), &[]).unwrap();
+    let err = suite.add_tx(&unrouted_dest, 1_000).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::InvalidDestination { .. }
+    ));
+    let err = suite.fulfill(&maker, unrouted, 1_000).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::NoDestinationRoute { .. }
+    ));
+    let err = suite
+        .execute(
+            &module_account,
+            &ExecuteMsg::RemoveTx { tx_id: unrouted },
+            &coins(1_000, DENOM),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::NoDestinationRoute { .. }
+    ));
+}
+
+#[test]
+fn test_outbound_withdrawal() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 0);
+    let owner = suite.maker("owner", 5_000);
+    let module_account = suite.module_account.clone();
+
+    let request = ExecuteMsg::RequestWithdrawal {
+        destination_chain: "ethereum".to_string(),
+        destination_addr: EVM_DESTINATION.to_string(),
+    };
+    let response = suite
+        .execute(&owner, &request, &coins(2_000, DENOM))
+        .unwrap();
+    let paid: u64 = wasm_attribute(&response, "withdrawal_id").parse().unwrap();
+    let response = suite
+        .execute(&owner, &request, &coins(3_000, DENOM))
+        .unwrap();
+    let refunded: u64 = wasm_attribute(&response, "withdrawal_id").parse().unwrap();
+    assert_eq!(suite.balance(&suite.contract.clone()), Uint128::new(5_000));
+
+    // The maker pays out on the destination chain, and the poster confirms it
+    suite
+        .execute(
+            &maker,
+            &ExecuteMsg::ClaimWithdrawal {
+                id: paid,
+                payout: TxOrigin {
+                    chain: "ethereum".to_string(),
+                    tx_hash: "0xfeed".to_string(),
+                    vout: 0,
+                },
+            },
+            &[],
+        )
+        .unwrap();
+    suite
+        .execute(
+            &module_account,
+            &ExecuteMsg::ConfirmWithdrawal {
+                id: paid,
+                maker: maker.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(suite.balance(&maker), Uint128::new(2_000));
+
+    // Nobody delivers the other withdrawal, so the owner is refunded once it expires
+    let refund = ExecuteMsg::RefundWithdrawal { id: refunded };
+    let err = suite.execute(&maker, &refund, &[]).unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::WithdrawalNotExpired { .. }
+    ));
+    suite.advance_seconds(86_400);
+    suite.execute(&maker, &refund, &[]).unwrap();
+    assert_eq!(suite.balance(&owner), Uint128::new(3_000));
+    assert_eq!(suite.balance(&suite.contract.clone()), Uint128::zero());
+
+    let response: WithdrawalResponse = suite.query(&QueryMsg::Withdrawal { id: refunded });
+    assert_eq!(response.withdrawal.status, WithdrawalStatus::Refunded);
+}
+
+#[test]
+fn test_import_exported_state() {
+    let mut suite = Suite::new();
+    let maker = suite.maker("maker", 10_000);
+    let dest = suite.destination("dest");
+    for amount in [1_000, 2_000, 3_000] {
+        suite.add_tx(&dest, amount).unwrap();
+    }
+    suite.fulfill(&maker, 1, 1_000).unwrap();
+
+    // Seed a second deployment through migrate, one page at a time, once it holds the
+    // protocol fees being imported
+    let seeded = suite.deploy("seeded");
+    suite
+        .app
+        .send_tokens(maker.clone(), seeded.clone(), &coins(10, DENOM))
+        .unwrap();
+    let mut start_after = None;
+    loop {
+        let page: StateExport = suite.query(&QueryMsg::ExportState {
+            start_after,
+            limit: Some(2),
+        });
+        start_after = page.next.clone();
+        suite
+            .app
+            .migrate_contract(
+                suite.admin.clone(),
+                seeded.clone(),
+                &MigrateMsg::ImportState { export: page },
+                suite.code_id,
+            )
+            .unwrap();
+        if start_after.is_none() {
+            break;
+        }
+    }
+
+    let original = suite.contract.clone();
+    let export = |suite: &Suite, contract: &Addr| -> StateExport {
+        suite
+            .app
+            .wrap()
+            .query_wasm_smart(
+                contract,
+                &QueryMsg::ExportState {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+    };
+    assert_eq!(export(&suite, &seeded), export(&suite, &original));
+
+    // Only the contract admin can migrate
+    let err = suite.app.migrate_contract(
+        maker,
+        seeded.clone(),
+        &MigrateMsg::ImportState {
+            export: export(&suite, &original),
+        },
+        suite.code_id,
+    );
+    assert!(err.is_err());
+
+    // Once the last page is imported, not even the admin can overwrite the seeded state
+    let err = suite
+        .app
+        .migrate_contract(
+            suite.admin.clone(),
+            seeded,
+            &MigrateMsg::ImportState {
+                export: export(&suite, &original),
+            },
+            suite.code_id,
+        )
+        .unwrap_err();
+    assert!(matches!(
+        contract_error(err),
+        ContractError::ImportClosed {}
+    ));
+}
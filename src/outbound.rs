@@ -0,0 +1,690 @@
+use cosmwasm_std::{
+    Addr, BankMsg, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage,
+};
+
+use crate::attestation::{attestation_enabled, load_threshold};
+use crate::denoms::ensure_denom_enabled;
+use crate::events::{
+    withdrawal_event, WITHDRAWAL_CLAIMED_EVENT, WITHDRAWAL_PAID_EVENT, WITHDRAWAL_REFUNDED_EVENT,
+    WITHDRAWAL_REQUESTED_EVENT,
+};
+use crate::execute::TxOrigin;
+use crate::roles::authorize_poster;
+use crate::state::{
+    withdrawals, State, Withdrawal, WithdrawalStatus, ATTESTERS, CONFIG, NEXT_WITHDRAWAL_ID,
+    PROCESSED_PAYOUTS, STATE, WITHDRAWAL_ATTESTATIONS, WITHDRAWAL_CLAIMS,
+};
+use crate::ContractError;
+
+fn load_open_withdrawal(storage: &dyn Storage, id: u64) -> Result<Withdrawal, ContractError> {
+    let withdrawal = withdrawals()
+        .may_load(storage, id)?
+        .ok_or(ContractError::WithdrawalNotFound { id })?;
+    if withdrawal.status != WithdrawalStatus::Open {
+        return Err(ContractError::WithdrawalNotOpen { id });
+    }
+    Ok(withdrawal)
+}
+
+// request_withdrawal locks the funds sent with it until a market maker delivers them to
+// the destination on the external chain, or the withdrawal expires and is refunded.
+pub fn request_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    destination_chain: String,
+    destination_addr: String,
+) -> Result<Response, ContractError> {
+    if info.funds.len() != 1 {
+        return Err(ContractError::MultipleCoinError {
+            num_coins: info.funds.len(),
+        });
+    }
+    let coin = info.funds[0].clone();
+    ensure_denom_enabled(deps.storage, &coin.denom)?;
+
+    // Withdrawals leave this chain, so only foreign destinations are accepted
+    let config = CONFIG.load(deps.storage)?;
+    if !config
+        .foreign_destination_formats
+        .iter()
+        .any(|format| format.matches(&destination_addr))
+    {
+        return Err(ContractError::InvalidDestination {
+            address: destination_addr,
+            reason: "not a supported foreign address".to_string(),
+        });
+    }
+
+    let id = NEXT_WITHDRAWAL_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_WITHDRAWAL_ID.save(deps.storage, &(id + 1))?;
+
+    let withdrawal = Withdrawal {
+        id,
+        owner: info.sender,
+        coin,
+        destination_chain,
+        destination_addr,
+        created_at: env.block.time,
+        expires_at: env.block.time.plus_seconds(config.outbound_timeout_seconds),
+        status: WithdrawalStatus::Open,
+        maker: None,
+        payout: None,
+    };
+    withdrawals().save(deps.storage, id, &withdrawal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "request_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_event(withdrawal_event(WITHDRAWAL_REQUESTED_EVENT, &withdrawal)))
+}
+
+// claim_withdrawal is called by a market maker once it has paid out the withdrawal on the
+// destination chain, recording the payout so it can be confirmed by a poster or attesters.
+pub fn claim_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    payout: TxOrigin,
+) -> Result<Response, ContractError> {
+    let withdrawal = load_open_withdrawal(deps.storage, id)?;
+    if env.block.time >= withdrawal.expires_at {
+        return Err(ContractError::WithdrawalExpired {
+            id,
+            expires_at: withdrawal.expires_at,
+        });
+    }
+    if payout.chain != withdrawal.destination_chain {
+        return Err(ContractError::InvalidPayout {
+            reason: format!(
+                "payout is on {}, withdrawal is to {}",
+                payout.chain, withdrawal.destination_chain
+            ),
+        });
+    }
+    if let Some(paid_id) = PROCESSED_PAYOUTS.may_load(deps.storage, &payout.key())? {
+        return Err(ContractError::InvalidPayout {
+            reason: format!("{} already paid withdrawal {}", payout.key(), paid_id),
+        });
+    }
+    WITHDRAWAL_CLAIMS.save(deps.storage, (id, &info.sender), &payout)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "claim_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_attribute("maker", info.sender.to_string())
+        .add_attribute("payout", payout.key())
+        .add_event(withdrawal_event(WITHDRAWAL_CLAIMED_EVENT, &withdrawal)))
+}
+
+// confirm_withdrawal is called by the module account or a poster scoped to the withdrawal
+// once it has verified the maker's payout, releasing the locked funds to the maker.
+pub fn confirm_withdrawal(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u64,
+    maker: String,
+) -> Result<Response, ContractError> {
+    let maker = deps.api.addr_validate(&maker)?;
+    let withdrawal = load_open_withdrawal(deps.storage, id)?;
+    let payout = load_claim(deps.storage, id, &maker)?;
+
+    let state: State = STATE.load(deps.storage)?;
+    authorize_poster(
+        deps.as_ref(),
+        &state,
+        &info.sender,
+        &withdrawal.coin,
+        Some(&payout),
+    )?;
+
+    // While attestation is enabled, withdrawals are only released once enough attesters agree
+    if attestation_enabled(deps.storage)? {
+        return Err(ContractError::AttestationRequired {});
+    }
+
+    let withdrawal = release_withdrawal(deps.storage, withdrawal, &maker, payout)?;
+    Ok(Response::new()
+        .add_attribute("action", "confirm_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_message(maker_payment(&withdrawal, &maker))
+        .add_event(withdrawal_event(WITHDRAWAL_PAID_EVENT, &withdrawal)))
+}
+
+// attest_withdrawal is called by an attester to confirm the given maker paid out the
+// withdrawal. The locked funds are released once the threshold agrees on the same maker.
+pub fn attest_withdrawal(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    id: u64,
+    maker: String,
+) -> Result<Response, ContractError> {
+    let threshold = load_threshold(deps.storage)?;
+    if !ATTESTERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let maker = deps.api.addr_validate(&maker)?;
+    let withdrawal = load_open_withdrawal(deps.storage, id)?;
+    let payout = load_claim(deps.storage, id, &maker)?;
+    if WITHDRAWAL_ATTESTATIONS.has(deps.storage, (id, &info.sender)) {
+        return Err(ContractError::AlreadyAttested {
+            key: format!("withdrawal/{id}"),
+        });
+    }
+    WITHDRAWAL_ATTESTATIONS.save(deps.storage, (id, &info.sender), &maker)?;
+
+    // Only attesters still in the set count towards the threshold
+    let attestations = WITHDRAWAL_ATTESTATIONS
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Addr, Addr)>>>()?
+        .into_iter()
+        .filter(|(attester, attested)| *attested == maker && ATTESTERS.has(deps.storage, attester))
+        .count() as u32;
+
+    let response = Response::new()
+        .add_attribute("action", "attest_withdrawal")
+        .add_attribute("attester", info.sender)
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_attribute("maker", maker.to_string())
+        .add_attribute("attestations", attestations.to_string())
+        .add_attribute("threshold", threshold.to_string());
+    if attestations < threshold {
+        return Ok(response);
+    }
+
+    let withdrawal = release_withdrawal(deps.storage, withdrawal, &maker, payout)?;
+    Ok(response
+        .add_message(maker_payment(&withdrawal, &maker))
+        .add_event(withdrawal_event(WITHDRAWAL_PAID_EVENT, &withdrawal)))
+}
+
+// refund_withdrawal returns the locked funds of an expired withdrawal to its owner. It
+// may be called by anyone, since the funds can only go back to the owner. A withdrawal a
+// maker has claimed gets another outbound timeout for the claim to be confirmed, so the
+// owner cannot take the funds back right after the maker paid out.
+pub fn refund_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let mut withdrawal = load_open_withdrawal(deps.storage, id)?;
+    if env.block.time < withdrawal.expires_at {
+        return Err(ContractError::WithdrawalNotExpired {
+            id,
+            expires_at: withdrawal.expires_at,
+        });
+    }
+    let claimed = WITHDRAWAL_CLAIMS
+        .prefix(id)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if claimed {
+        let config = CONFIG.load(deps.storage)?;
+        let refundable_at = withdrawal
+            .expires_at
+            .plus_seconds(config.outbound_timeout_seconds);
+        if env.block.time < refundable_at {
+            return Err(ContractError::WithdrawalClaimPending { id, refundable_at });
+        }
+    }
+    withdrawal.status = WithdrawalStatus::Refunded;
+    withdrawals().save(deps.storage, id, &withdrawal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "refund_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_message(BankMsg::Send {
+            to_address: withdrawal.owner.to_string(),
+            amount: vec![withdrawal.coin.clone()],
+        })
+        .add_event(withdrawal_event(WITHDRAWAL_REFUNDED_EVENT, &withdrawal)))
+}
+
+fn load_claim(storage: &dyn Storage, id: u64, maker: &Addr) -> Result<TxOrigin, ContractError> {
+    WITHDRAWAL_CLAIMS
+        .may_load(storage, (id, maker))?
+        .ok_or(ContractError::WithdrawalNotClaimed {
+            id,
+            maker: maker.to_string(),
+        })
+}
+
+// release_withdrawal marks the withdrawal as paid by the maker. Each payout can only ever
+// release a single withdrawal.
+fn release_withdrawal(
+    storage: &mut dyn Storage,
+    mut withdrawal: Withdrawal,
+    maker: &Addr,
+    payout: TxOrigin,
+) -> Result<Withdrawal, ContractError> {
+    let key = payout.key();
+    if let Some(paid_id) = PROCESSED_PAYOUTS.may_load(storage, &key)? {
+        return Err(ContractError::InvalidPayout {
+            reason: format!("{key} already paid withdrawal {paid_id}"),
+        });
+    }
+    PROCESSED_PAYOUTS.save(storage, &key, &withdrawal.id)?;
+
+    withdrawal.status = WithdrawalStatus::Paid;
+    withdrawal.maker = Some(maker.clone());
+    withdrawal.payout = Some(payout);
+    withdrawals().save(storage, withdrawal.id, &withdrawal)?;
+    Ok(withdrawal)
+}
+
+// maker_payment sends the locked funds of a paid withdrawal to the maker that delivered it.
+fn maker_payment(withdrawal: &Withdrawal, maker: &Addr) -> BankMsg {
+    BankMsg::Send {
+        to_address: maker.to_string(),
+        amount: vec![withdrawal.coin.clone()],
+    }
+}
+
+// open_withdrawals returns the withdrawals whose funds are still locked in the contract.
+// They are read through the status index, so settled withdrawals are never loaded.
+pub fn open_withdrawals(deps: Deps) -> StdResult<Vec<Withdrawal>> {
+    withdrawals()
+        .idx
+        .status
+        .prefix(WithdrawalStatus::Open.index_key())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::configure_attestation;
+    use crate::state::DestinationFormat;
+    use crate::testing::{mock_denom, mock_state};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, coins, CosmosMsg, MessageInfo};
+
+    const DESTINATION: &str = "0xabcdef0123456789abcdef0123456789abcdef01";
+
+    fn setup(deps: DepsMut) {
+        mock_state(deps.storage);
+        CONFIG
+            .update(deps.storage, |mut config| -> StdResult<_> {
+                config.foreign_destination_formats = vec![DestinationFormat::Hex {
+                    prefix: "0x".to_string(),
+                    length: 20,
+                }];
+                Ok(config)
+            })
+            .unwrap();
+        mock_denom(deps.storage, "token");
+    }
+
+    fn payout(tx_hash: &str) -> TxOrigin {
+        TxOrigin {
+            chain: "ethereum".to_string(),
+            tx_hash: tx_hash.to_string(),
+            vout: 0,
+        }
+    }
+
+    fn request(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+        request_withdrawal(
+            deps,
+            env,
+            info,
+            "ethereum".to_string(),
+            DESTINATION.to_string(),
+        )
+    }
+
+    #[test]
+    fn test_withdrawal_lifecycle() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut());
+
+        // Withdrawals must lock a single registered coin, bound for a foreign destination
+        let err = request(deps.as_mut(), env.clone(), mock_info("owner", &[])).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::MultipleCoinError { num_coins: 0 }
+        ));
+        let err = request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "other")),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::DenomNotRegistered { .. }));
+        let err = request_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+            "ethereum".to_string(),
+            "destination_addr".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidDestination { .. }));
+
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+        )
+        .unwrap();
+
+        // The payout must be on the destination chain
+        let err = claim_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            TxOrigin {
+                chain: "bitcoin".to_string(),
+                ..payout("deadbeef")
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPayout { .. }));
+        claim_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            payout("deadbeef"),
+        )
+        .unwrap();
+
+        // Only the module account or a scoped poster can confirm the payout, and only for
+        // a maker that claimed it
+        let err = confirm_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            "market_maker".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = confirm_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+            "someone".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::WithdrawalNotClaimed { .. }));
+
+        let response = confirm_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+            "market_maker".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "market_maker".to_string(),
+                amount: coins(100, "token"),
+            })
+        );
+        let withdrawal = withdrawals().load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(withdrawal.status, WithdrawalStatus::Paid);
+        assert_eq!(withdrawal.maker, Some(Addr::unchecked("market_maker")));
+
+        // The same payout cannot be used to claim another withdrawal
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+        )
+        .unwrap();
+        let err = claim_withdrawal(
+            deps.as_mut(),
+            env,
+            mock_info("market_maker", &[]),
+            1,
+            payout("deadbeef"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPayout { .. }));
+
+        // Only the withdrawal still locking funds is open
+        let open = open_withdrawals(deps.as_ref()).unwrap();
+        assert_eq!(open.iter().map(|w| w.id).collect::<Vec<_>>(), vec![1]);
+    }
+
+    #[test]
+    fn test_withdrawal_timeout_refund() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        setup(deps.as_mut());
+
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+        )
+        .unwrap();
+        assert_eq!(open_withdrawals(deps.as_ref()).unwrap().len(), 1);
+
+        let err =
+            refund_withdrawal(deps.as_mut(), env.clone(), mock_info("anyone", &[]), 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::WithdrawalNotExpired { id: 0, .. }
+        ));
+
+        // Once expired, makers can no longer claim the withdrawal and anyone can refund it
+        env.block.time = env.block.time.plus_seconds(3_600);
+        let err = claim_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            payout("deadbeef"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::WithdrawalExpired { id: 0, .. }
+        ));
+
+        let response =
+            refund_withdrawal(deps.as_mut(), env.clone(), mock_info("anyone", &[]), 0).unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner".to_string(),
+                amount: coins(100, "token"),
+            })
+        );
+        assert!(open_withdrawals(deps.as_ref()).unwrap().is_empty());
+
+        let err = refund_withdrawal(deps.as_mut(), env, mock_info("anyone", &[]), 0).unwrap_err();
+        assert!(matches!(err, ContractError::WithdrawalNotOpen { id: 0 }));
+    }
+
+    #[test]
+    fn test_claimed_withdrawal_refund() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        setup(deps.as_mut());
+
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+        )
+        .unwrap();
+        claim_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            payout("deadbeef"),
+        )
+        .unwrap();
+
+        // The pending claim keeps the funds locked past expiry, so it can still be confirmed
+        env.block.time = env.block.time.plus_seconds(3_600);
+        let err =
+            refund_withdrawal(deps.as_mut(), env.clone(), mock_info("owner", &[]), 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::WithdrawalClaimPending { id: 0, .. }
+        ));
+        confirm_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+            "market_maker".to_string(),
+        )
+        .unwrap();
+        let err = refund_withdrawal(deps.as_mut(), env, mock_info("owner", &[]), 0).unwrap_err();
+        assert!(matches!(err, ContractError::WithdrawalNotOpen { id: 0 }));
+    }
+
+    #[test]
+    fn test_unconfirmed_claim_refund() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        setup(deps.as_mut());
+
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &coins(100, "token")),
+        )
+        .unwrap();
+        claim_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            0,
+            payout("deadbeef"),
+        )
+        .unwrap();
+
+        // A claim that is never confirmed only delays the refund by another timeout
+        env.block.time = env.block.time.plus_seconds(2 * 3_600 - 1);
+        let err =
+            refund_withdrawal(deps.as_mut(), env.clone(), mock_info("owner", &[]), 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::WithdrawalClaimPending { id: 0, .. }
+        ));
+        env.block.time = env.block.time.plus_seconds(1);
+        refund_withdrawal(deps.as_mut(), env, mock_info("owner", &[]), 0).unwrap();
+    }
+
+    #[test]
+    fn test_attested_withdrawal() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut());
+        configure_attestation(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            vec!["attester_a".to_string(), "attester_b".to_string()],
+            2,
+        )
+        .unwrap();
+
+        request(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[coin(100, "token")]),
+        )
+        .unwrap();
+        for maker in ["maker_a", "maker_b"] {
+            claim_withdrawal(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(maker, &[]),
+                0,
+                payout(maker),
+            )
+            .unwrap();
+        }
+
+        // Posters cannot confirm payouts while attestation is enabled
+        let err = confirm_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            0,
+            "maker_a".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AttestationRequired {}));
+
+        // Attesters disagreeing on the maker do not release the funds
+        let response = attest_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester_a", &[]),
+            0,
+            "maker_a".to_string(),
+        )
+        .unwrap();
+        assert!(response.messages.is_empty());
+        let response = attest_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester_b", &[]),
+            0,
+            "maker_b".to_string(),
+        )
+        .unwrap();
+        assert!(response.messages.is_empty());
+        let err = attest_withdrawal(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("attester_b", &[]),
+            0,
+            "maker_a".to_string(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::AlreadyAttested { .. }));
+
+        // Once the set changes, the new attester can tip the threshold
+        configure_attestation(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            vec!["attester_a".to_string(), "attester_c".to_string()],
+            2,
+        )
+        .unwrap();
+        let response = attest_withdrawal(
+            deps.as_mut(),
+            env,
+            mock_info("attester_c", &[]),
+            0,
+            "maker_a".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "maker_a".to_string(),
+                amount: coins(100, "token"),
+            })
+        );
+        let withdrawal = withdrawals().load(deps.as_ref().storage, 0).unwrap();
+        assert_eq!(withdrawal.payout, Some(payout("maker_a")));
+    }
+}
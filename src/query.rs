@@ -1,7 +1,28 @@
-use cosmwasm_std::{to_json_binary, Binary, Deps, StdResult};
+use cosmwasm_std::{to_json_binary, Binary, Coin, Deps, Env, Order, StdError, StdResult};
+use cw_storage_plus::Bound;
 
-use crate::msg::GetTxsResponse;
-use crate::state::{State, STATE};
+use crate::error::ErrorCode;
+use crate::execute::{plan_fill, plan_fills, Tx, TxOrigin, TxOwner, TxStatus};
+use crate::invariants::check_invariants;
+use crate::msg::{
+    AttestationConfigResponse, AttestationConflictsResponse, DenomInfo, DenomStatsResponse,
+    DenomsResponse, ErrorCodeInfo, ErrorCodesResponse, GetTxsResponse, MakerStatsResponse,
+    OwnerActionDigestResponse, PosterInfo, PostersResponse, SignedOrderDigestResponse,
+    SignerSetResponse, SimulateFulfillResponse, SimulatedError, SimulatedFill, SpvStatusResponse,
+    StatsResponse, StatusCounts, TreasuryResponse, TxAttestationsResponse, TxRecordsResponse,
+    WithdrawalClaim, WithdrawalResponse, WithdrawalsResponse,
+};
+use crate::signatures::{payload_digest, OwnerAction, OwnerActionPayload, SignedOrderPayload};
+use crate::spv::{display_hash, spv_tip};
+use crate::state::{
+    tx_records, withdrawals, State, TxRecord, Withdrawal, ATTESTATION_CONFIG,
+    ATTESTATION_CONFLICTS, ATTESTERS, CONFIG, DENOM_REGISTRY, DENOM_STATS, FEE_WITHDRAWALS,
+    MAKER_STATS, PENDING_TIMELOCK, POSTERS, PROCESSED_ORIGINS, SIGNER_SET, SPV_CONFIG, STATE,
+    TX_ATTESTATIONS, WITHDRAWAL_CLAIMS,
+};
+use crate::stats::average_fill_blocks;
+use crate::treasury::reconcile_treasury;
+use crate::ContractError;
 
 pub fn query_pending_txs(deps: Deps) -> StdResult<Binary> {
     let state: State = STATE.load(deps.storage)?;
@@ -10,6 +31,75 @@ pub fn query_pending_txs(deps: Deps) -> StdResult<Binary> {
     to_json_binary(&response)
 }
 
+pub fn query_pending_txs_page(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let state: State = STATE.load(deps.storage)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mut txs: Vec<Tx> = state
+        .pending_txs
+        .into_iter()
+        .filter(|tx| start_after.is_none_or(|start_after| tx.id > start_after))
+        .collect();
+    txs.sort_by_key(|tx| tx.id);
+    txs.truncate(limit);
+    to_json_binary(&GetTxsResponse { txs })
+}
+
+// query_simulate_fulfill dry-runs `FulfillTx` for a single tx, or `FulfillTxs` for a batch,
+// against a copy of the order book
+pub fn query_simulate_fulfill(
+    deps: Deps,
+    _env: Env,
+    tx_ids: Vec<u64>,
+    sender: String,
+    funds: Vec<Coin>,
+) -> StdResult<Binary> {
+    deps.api.addr_validate(&sender)?;
+    let mut state: State = STATE.load(deps.storage)?;
+    let (plans, refund) = match tx_ids.as_slice() {
+        // A single tx is checked against the funds exactly as `FulfillTx` receives them
+        [tx_id] => (
+            vec![(*tx_id, plan_fill(deps, &state, *tx_id, &funds))],
+            vec![],
+        ),
+        _ => plan_fills(deps, &mut state, &tx_ids, &funds),
+    };
+
+    let fills = plans
+        .into_iter()
+        .map(|(tx_id, plan)| match plan {
+            Ok(plan) => SimulatedFill {
+                tx_id,
+                status: Some(TxStatus::Fulfilled),
+                funds: Some(plan.tx.coin),
+                protocol_fee: Some(plan.protocol_fee),
+                messages: vec![plan.payout.msg],
+                error: None,
+            },
+            Err(err) => SimulatedFill {
+                tx_id,
+                status: match err {
+                    ContractError::InvalidStatus { status, .. } => Some(status),
+                    ContractError::TransactionNotFound { .. } => None,
+                    _ => Some(TxStatus::Pending),
+                },
+                funds: None,
+                protocol_fee: None,
+                messages: vec![],
+                error: Some(SimulatedError {
+                    code: err.code(),
+                    message: err.to_string(),
+                }),
+            },
+        })
+        .collect();
+
+    to_json_binary(&SimulateFulfillResponse { fills, refund })
+}
+
 pub fn query_fulfilled_txs(deps: Deps) -> StdResult<Binary> {
     let state: State = STATE.load(deps.storage)?;
     let txs = state.fulfilled_txs;
@@ -17,13 +107,359 @@ pub fn query_fulfilled_txs(deps: Deps) -> StdResult<Binary> {
     to_json_binary(&response)
 }
 
+pub fn query_stats(deps: Deps) -> StdResult<Binary> {
+    let state: State = STATE.load(deps.storage)?;
+
+    let denoms = DENOM_STATS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (denom, stats) = item?;
+            Ok(DenomStatsResponse {
+                average_fill_blocks: average_fill_blocks(&stats),
+                denom,
+                stats,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let makers = MAKER_STATS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let ((maker, denom), stats) = item?;
+            Ok(MakerStatsResponse {
+                maker,
+                denom,
+                stats,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let status_counts = StatusCounts {
+        pending: state.pending_txs.len() as u64,
+        fulfilled: state.fulfilled_txs.len() as u64,
+        settled: denoms.iter().map(|d| d.stats.settled_count).sum(),
+        expired: denoms.iter().map(|d| d.stats.expired_count).sum(),
+        cancelled: denoms.iter().map(|d| d.stats.cancelled_count).sum(),
+    };
+
+    to_json_binary(&StatsResponse {
+        denoms,
+        makers,
+        status_counts,
+    })
+}
+
+pub fn query_config(deps: Deps) -> StdResult<Binary> {
+    to_json_binary(&CONFIG.load(deps.storage)?)
+}
+
+pub fn query_treasury(deps: Deps, env: Env) -> StdResult<Binary> {
+    let balances = reconcile_treasury(deps, &env.contract.address)?;
+    let withdrawals = FEE_WITHDRAWALS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&TreasuryResponse {
+        balances,
+        withdrawals,
+        pending_timelock: PENDING_TIMELOCK.may_load(deps.storage)?,
+    })
+}
+
+pub fn query_check_invariants(deps: Deps, env: Env) -> StdResult<Binary> {
+    to_json_binary(&check_invariants(deps, &env)?)
+}
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+pub fn query_txs_by_fulfiller(
+    deps: Deps,
+    fulfiller: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let fulfiller = deps.api.addr_validate(&fulfiller)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let records = tx_records()
+        .idx
+        .fulfiller
+        .prefix(fulfiller.to_string())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<TxRecord>>>()?;
+    to_json_binary(&TxRecordsResponse { records })
+}
+
+pub fn query_txs_by_destination(
+    deps: Deps,
+    destination: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let records = tx_records()
+        .idx
+        .destination
+        .prefix(destination)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<StdResult<Vec<TxRecord>>>()?;
+    to_json_binary(&TxRecordsResponse { records })
+}
+
+pub fn query_denoms(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let denoms = DENOM_REGISTRY
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(denom, metadata)| DenomInfo { denom, metadata }))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&DenomsResponse { denoms })
+}
+
+pub fn query_denom(deps: Deps, denom: String) -> StdResult<Binary> {
+    let metadata = DENOM_REGISTRY.load(deps.storage, &denom)?;
+    to_json_binary(&DenomInfo { denom, metadata })
+}
+
+pub fn query_posters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let state: State = STATE.load(deps.storage)?;
+    let start_after = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let posters = POSTERS
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(address, scope)| PosterInfo { address, scope }))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&PostersResponse {
+        module_account: state.module_account,
+        posters,
+    })
+}
+
+pub fn query_attestation_config(deps: Deps) -> StdResult<Binary> {
+    let threshold = ATTESTATION_CONFIG
+        .may_load(deps.storage)?
+        .map_or(0, |config| config.threshold);
+    let attesters = ATTESTERS
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&AttestationConfigResponse {
+        threshold,
+        attesters,
+    })
+}
+
+pub fn query_spv_status(deps: Deps) -> StdResult<Binary> {
+    let config = SPV_CONFIG.may_load(deps.storage)?;
+    let tip = spv_tip(deps.storage)?;
+    to_json_binary(&SpvStatusResponse {
+        config,
+        tip_hash: tip.as_ref().map(|(hash, _)| display_hash(hash.as_slice())),
+        tip_height: tip.map(|(_, header)| header.height),
+    })
+}
+
+pub fn query_signer_set(deps: Deps) -> StdResult<Binary> {
+    let signer_set = SIGNER_SET.may_load(deps.storage)?;
+    to_json_binary(&SignerSetResponse { signer_set })
+}
+
+pub fn query_signed_order_digest(
+    deps: Deps,
+    env: Env,
+    destination_addr: String,
+    output_coin: Coin,
+    origin: TxOrigin,
+    owner: Option<TxOwner>,
+) -> StdResult<Binary> {
+    let signer_set_version = SIGNER_SET
+        .may_load(deps.storage)?
+        .map_or(0, |signer_set| signer_set.version);
+    let payload = SignedOrderPayload {
+        chain_id: env.block.chain_id,
+        contract: env.contract.address.to_string(),
+        signer_set_version,
+        destination_addr,
+        output_coin,
+        origin,
+        owner,
+    };
+    let digest = payload_digest(&payload)?;
+    to_json_binary(&SignedOrderDigestResponse {
+        payload,
+        digest: Binary::from(digest),
+    })
+}
+
+pub fn query_owner_action_digest(
+    deps: Deps,
+    env: Env,
+    tx_id: u64,
+    action: OwnerAction,
+) -> StdResult<Binary> {
+    let state: State = STATE.load(deps.storage)?;
+    let tx = state
+        .pending_txs
+        .iter()
+        .find(|tx| tx.id == tx_id)
+        .ok_or_else(|| StdError::not_found(format!("pending tx {tx_id}")))?;
+    let payload = OwnerActionPayload {
+        chain_id: env.block.chain_id,
+        contract: env.contract.address.to_string(),
+        tx_id,
+        amendments: tx.amendments,
+        action,
+    };
+    let digest = payload_digest(&payload)?;
+    to_json_binary(&OwnerActionDigestResponse {
+        payload,
+        digest: Binary::from(digest),
+    })
+}
+
+pub fn query_tx_attestations(deps: Deps, origin: TxOrigin) -> StdResult<Binary> {
+    let key = origin.key();
+    let attestations = TX_ATTESTATIONS
+        .prefix(&key)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let tx_id = PROCESSED_ORIGINS.may_load(deps.storage, &key)?;
+    to_json_binary(&TxAttestationsResponse {
+        attestations,
+        tx_id,
+    })
+}
+
+pub fn query_attestation_conflicts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let conflicts = ATTESTATION_CONFLICTS
+        .range(
+            deps.storage,
+            start_after.as_deref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, conflict)| conflict))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&AttestationConflictsResponse { conflicts })
+}
+
+pub fn query_withdrawal(deps: Deps, id: u64) -> StdResult<Binary> {
+    let withdrawal = withdrawals().load(deps.storage, id)?;
+    let claims = WITHDRAWAL_CLAIMS
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(maker, payout)| WithdrawalClaim { maker, payout }))
+        .collect::<StdResult<Vec<_>>>()?;
+    to_json_binary(&WithdrawalResponse { withdrawal, claims })
+}
+
+pub fn query_withdrawals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let withdrawals = withdrawals()
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal))
+        .collect::<StdResult<Vec<Withdrawal>>>()?;
+    to_json_binary(&WithdrawalsResponse { withdrawals })
+}
+
+pub fn query_withdrawals_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Binary> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let withdrawals = withdrawals()
+        .idx
+        .owner
+        .prefix(owner.to_string())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, withdrawal)| withdrawal))
+        .collect::<StdResult<Vec<Withdrawal>>>()?;
+    to_json_binary(&WithdrawalsResponse { withdrawals })
+}
+
+pub fn query_error_codes() -> StdResult<Binary> {
+    let codes = ErrorCode::ALL
+        .into_iter()
+        .map(|error| ErrorCodeInfo {
+            code: error as u32,
+            error,
+        })
+        .collect();
+    to_json_binary(&ErrorCodesResponse { codes })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::execute::{add_pending_tx, move_pending_tx_to_fulfilled_tx};
+    use crate::contract::reply;
+    use crate::execute::{
+        add_pending_tx, fulfill_pending_tx, move_pending_tx_to_fulfilled_tx, remove_fulfilled_tx,
+        remove_pending_tx, TxStatus,
+    };
     use crate::state::FulfillState;
+    use crate::state::CONFIG;
+    use crate::testing::{mock_config, mock_denom};
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{coins, Coin, Reply, SubMsgResponse, SubMsgResult};
+    use cosmwasm_std::{coins, Addr, Coin, Decimal, Reply, SubMsgResponse, SubMsgResult};
     use cosmwasm_std::{from_json, Uint128};
     #[test]
     fn test_query_pending_txs() {
@@ -45,6 +481,8 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Add 3 transactions
         for _ in 0..3 {
@@ -54,6 +492,9 @@ mod tests {
                 info.clone(),
                 owner.clone(),
                 output_coin.clone(),
+                None,
+                None,
+                None,
             )
             .unwrap();
         }
@@ -74,6 +515,7 @@ mod tests {
             msg,
             FulfillState {
                 fulfiller_addr: info.sender,
+                protocol_fee: Uint128::zero(),
             },
         )
         .unwrap();
@@ -106,6 +548,8 @@ mod tests {
             next_id: 0,
         };
         STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
 
         // Add 3 transactions
         for _ in 0..3 {
@@ -115,6 +559,9 @@ mod tests {
                 info.clone(),
                 owner.clone(),
                 output_coin.clone(),
+                None,
+                None,
+                None,
             )
             .unwrap();
         }
@@ -135,6 +582,7 @@ mod tests {
             msg,
             FulfillState {
                 fulfiller_addr: info.sender,
+                protocol_fee: Uint128::zero(),
             },
         )
         .unwrap();
@@ -146,4 +594,253 @@ mod tests {
         // Check if the returned result contains the 1 transaction1
         assert_eq!(response.txs.len(), 1);
     }
+
+    #[test]
+    fn test_query_stats() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("module_account", &[]);
+
+        let output_coin = Coin {
+            denom: "token".to_string(),
+            amount: Uint128::from(100u128),
+        };
+
+        // Initialize state
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        // Add 3 transactions
+        for _ in 0..3 {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                "owner".to_string(),
+                output_coin.clone(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        // Fulfill the first transaction 4 blocks later
+        env.block.height += 4;
+        let msg = Reply {
+            id: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                data: None,
+                events: vec![],
+            }),
+        };
+        move_pending_tx_to_fulfilled_tx(
+            deps.as_mut(),
+            env.clone(),
+            msg,
+            FulfillState {
+                fulfiller_addr: Addr::unchecked("market_maker"),
+                protocol_fee: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+        // Expire the second and settle the first transaction
+        remove_pending_tx(deps.as_mut(), env.clone(), info.clone(), 1).unwrap();
+        remove_fulfilled_tx(deps.as_mut(), env, info, 0).unwrap();
+
+        let result = query_stats(deps.as_ref()).unwrap();
+        let response: StatsResponse = from_json(result).unwrap();
+
+        assert_eq!(response.denoms.len(), 1);
+        let denom = &response.denoms[0];
+        assert_eq!(denom.denom, "token");
+        assert_eq!(denom.stats.added_count, 3);
+        assert_eq!(denom.stats.added_volume, Uint128::from(300u128));
+        assert_eq!(denom.stats.filled_count, 1);
+        assert_eq!(denom.stats.filled_volume, Uint128::from(100u128));
+        assert_eq!(denom.stats.settled_volume, Uint128::from(100u128));
+        assert_eq!(denom.stats.expired_volume, Uint128::from(100u128));
+        assert_eq!(
+            denom.average_fill_blocks,
+            Some(Decimal::from_ratio(4u64, 1u64))
+        );
+
+        assert_eq!(response.makers.len(), 1);
+        assert_eq!(response.makers[0].maker, Addr::unchecked("market_maker"));
+        assert_eq!(response.makers[0].stats.filled_count, 1);
+        assert_eq!(response.makers[0].stats.settled_count, 1);
+        assert_eq!(response.makers[0].stats.fee_revenue, Uint128::zero());
+
+        assert_eq!(
+            response.status_counts,
+            StatusCounts {
+                pending: 1,
+                fulfilled: 0,
+                settled: 1,
+                expired: 1,
+                cancelled: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_stats_fee_revenue() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("module_account", &[]);
+
+        // Initialize state with a 1% protocol fee
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let mut config = mock_config();
+        config.protocol_fee_bps = 100;
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            "owner".to_string(),
+            Coin::new(1_000, "token"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // Fulfill the transaction and confirm the bank send
+        fulfill_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &coins(1_000, "token")),
+            0,
+        )
+        .unwrap();
+        let msg = Reply {
+            id: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                data: None,
+                events: vec![],
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), msg).unwrap();
+
+        // The protocol fee is recorded as soon as the fill is, and settling adds nothing
+        let response: StatsResponse = from_json(query_stats(deps.as_ref()).unwrap()).unwrap();
+        assert_eq!(response.makers[0].stats.fee_revenue, Uint128::from(10u128));
+        remove_fulfilled_tx(deps.as_mut(), env, info, 0).unwrap();
+        let response: StatsResponse = from_json(query_stats(deps.as_ref()).unwrap()).unwrap();
+        assert_eq!(response.makers[0].stats.settled_count, 1);
+        assert_eq!(response.makers[0].stats.fee_revenue, Uint128::from(10u128));
+    }
+
+    #[test]
+    fn test_query_txs_by_fulfiller_and_destination() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("module_account", &[]);
+
+        let output_coin = Coin {
+            denom: "token".to_string(),
+            amount: Uint128::from(100u128),
+        };
+
+        // Initialize state
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        // Add 2 transactions to alice and 1 to bob
+        for destination in ["alice", "alice", "bob"] {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                info.clone(),
+                destination.to_string(),
+                output_coin.clone(),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        // Fulfill and settle the first transaction
+        let msg = Reply {
+            id: 0,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                data: None,
+                events: vec![],
+            }),
+        };
+        move_pending_tx_to_fulfilled_tx(
+            deps.as_mut(),
+            env.clone(),
+            msg,
+            FulfillState {
+                fulfiller_addr: Addr::unchecked("market_maker"),
+                protocol_fee: Uint128::zero(),
+            },
+        )
+        .unwrap();
+        remove_fulfilled_tx(deps.as_mut(), env.clone(), info.clone(), 0).unwrap();
+
+        // The settled transaction keeps its original destination and is still in the history
+        let result =
+            query_txs_by_fulfiller(deps.as_ref(), "market_maker".to_string(), None, None).unwrap();
+        let response: TxRecordsResponse = from_json(result).unwrap();
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].tx.id, 0);
+        assert_eq!(response.records[0].tx.destination_addr, "alice");
+        assert_eq!(response.records[0].status, TxStatus::Settled);
+
+        // Paginate over the destination index
+        let result =
+            query_txs_by_destination(deps.as_ref(), "alice".to_string(), None, Some(1)).unwrap();
+        let response: TxRecordsResponse = from_json(result).unwrap();
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].tx.id, 0);
+
+        let result =
+            query_txs_by_destination(deps.as_ref(), "alice".to_string(), Some(0), None).unwrap();
+        let response: TxRecordsResponse = from_json(result).unwrap();
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].tx.id, 1);
+        assert_eq!(response.records[0].status, TxStatus::Pending);
+
+        // Once the retention window has passed, the next transition prunes the settled transaction
+        env.block.height += 101;
+        remove_pending_tx(deps.as_mut(), env, info, 2).unwrap();
+
+        let result =
+            query_txs_by_fulfiller(deps.as_ref(), "market_maker".to_string(), None, None).unwrap();
+        let response: TxRecordsResponse = from_json(result).unwrap();
+        assert!(response.records.is_empty());
+
+        let result =
+            query_txs_by_destination(deps.as_ref(), "bob".to_string(), None, None).unwrap();
+        let response: TxRecordsResponse = from_json(result).unwrap();
+        assert_eq!(response.records.len(), 1);
+        assert_eq!(response.records[0].status, TxStatus::Expired);
+    }
 }
@@ -0,0 +1,228 @@
+use cosmwasm_std::{Addr, Coin, Deps, DepsMut, Env, MessageInfo, Response};
+
+use crate::execute::TxOrigin;
+use crate::state::{PosterScope, State, CONFIG, POSTERS};
+use crate::ContractError;
+
+impl PosterScope {
+    // covers returns whether a tx in the given denom, funded from the given origin,
+    // falls within the scope. An empty list places no restriction on that dimension,
+    // but a scope restricted to origin chains never covers a tx without an origin.
+    pub fn covers(&self, denom: &str, origin: Option<&TxOrigin>) -> bool {
+        let denom_allowed = self.denoms.is_empty() || self.denoms.iter().any(|d| d == denom);
+        let chain_allowed = self.origin_chains.is_empty()
+            || origin.is_some_and(|origin| self.origin_chains.contains(&origin.chain));
+        denom_allowed && chain_allowed
+    }
+}
+
+// authorize_poster checks that the sender may add, expire or settle the given tx.
+// The module account set at instantiation may act on any tx, while registered
+// posters may only act on txs within their scope.
+pub fn authorize_poster(
+    deps: Deps,
+    state: &State,
+    sender: &Addr,
+    coin: &Coin,
+    origin: Option<&TxOrigin>,
+) -> Result<(), ContractError> {
+    if *sender == state.module_account {
+        return Ok(());
+    }
+
+    match POSTERS.may_load(deps.storage, sender)? {
+        Some(scope) if scope.covers(&coin.denom, origin) => Ok(()),
+        _ => Err(ContractError::Unauthorized {}),
+    }
+}
+
+// set_poster is called by the admin to register a poster, or replace the scope of
+// an existing one.
+pub fn set_poster(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: String,
+    scope: PosterScope,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    POSTERS.save(deps.storage, &address, &scope)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_poster")
+        .add_attribute("poster", address)
+        .add_attribute("denoms", scope.denoms.join(","))
+        .add_attribute("origin_chains", scope.origin_chains.join(",")))
+}
+
+// remove_poster is called by the admin to revoke a poster.
+pub fn remove_poster(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    if !POSTERS.has(deps.storage, &address) {
+        return Err(ContractError::PosterNotFound {
+            address: address.to_string(),
+        });
+    }
+    POSTERS.remove(deps.storage, &address);
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_poster")
+        .add_attribute("poster", address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{add_pending_tx, remove_pending_tx};
+    use crate::state::STATE;
+    use crate::testing::{mock_config, mock_denom};
+    use cosmwasm_std::coin;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+
+    fn btc_origin() -> TxOrigin {
+        TxOrigin {
+            chain: "bitcoin".to_string(),
+            tx_hash: "deadbeef".to_string(),
+            vout: 0,
+        }
+    }
+
+    #[test]
+    fn test_poster_scope_covers() {
+        let scope = PosterScope {
+            origin_chains: vec!["bitcoin".to_string()],
+            denoms: vec![],
+        };
+        assert!(scope.covers("ubtc", Some(&btc_origin())));
+        assert!(!scope.covers("ubtc", None));
+
+        let scope = PosterScope {
+            origin_chains: vec![],
+            denoms: vec!["ubtc".to_string()],
+        };
+        assert!(scope.covers("ubtc", None));
+        assert!(!scope.covers("ueth", Some(&btc_origin())));
+
+        assert!(PosterScope::default().covers("ueth", None));
+    }
+
+    #[test]
+    fn test_scoped_poster() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        mock_denom(deps.as_mut().storage, "ubtc");
+        mock_denom(deps.as_mut().storage, "ueth");
+
+        // Only the admin can register posters
+        let scope = PosterScope {
+            origin_chains: vec!["bitcoin".to_string()],
+            denoms: vec!["ubtc".to_string()],
+        };
+        let err = set_poster(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            "relayer".to_string(),
+            scope.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        set_poster(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "relayer".to_string(),
+            scope,
+        )
+        .unwrap();
+
+        // The poster can add and expire txs within its scope
+        let relayer_info = mock_info("relayer", &[]);
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            relayer_info.clone(),
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            Some(btc_origin()),
+            None,
+            None,
+        )
+        .unwrap();
+        remove_pending_tx(deps.as_mut(), env.clone(), relayer_info.clone(), 0).unwrap();
+
+        // But not outside of it
+        let err = add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            relayer_info.clone(),
+            "destination_addr".to_string(),
+            coin(100, "ueth"),
+            Some(btc_origin()),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("module_account", &[]),
+            "destination_addr".to_string(),
+            coin(100, "ueth"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let err =
+            remove_pending_tx(deps.as_mut(), env.clone(), relayer_info.clone(), 1).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // Once removed, the poster can no longer add txs
+        remove_poster(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "relayer".to_string(),
+        )
+        .unwrap();
+        let err = add_pending_tx(
+            deps.as_mut(),
+            env,
+            relayer_info,
+            "destination_addr".to_string(),
+            coin(100, "ubtc"),
+            Some(btc_origin()),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+}
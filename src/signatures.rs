@@ -0,0 +1,571 @@
+use std::collections::BTreeSet;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_vec, Binary, Coin, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::execute::{Tx, TxOrigin, TxOwner};
+use crate::state::{KeyType, Signer, SignerSet, CONFIG, SIGNER_SET, SIGNER_SET_VERSION};
+use crate::ContractError;
+
+// OrderSignature is a signature by one member of the signer set over the digest of
+// the canonical order payload
+#[cw_serde]
+pub struct OrderSignature {
+    pub pubkey: Binary,
+    pub signature: Binary,
+}
+
+// SignedOrderPayload is the canonical payload signers sign to authorize a tx. It is bound
+// to the chain, the contract and the signer set version, so signatures cannot be replayed
+// elsewhere or after the signer set is rotated. The origin deposit can only fund a single
+// tx, which prevents replays on this contract.
+#[cw_serde]
+pub struct SignedOrderPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub signer_set_version: u64,
+    pub destination_addr: String,
+    pub output_coin: Coin,
+    pub origin: TxOrigin,
+    pub owner: Option<TxOwner>,
+}
+
+// OwnerAction is a change the owner of a pending tx authorizes
+#[cw_serde]
+pub enum OwnerAction {
+    Cancel {},
+    Amend { destination_addr: String },
+}
+
+// OwnerActionPayload is the canonical payload an owner signs to authorize a change to their
+// tx. It is bound to the number of amendments already made, so an amendment can't be
+// replayed once the destination has changed again.
+#[cw_serde]
+pub struct OwnerActionPayload {
+    pub chain_id: String,
+    pub contract: String,
+    pub tx_id: u64,
+    pub amendments: u32,
+    pub action: OwnerAction,
+}
+
+// payload_digest returns the sha256 digest of the canonical JSON encoding of a payload,
+// which is what gets signed.
+pub fn payload_digest<T: Serialize>(payload: &T) -> StdResult<[u8; 32]> {
+    Ok(Sha256::digest(to_json_vec(payload)?).into())
+}
+
+// verify_owner_signature checks the owner of a tx signed off on the action
+pub fn verify_owner_signature(
+    deps: Deps,
+    env: &Env,
+    tx: &Tx,
+    signer: &Signer,
+    action: OwnerAction,
+    signature: &Binary,
+) -> Result<(), ContractError> {
+    let digest = payload_digest(&OwnerActionPayload {
+        chain_id: env.block.chain_id.clone(),
+        contract: env.contract.address.to_string(),
+        tx_id: tx.id,
+        amendments: tx.amendments,
+        action,
+    })?;
+    let valid = match signer.key_type {
+        KeyType::Secp256k1 => {
+            deps.api
+                .secp256k1_verify(&digest, signature.as_slice(), signer.pubkey.as_slice())?
+        }
+        KeyType::Ed25519 => {
+            deps.api
+                .ed25519_verify(&digest, signature.as_slice(), signer.pubkey.as_slice())?
+        }
+    };
+    if !valid {
+        return Err(ContractError::InvalidSignature {
+            pubkey: signer.pubkey.to_base64(),
+        });
+    }
+    Ok(())
+}
+
+// verify_order_signatures checks that at least the threshold of distinct members of the
+// current signer set produced a valid signature over the order.
+pub fn verify_order_signatures(
+    deps: Deps,
+    env: &Env,
+    destination_addr: &str,
+    output_coin: &Coin,
+    origin: &TxOrigin,
+    owner: Option<&TxOwner>,
+    signatures: &[OrderSignature],
+) -> Result<(), ContractError> {
+    let signer_set = SIGNER_SET
+        .may_load(deps.storage)?
+        .ok_or(ContractError::SignerSetNotConfigured {})?;
+
+    let digest = payload_digest(&SignedOrderPayload {
+        chain_id: env.block.chain_id.clone(),
+        contract: env.contract.address.to_string(),
+        signer_set_version: signer_set.version,
+        destination_addr: destination_addr.to_string(),
+        output_coin: output_coin.clone(),
+        origin: origin.clone(),
+        owner: owner.cloned(),
+    })?;
+
+    let mut signed = BTreeSet::new();
+    let mut ed25519_signatures: Vec<&[u8]> = vec![];
+    let mut ed25519_pubkeys: Vec<&[u8]> = vec![];
+    for signature in signatures {
+        let signer = signer_set
+            .signers
+            .iter()
+            .find(|signer| signer.pubkey == signature.pubkey)
+            .ok_or_else(|| ContractError::UnknownSigner {
+                pubkey: signature.pubkey.to_base64(),
+            })?;
+        // Each signer only counts once towards the threshold
+        if !signed.insert(signer.pubkey.to_base64()) {
+            continue;
+        }
+
+        match signer.key_type {
+            KeyType::Secp256k1 => {
+                let valid = deps.api.secp256k1_verify(
+                    &digest,
+                    signature.signature.as_slice(),
+                    signer.pubkey.as_slice(),
+                )?;
+                if !valid {
+                    return Err(ContractError::InvalidSignature {
+                        pubkey: signer.pubkey.to_base64(),
+                    });
+                }
+            }
+            KeyType::Ed25519 => {
+                ed25519_signatures.push(signature.signature.as_slice());
+                ed25519_pubkeys.push(signer.pubkey.as_slice());
+            }
+        }
+    }
+
+    // All ed25519 signatures are over the same digest, so they are verified in one batch
+    if !ed25519_signatures.is_empty() {
+        let messages: Vec<&[u8]> = vec![&digest];
+        let valid =
+            deps.api
+                .ed25519_batch_verify(&messages, &ed25519_signatures, &ed25519_pubkeys)?;
+        if !valid {
+            return Err(ContractError::InvalidSignature {
+                pubkey: "ed25519 batch".to_string(),
+            });
+        }
+    }
+
+    if (signed.len() as u32) < signer_set.threshold {
+        return Err(ContractError::InsufficientSignatures {
+            threshold: signer_set.threshold,
+            signatures: signed.len() as u32,
+        });
+    }
+    Ok(())
+}
+
+// update_signer_set is called by the admin to rotate the signer set. Every update bumps
+// the version, invalidating signatures made for any previous set. A threshold of zero
+// disables signed orders.
+pub fn update_signer_set(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    signers: Vec<Signer>,
+    threshold: u32,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let version = SIGNER_SET_VERSION
+        .may_load(deps.storage)?
+        .map_or(0, |version| version + 1);
+    if threshold == 0 {
+        SIGNER_SET_VERSION.save(deps.storage, &version)?;
+        SIGNER_SET.remove(deps.storage);
+        return Ok(Response::new()
+            .add_attribute("action", "update_signer_set")
+            .add_attribute("version", version.to_string())
+            .add_attribute("threshold", "0"));
+    }
+
+    let unique = signers
+        .iter()
+        .map(|signer| signer.pubkey.clone())
+        .collect::<BTreeSet<_>>();
+    if threshold as usize > unique.len() {
+        return Err(ContractError::InvalidSignerThreshold {
+            threshold,
+            signers: unique.len() as u32,
+        });
+    }
+    for signer in signers.iter() {
+        let expected_len = match signer.key_type {
+            // Compressed or uncompressed SEC1 encoding
+            KeyType::Secp256k1 => [33, 65].contains(&signer.pubkey.len()),
+            KeyType::Ed25519 => signer.pubkey.len() == 32,
+        };
+        if !expected_len {
+            return Err(ContractError::InvalidPubkey {
+                pubkey: signer.pubkey.to_base64(),
+            });
+        }
+    }
+
+    SIGNER_SET_VERSION.save(deps.storage, &version)?;
+    SIGNER_SET.save(
+        deps.storage,
+        &SignerSet {
+            version,
+            threshold,
+            signers,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_signer_set")
+        .add_attribute("version", version.to_string())
+        .add_attribute("threshold", threshold.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attestation::{attest_tx, configure_attestation};
+    use crate::execute::add_pending_tx;
+    use crate::state::{STATE, TX_ATTESTATIONS};
+    use crate::testing::{mock_denom, mock_state};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, Order, Storage};
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+
+    fn secp256k1_key(seed: u8) -> k256::ecdsa::SigningKey {
+        k256::ecdsa::SigningKey::from_bytes(&[seed; 32].into()).unwrap()
+    }
+
+    fn secp256k1_signer(key: &k256::ecdsa::SigningKey) -> Signer {
+        Signer {
+            key_type: KeyType::Secp256k1,
+            pubkey: Binary::from(key.verifying_key().to_sec1_bytes().to_vec()),
+        }
+    }
+
+    fn secp256k1_sign(key: &k256::ecdsa::SigningKey, digest: &[u8; 32]) -> OrderSignature {
+        let signature: k256::ecdsa::Signature = key.sign_prehash(digest).unwrap();
+        OrderSignature {
+            pubkey: secp256k1_signer(key).pubkey,
+            signature: Binary::from(signature.to_bytes().to_vec()),
+        }
+    }
+
+    fn ed25519_sign(key: &ed25519_zebra::SigningKey, digest: &[u8; 32]) -> OrderSignature {
+        let pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(key).into();
+        let signature: [u8; 64] = key.sign(digest).into();
+        OrderSignature {
+            pubkey: Binary::from(pubkey.to_vec()),
+            signature: Binary::from(signature.to_vec()),
+        }
+    }
+
+    fn mock_origin() -> TxOrigin {
+        TxOrigin {
+            chain: "bitcoin".to_string(),
+            tx_hash: "deadbeef".to_string(),
+            vout: 0,
+        }
+    }
+
+    fn setup(storage: &mut dyn Storage) {
+        mock_state(storage);
+        mock_denom(storage, "token");
+    }
+
+    fn digest(env: &Env, version: u64) -> [u8; 32] {
+        payload_digest(&SignedOrderPayload {
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.to_string(),
+            signer_set_version: version,
+            destination_addr: "destination_addr".to_string(),
+            output_coin: coin(100, "token"),
+            origin: mock_origin(),
+            owner: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_signed_add_tx() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut().storage);
+
+        let secp256k1 = secp256k1_key(1);
+        let ed25519 = ed25519_zebra::SigningKey::from([2; 32]);
+        let ed25519_pubkey: [u8; 32] = ed25519_zebra::VerificationKey::from(&ed25519).into();
+        let signers = vec![
+            secp256k1_signer(&secp256k1),
+            Signer {
+                key_type: KeyType::Ed25519,
+                pubkey: Binary::from(ed25519_pubkey.to_vec()),
+            },
+            secp256k1_signer(&secp256k1_key(3)),
+        ];
+        update_signer_set(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            signers,
+            2,
+        )
+        .unwrap();
+
+        let add = |deps: DepsMut, signatures: Vec<OrderSignature>| {
+            add_pending_tx(
+                deps,
+                env.clone(),
+                mock_info("relayer", &[]),
+                "destination_addr".to_string(),
+                coin(100, "token"),
+                Some(mock_origin()),
+                None,
+                Some(signatures),
+            )
+        };
+
+        // A single signature, even repeated, does not reach the threshold
+        let digest = digest(&env, 0);
+        let signature = secp256k1_sign(&secp256k1, &digest);
+        let err = add(deps.as_mut(), vec![signature.clone(), signature.clone()]).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InsufficientSignatures {
+                threshold: 2,
+                signatures: 1
+            }
+        ));
+
+        // Signatures over a different order are rejected
+        let mut forged = ed25519_sign(&ed25519, &[0; 32]);
+        let err = add(deps.as_mut(), vec![signature.clone(), forged.clone()]).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature { .. }));
+
+        // Signatures from keys outside the signer set are rejected
+        forged.pubkey = Binary::from(vec![0; 32]);
+        let err = add(deps.as_mut(), vec![signature.clone(), forged]).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownSigner { .. }));
+
+        // Any relayer can submit an order signed by enough of the signer set
+        let signatures = vec![signature, ed25519_sign(&ed25519, &digest)];
+        add(deps.as_mut(), signatures.clone()).unwrap();
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs.len(), 1);
+        assert_eq!(state.pending_txs[0].origin, Some(mock_origin()));
+
+        // The origin deposit can only fund a single tx
+        let err = add(deps.as_mut(), signatures).unwrap_err();
+        assert!(matches!(err, ContractError::OriginAlreadyProcessed { .. }));
+    }
+
+    #[test]
+    fn test_signed_add_tx_with_attestation_enabled() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut().storage);
+
+        let key = secp256k1_key(1);
+        update_signer_set(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            vec![secp256k1_signer(&key)],
+            1,
+        )
+        .unwrap();
+        configure_attestation(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            vec!["attester1".to_string(), "attester2".to_string()],
+            2,
+        )
+        .unwrap();
+        let attest = |deps: DepsMut, attester: &str| {
+            attest_tx(
+                deps,
+                env.clone(),
+                mock_info(attester, &[]),
+                "destination_addr".to_string(),
+                coin(100, "token"),
+                mock_origin(),
+                None,
+            )
+        };
+        attest(deps.as_mut(), "attester1").unwrap();
+
+        // A signed order doesn't wait for the attesters
+        let signatures = vec![secp256k1_sign(&key, &digest(&env, 0))];
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            "destination_addr".to_string(),
+            coin(100, "token"),
+            Some(mock_origin()),
+            None,
+            Some(signatures),
+        )
+        .unwrap();
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.pending_txs.len(), 1);
+
+        // The attestations it made redundant are cleared and no more can be made
+        assert!(TX_ATTESTATIONS
+            .prefix(&mock_origin().key())
+            .range(deps.as_ref().storage, None, None, Order::Ascending)
+            .next()
+            .is_none());
+        let err = attest(deps.as_mut(), "attester2").unwrap_err();
+        assert!(matches!(err, ContractError::OriginAlreadyProcessed { .. }));
+    }
+
+    #[test]
+    fn test_update_signer_set() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut().storage);
+
+        let key = secp256k1_key(1);
+        let signers = vec![secp256k1_signer(&key)];
+
+        let err = update_signer_set(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            signers.clone(),
+            1,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let err = update_signer_set(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            signers.clone(),
+            2,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignerThreshold { .. }));
+
+        let err = update_signer_set(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            vec![Signer {
+                key_type: KeyType::Ed25519,
+                pubkey: Binary::from(vec![0; 33]),
+            }],
+            1,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPubkey { .. }));
+
+        // Rotating the signer set invalidates signatures made for the previous version
+        for _ in 0..2 {
+            update_signer_set(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("admin", &[]),
+                signers.clone(),
+                1,
+            )
+            .unwrap();
+        }
+        assert_eq!(SIGNER_SET.load(deps.as_ref().storage).unwrap().version, 1);
+
+        let stale = secp256k1_sign(&key, &digest(&env, 0));
+        let err = verify_order_signatures(
+            deps.as_ref(),
+            &env,
+            "destination_addr",
+            &coin(100, "token"),
+            &mock_origin(),
+            None,
+            &[stale],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature { .. }));
+
+        let current = secp256k1_sign(&key, &digest(&env, 1));
+        verify_order_signatures(
+            deps.as_ref(),
+            &env,
+            "destination_addr",
+            &coin(100, "token"),
+            &mock_origin(),
+            None,
+            &[current],
+        )
+        .unwrap();
+
+        // A threshold of zero disables signed orders
+        update_signer_set(deps.as_mut(), env, mock_info("admin", &[]), vec![], 0).unwrap();
+        assert!(SIGNER_SET
+            .may_load(deps.as_ref().storage)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_reenabled_signer_set_rejects_old_signatures() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut().storage);
+
+        let key = secp256k1_key(1);
+        let signers = vec![secp256k1_signer(&key)];
+        let update = |deps: DepsMut, signers: Vec<Signer>, threshold| {
+            update_signer_set(
+                deps,
+                env.clone(),
+                mock_info("admin", &[]),
+                signers,
+                threshold,
+            )
+        };
+        update(deps.as_mut(), signers.clone(), 1).unwrap();
+        let old = secp256k1_sign(&key, &digest(&env, 0));
+
+        // Disabling and enabling the same set again still moves on to a new version
+        update(deps.as_mut(), vec![], 0).unwrap();
+        update(deps.as_mut(), signers, 1).unwrap();
+        assert_eq!(SIGNER_SET.load(deps.as_ref().storage).unwrap().version, 2);
+
+        let err = add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            "destination_addr".to_string(),
+            coin(100, "token"),
+            Some(mock_origin()),
+            None,
+            Some(vec![old]),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidSignature { .. }));
+    }
+}
@@ -0,0 +1,733 @@
+// Economic simulation of the order book. A trace of origin chain deposits, their
+// confirmations and reorgs is replayed through the contract's entry points on mock deps,
+// with the simulation acting as the bank, the module account and a set of market makers
+// following configurable strategies.
+//
+// A deposit is posted once it has `post_confirmations`, and closed once it has
+// `safe_confirmations`: expired if still pending, settled otherwise, repaying the maker.
+// A reorg drops the deposit for good. Its tx is removed if still pending, and a maker that
+// already filled it is never repaid.
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    coins, from_json, BankMsg, Coin, CosmosMsg, Env, Int128, OwnedDeps, Reply, Response, StdError,
+    SubMsgResponse, SubMsgResult, Timestamp, Uint128,
+};
+use thiserror::Error;
+
+use crate::contract::{execute, instantiate, reply};
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::state::{DenomMetadata, SettlementMode, STATE};
+use crate::ContractError;
+
+const MODULE_ACCOUNT: &str = "module_account";
+const DESTINATION: &str = "destination";
+
+#[cw_serde]
+pub struct SimConfig {
+    pub denom: String,
+    pub protocol_fee_bps: u64,
+    pub block_time_seconds: u64,
+    pub post_confirmations: u32,
+    pub safe_confirmations: u32,
+    pub makers: Vec<MakerStrategy>,
+}
+
+// MakerStrategy is how a simulated market maker picks and races for fills
+#[cw_serde]
+pub struct MakerStrategy {
+    pub name: String,
+    pub balance: Uint128,
+    // Confirmations a deposit needs before the maker takes on its reorg risk
+    pub min_confirmations: u32,
+    // Seconds between a tx becoming eligible and the maker's fill executing
+    pub latency_seconds: u64,
+    pub max_amount: Uint128,
+    // Maximum amount of the maker's fills awaiting settlement
+    pub max_exposure: Uint128,
+}
+
+#[cw_serde]
+pub enum TraceEventKind {
+    Deposit,
+    Confirmation,
+    Reorg,
+}
+
+// TraceEvent is a line of a trace. Deposits carry their amount and confirmations their new
+// confirmation count.
+#[cw_serde]
+pub struct TraceEvent {
+    pub time: u64,
+    pub event: TraceEventKind,
+    pub deposit: String,
+    #[serde(default)]
+    pub amount: Option<Uint128>,
+    #[serde(default)]
+    pub confirmations: Option<u32>,
+}
+
+#[derive(Error, Debug)]
+pub enum SimError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Contract(#[from] ContractError),
+
+    #[error("Invalid trace at line {line:}: {reason:}")]
+    InvalidTrace { line: usize, reason: String },
+}
+
+// parse_trace reads a trace in CSV, with a `time,event,deposit,amount,confirmations`
+// header, or as JSON lines
+pub fn parse_trace(contents: &str, csv: bool) -> Result<Vec<TraceEvent>, SimError> {
+    let mut events = vec![];
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (csv && index == 0) {
+            continue;
+        }
+        let invalid = |reason: String| SimError::InvalidTrace {
+            line: index + 1,
+            reason,
+        };
+        let event = if csv {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [time, event, deposit, amount, confirmations] = fields[..] else {
+                return Err(invalid(format!("expected 5 fields, got {}", fields.len())));
+            };
+            TraceEvent {
+                time: time.parse().map_err(|_| invalid(format!("time {time}")))?,
+                event: match event {
+                    "deposit" => TraceEventKind::Deposit,
+                    "confirmation" => TraceEventKind::Confirmation,
+                    "reorg" => TraceEventKind::Reorg,
+                    _ => return Err(invalid(format!("event {event}"))),
+                },
+                deposit: deposit.to_string(),
+                amount: match amount {
+                    "" => None,
+                    amount => Some(
+                        amount
+                            .parse()
+                            .map_err(|_| invalid(format!("amount {amount}")))?,
+                    ),
+                },
+                confirmations: match confirmations {
+                    "" => None,
+                    count => Some(
+                        count
+                            .parse()
+                            .map_err(|_| invalid(format!("confirmations {count}")))?,
+                    ),
+                },
+            }
+        } else {
+            from_json(line).map_err(|err| invalid(err.to_string()))?
+        };
+        let missing = match event.event {
+            TraceEventKind::Deposit => event.amount.is_none(),
+            TraceEventKind::Confirmation => event.confirmations.is_none(),
+            TraceEventKind::Reorg => false,
+        };
+        if missing {
+            return Err(invalid(format!("{:?} without its value", event.event)));
+        }
+        events.push(event);
+    }
+    Ok(events)
+}
+
+// Distribution summarizes samples with nearest-rank percentiles
+#[cw_serde]
+#[derive(Default)]
+pub struct Distribution {
+    pub count: u64,
+    pub min: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl Distribution {
+    pub fn new(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Distribution::default();
+        }
+        samples.sort_unstable();
+        let rank = |percentile: usize| {
+            let rank = (percentile * samples.len()).div_ceil(100);
+            samples[rank.max(1) - 1]
+        };
+        Distribution {
+            count: samples.len() as u64,
+            min: samples[0],
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+#[cw_serde]
+pub struct MakerReport {
+    pub name: String,
+    pub fills: u64,
+    // Share of the posted txs the maker filled
+    pub fill_rate: String,
+    pub filled_volume: Uint128,
+    pub repaid_volume: Uint128,
+    // Fills whose deposit was dropped by a reorg, never to be repaid
+    pub lost_volume: Uint128,
+    // Fills still awaiting settlement when the trace ends
+    pub outstanding_volume: Uint128,
+    // Repaid less filled volume, counting outstanding fills as not yet repaid
+    pub pnl: Int128,
+    pub seconds_to_fill: Distribution,
+}
+
+#[cw_serde]
+pub struct SimReport {
+    pub deposits: u64,
+    pub posted: u64,
+    pub filled: u64,
+    pub expired: u64,
+    pub reorged: u64,
+    pub fill_rate: String,
+    pub protocol_fees: Uint128,
+    pub destination_received: Uint128,
+    pub seconds_to_fill: Distribution,
+    pub makers: Vec<MakerReport>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Seen,
+    Posted,
+    Closed,
+}
+
+struct Deposit {
+    amount: Uint128,
+    confirmations: u32,
+    phase: Phase,
+    tx_id: Option<u64>,
+    posted_at: u64,
+    // The maker that filled the deposit's tx, and when
+    fill: Option<(usize, u64)>,
+    // Makers already scheduled to race for the tx
+    scheduled: Vec<bool>,
+}
+
+#[derive(Default)]
+struct MakerLedger {
+    balance: Uint128,
+    fills: u64,
+    filled: Uint128,
+    repaid: Uint128,
+    lost: Uint128,
+    exposure: Uint128,
+    seconds_to_fill: Vec<u64>,
+}
+
+struct Sim<'a> {
+    config: &'a SimConfig,
+    deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    contract_balance: Uint128,
+    now: u64,
+    deposits: BTreeMap<String, Deposit>,
+    makers: Vec<MakerLedger>,
+    // Fill attempts by time, then scheduling order
+    attempts: BinaryHeap<Reverse<(u64, u64, usize, String)>>,
+    scheduled: u64,
+    report: SimReport,
+}
+
+impl<'a> Sim<'a> {
+    fn new(config: &'a SimConfig) -> Result<Self, SimError> {
+        let mut sim = Sim {
+            config,
+            deps: mock_dependencies(),
+            contract_balance: Uint128::zero(),
+            now: 0,
+            deposits: BTreeMap::new(),
+            makers: config
+                .makers
+                .iter()
+                .map(|maker| MakerLedger {
+                    balance: maker.balance,
+                    ..Default::default()
+                })
+                .collect(),
+            attempts: BinaryHeap::new(),
+            scheduled: 0,
+            report: SimReport {
+                deposits: 0,
+                posted: 0,
+                filled: 0,
+                expired: 0,
+                reorged: 0,
+                fill_rate: String::new(),
+                protocol_fees: Uint128::zero(),
+                destination_received: Uint128::zero(),
+                seconds_to_fill: Distribution::default(),
+                makers: vec![],
+            },
+        };
+
+        let env = sim.env();
+        instantiate(
+            sim.deps.as_mut(),
+            env,
+            mock_info(MODULE_ACCOUNT, &[]),
+            InstantiateMsg {
+                module_account: MODULE_ACCOUNT.to_string(),
+                admin: None,
+                history_retention_blocks: None,
+                protocol_fee_bps: Some(config.protocol_fee_bps),
+                withdrawal_timelock_seconds: None,
+                foreign_destination_formats: None,
+                outbound_timeout_seconds: None,
+                destination_routes: None,
+            },
+        )?;
+        sim.execute(
+            MODULE_ACCOUNT,
+            ExecuteMsg::SetDenom {
+                denom: config.denom.clone(),
+                metadata: DenomMetadata {
+                    symbol: config.denom.clone(),
+                    decimals: 0,
+                    origin_chain: "origin".to_string(),
+                    enabled: true,
+                    settlement: SettlementMode::Module,
+                },
+            },
+            None,
+        )?;
+        Ok(sim)
+    }
+
+    fn env(&self) -> Env {
+        let mut env = mock_env();
+        env.block.height = 1 + self.now / self.config.block_time_seconds.max(1);
+        env.block.time = Timestamp::from_seconds(self.now);
+        env
+    }
+
+    // execute runs a message with the funds already credited to the contract, as the bank
+    // would, and applies the bank sends of the response
+    fn execute(
+        &mut self,
+        sender: &str,
+        msg: ExecuteMsg,
+        funds: Option<Uint128>,
+    ) -> Result<Response, ContractError> {
+        let funds = funds.map(|amount| coins(amount.u128(), &self.config.denom));
+        let env = self.env();
+        let before = self.contract_balance;
+        if let Some(funds) = &funds {
+            self.set_contract_balance(before + funds[0].amount);
+        }
+        let response = execute(
+            self.deps.as_mut(),
+            env,
+            mock_info(sender, funds.as_deref().unwrap_or_default()),
+            msg,
+        );
+        let response = match response {
+            Ok(response) => response,
+            Err(err) => {
+                self.set_contract_balance(before);
+                return Err(err);
+            }
+        };
+
+        for sub_msg in response.messages.iter() {
+            if let CosmosMsg::Bank(BankMsg::Send { to_address, amount }) = &sub_msg.msg {
+                let sent: Uint128 = amount.iter().map(|coin| coin.amount).sum();
+                self.set_contract_balance(self.contract_balance - sent);
+                if to_address == DESTINATION {
+                    self.report.destination_received += sent;
+                }
+                let env = self.env();
+                reply(
+                    self.deps.as_mut(),
+                    env,
+                    Reply {
+                        id: sub_msg.id,
+                        result: SubMsgResult::Ok(SubMsgResponse {
+                            events: vec![],
+                            data: None,
+                        }),
+                    },
+                )?;
+            }
+        }
+        Ok(response)
+    }
+
+    fn set_contract_balance(&mut self, amount: Uint128) {
+        self.contract_balance = amount;
+        let contract = self.env().contract.address;
+        self.deps
+            .querier
+            .update_balance(contract, vec![Coin::new(amount.u128(), &self.config.denom)]);
+    }
+
+    fn is_pending(&self, tx_id: u64) -> Result<bool, SimError> {
+        let state = STATE.load(&self.deps.storage)?;
+        Ok(state.pending_txs.iter().any(|tx| tx.id == tx_id))
+    }
+
+    fn apply(&mut self, event: &TraceEvent) -> Result<(), SimError> {
+        if event.event == TraceEventKind::Deposit {
+            if !self.deposits.contains_key(&event.deposit) {
+                self.report.deposits += 1;
+                self.deposits.insert(
+                    event.deposit.clone(),
+                    Deposit {
+                        amount: event.amount.unwrap_or_default(),
+                        confirmations: 0,
+                        phase: Phase::Seen,
+                        tx_id: None,
+                        posted_at: 0,
+                        fill: None,
+                        scheduled: vec![false; self.makers.len()],
+                    },
+                );
+            }
+            return self.advance(&event.deposit);
+        }
+        let Some(deposit) = self.deposits.get_mut(&event.deposit) else {
+            return Ok(());
+        };
+        if deposit.phase == Phase::Closed {
+            return Ok(());
+        }
+        if event.event == TraceEventKind::Confirmation {
+            deposit.confirmations = event.confirmations.unwrap_or_default();
+            return self.advance(&event.deposit);
+        }
+
+        // A reorg drops the deposit: its tx is removed if still pending, and a maker that
+        // filled it is left without repayment
+        self.report.reorged += 1;
+        deposit.phase = Phase::Closed;
+        let (tx_id, fill, amount) = (deposit.tx_id, deposit.fill, deposit.amount);
+        if let Some(tx_id) = tx_id {
+            if self.is_pending(tx_id)? {
+                self.execute(MODULE_ACCOUNT, ExecuteMsg::RemoveTx { tx_id }, None)?;
+            }
+        }
+        if let Some((maker, _)) = fill {
+            let ledger = &mut self.makers[maker];
+            ledger.lost += amount;
+            ledger.exposure -= amount;
+        }
+        Ok(())
+    }
+
+    // advance posts, schedules fills for and closes a deposit as its confirmations allow
+    fn advance(&mut self, key: &str) -> Result<(), SimError> {
+        let deposit = &self.deposits[key];
+        let (amount, confirmations) = (deposit.amount, deposit.confirmations);
+
+        if deposit.phase == Phase::Seen && confirmations >= self.config.post_confirmations {
+            let response = self.execute(
+                MODULE_ACCOUNT,
+                ExecuteMsg::AddTx {
+                    destination_addr: DESTINATION.to_string(),
+                    output_coin: Coin::new(amount.u128(), &self.config.denom),
+                    origin: None,
+                    owner: None,
+                    signatures: None,
+                },
+                None,
+            )?;
+            let tx_id = response
+                .attributes
+                .iter()
+                .find(|attr| attr.key == "tx_id")
+                .and_then(|attr| attr.value.parse().ok());
+            let deposit = self.deposits.get_mut(key).unwrap();
+            deposit.phase = Phase::Posted;
+            deposit.tx_id = tx_id;
+            deposit.posted_at = self.now;
+            self.report.posted += 1;
+        }
+
+        let deposit = &self.deposits[key];
+        if deposit.phase != Phase::Posted {
+            return Ok(());
+        }
+        let tx_id = deposit.tx_id.unwrap_or_default();
+
+        if confirmations >= self.config.safe_confirmations {
+            if self.is_pending(tx_id)? {
+                self.execute(MODULE_ACCOUNT, ExecuteMsg::RemoveTx { tx_id }, None)?;
+                self.report.expired += 1;
+            } else if let Some((maker, _)) = deposit.fill {
+                self.execute(
+                    MODULE_ACCOUNT,
+                    ExecuteMsg::RemoveFulfilledTx { tx_id },
+                    None,
+                )?;
+                let ledger = &mut self.makers[maker];
+                ledger.balance += amount;
+                ledger.repaid += amount;
+                ledger.exposure -= amount;
+            }
+            self.deposits.get_mut(key).unwrap().phase = Phase::Closed;
+            return Ok(());
+        }
+
+        if deposit.fill.is_some() {
+            return Ok(());
+        }
+        for (index, strategy) in self.config.makers.iter().enumerate() {
+            let deposit = self.deposits.get_mut(key).unwrap();
+            if deposit.scheduled[index] || confirmations < strategy.min_confirmations {
+                continue;
+            }
+            deposit.scheduled[index] = true;
+            self.attempts.push(Reverse((
+                self.now + strategy.latency_seconds,
+                self.scheduled,
+                index,
+                key.to_string(),
+            )));
+            self.scheduled += 1;
+        }
+        Ok(())
+    }
+
+    // attempt fills a tx on behalf of a maker, if it is still pending and the maker's
+    // strategy allows it
+    fn attempt(&mut self, maker: usize, key: &str) -> Result<(), SimError> {
+        let deposit = &self.deposits[key];
+        let (amount, tx_id) = (deposit.amount, deposit.tx_id.unwrap_or_default());
+        if deposit.phase != Phase::Posted || !self.is_pending(tx_id)? {
+            return Ok(());
+        }
+        let strategy = &self.config.makers[maker];
+        let ledger = &self.makers[maker];
+        if amount > strategy.max_amount
+            || ledger.exposure + amount > strategy.max_exposure
+            || ledger.balance < amount
+        {
+            return Ok(());
+        }
+
+        let name = strategy.name.clone();
+        let response = self.execute(&name, ExecuteMsg::FulfillTx { tx_id }, Some(amount))?;
+        let fee: Uint128 = response
+            .attributes
+            .iter()
+            .find(|attr| attr.key == "protocol_fee")
+            .and_then(|attr| attr.value.trim_end_matches(&self.config.denom).parse().ok())
+            .unwrap_or_default();
+        self.report.protocol_fees += fee;
+        self.report.filled += 1;
+
+        let deposit = self.deposits.get_mut(key).unwrap();
+        deposit.fill = Some((maker, self.now));
+        let seconds = self.now - deposit.posted_at;
+        let ledger = &mut self.makers[maker];
+        ledger.balance -= amount;
+        ledger.fills += 1;
+        ledger.filled += amount;
+        ledger.exposure += amount;
+        ledger.seconds_to_fill.push(seconds);
+        Ok(())
+    }
+
+    fn run_attempts_before(&mut self, time: u64) -> Result<(), SimError> {
+        while let Some(Reverse((at, _, _, _))) = self.attempts.peek() {
+            if *at >= time {
+                break;
+            }
+            let Reverse((at, _, maker, key)) = self.attempts.pop().unwrap();
+            self.now = at;
+            self.attempt(maker, &key)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> SimReport {
+        let rate = |count: u64, total: u64| match total {
+            0 => "0".to_string(),
+            total => format!("{:.4}", count as f64 / total as f64),
+        };
+        let posted = self.report.posted;
+        self.report.fill_rate = rate(self.report.filled, posted);
+        self.report.seconds_to_fill = Distribution::new(
+            self.makers
+                .iter()
+                .flat_map(|ledger| ledger.seconds_to_fill.iter().copied())
+                .collect(),
+        );
+        self.report.makers = self
+            .config
+            .makers
+            .iter()
+            .zip(self.makers)
+            .map(|(strategy, ledger)| MakerReport {
+                name: strategy.name.clone(),
+                fills: ledger.fills,
+                fill_rate: rate(ledger.fills, posted),
+                filled_volume: ledger.filled,
+                repaid_volume: ledger.repaid,
+                lost_volume: ledger.lost,
+                outstanding_volume: ledger.exposure,
+                pnl: Int128::new(ledger.repaid.u128() as i128 - ledger.filled.u128() as i128),
+                seconds_to_fill: Distribution::new(ledger.seconds_to_fill),
+            })
+            .collect();
+        self.report
+    }
+}
+
+// simulate replays a trace, ordered by time, and reports how the makers fared
+pub fn simulate(config: &SimConfig, mut trace: Vec<TraceEvent>) -> Result<SimReport, SimError> {
+    trace.sort_by_key(|event| event.time);
+    let mut sim = Sim::new(config)?;
+    for event in trace.iter() {
+        sim.run_attempts_before(event.time)?;
+        sim.now = event.time;
+        sim.apply(event)?;
+    }
+    sim.run_attempts_before(u64::MAX)?;
+    Ok(sim.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SimConfig {
+        let maker = |name: &str, min_confirmations, latency_seconds| MakerStrategy {
+            name: name.to_string(),
+            balance: Uint128::new(10_000),
+            min_confirmations,
+            latency_seconds,
+            max_amount: Uint128::new(5_000),
+            max_exposure: Uint128::new(4_000),
+        };
+        SimConfig {
+            denom: "ubtc".to_string(),
+            protocol_fee_bps: 100,
+            block_time_seconds: 6,
+            post_confirmations: 1,
+            safe_confirmations: 3,
+            makers: vec![maker("fast", 1, 5), maker("careful", 2, 0)],
+        }
+    }
+
+    const TRACE: &str = "time,event,deposit,amount,confirmations
+0,deposit,a,1000,
+600,confirmation,a,,1
+1200,confirmation,a,,2
+1800,confirmation,a,,3
+0,deposit,b,2000,
+600,confirmation,b,,1
+602,reorg,b,,
+700,deposit,c,3000,
+1300,confirmation,c,,1
+1300,confirmation,c,,2
+1900,confirmation,c,,3
+800,deposit,d,9000,
+1400,confirmation,d,,1
+2000,confirmation,d,,3
+";
+
+    #[test]
+    fn test_replays_trace() {
+        let trace = parse_trace(TRACE, true).unwrap();
+        let report = simulate(&config(), trace).unwrap();
+
+        // a and c fill and settle, b is reorged before any fill and d is too large to fill
+        assert_eq!(report.deposits, 4);
+        assert_eq!(report.posted, 4);
+        assert_eq!(report.filled, 2);
+        assert_eq!(report.reorged, 1);
+        assert_eq!(report.expired, 1);
+        assert_eq!(report.fill_rate, "0.5000");
+        assert_eq!(report.protocol_fees, Uint128::new(40));
+        assert_eq!(report.destination_received, Uint128::new(3_960));
+
+        // The fast maker wins a, while c reaches two confirmations at once and goes to
+        // the careful maker, who does not wait
+        let fast = &report.makers[0];
+        assert_eq!(fast.fills, 1);
+        assert_eq!(fast.filled_volume, Uint128::new(1_000));
+        assert_eq!(fast.pnl, Int128::zero());
+        assert_eq!(fast.seconds_to_fill.max, 5);
+        let careful = &report.makers[1];
+        assert_eq!(careful.fills, 1);
+        assert_eq!(careful.repaid_volume, Uint128::new(3_000));
+        assert_eq!(careful.seconds_to_fill.max, 0);
+    }
+
+    #[test]
+    fn test_reorg_after_fill_is_a_loss() {
+        let trace = vec![
+            TraceEvent {
+                time: 0,
+                event: TraceEventKind::Deposit,
+                deposit: "a".to_string(),
+                amount: Some(Uint128::new(1_000)),
+                confirmations: None,
+            },
+            TraceEvent {
+                time: 10,
+                event: TraceEventKind::Confirmation,
+                deposit: "a".to_string(),
+                amount: None,
+                confirmations: Some(1),
+            },
+            TraceEvent {
+                time: 60,
+                event: TraceEventKind::Reorg,
+                deposit: "a".to_string(),
+                amount: None,
+                confirmations: None,
+            },
+        ];
+        let report = simulate(&config(), trace).unwrap();
+        let fast = &report.makers[0];
+        assert_eq!(fast.lost_volume, Uint128::new(1_000));
+        assert_eq!(fast.outstanding_volume, Uint128::zero());
+        assert_eq!(fast.pnl, Int128::new(-1_000));
+    }
+
+    #[test]
+    fn test_distribution_percentiles() {
+        let distribution = Distribution::new((1..=100).collect());
+        assert_eq!(distribution.p50, 50);
+        assert_eq!(distribution.p90, 90);
+        assert_eq!(distribution.p99, 99);
+        assert_eq!(distribution.max, 100);
+        assert_eq!(Distribution::new(vec![]), Distribution::default());
+    }
+
+    #[test]
+    fn test_parse_trace_rejects_malformed_lines() {
+        let err = parse_trace(
+            "time,event,deposit,amount,confirmations\n0,deposit,a,,\n",
+            true,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SimError::InvalidTrace { line: 2, .. }));
+        let events = parse_trace(r#"{"time":5,"event":"reorg","deposit":"a"}"#, false).unwrap();
+        assert_eq!(events[0].event, TraceEventKind::Reorg);
+    }
+}
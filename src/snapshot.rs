@@ -0,0 +1,1308 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, Coin, Deps, DepsMut, Empty, Env, HexBinary, Order, Response, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::Bound;
+
+use crate::execute::Tx;
+use crate::invariants::assert_invariants;
+use crate::msg::WithdrawalClaim;
+use crate::state::{
+    tx_records, withdrawals, AttestationConfig, AttestationConflict, AttestedTx, Config,
+    DenomMetadata, DenomStats, FeeWithdrawal, MakerStats, PendingTimelock, PosterScope, SignerSet,
+    SpvConfig, State, StoredHeader, TxRecord, Withdrawal, ATTESTATION_CONFIG,
+    ATTESTATION_CONFLICTS, ATTESTERS, BEST_CHAIN, BLOCK_HEADERS, CONFIG, DENOM_REGISTRY,
+    DENOM_STATS, FEE_WITHDRAWALS, IMPORT_OPEN, MAKER_STATS, NEXT_FEE_WITHDRAWAL_ID,
+    NEXT_WITHDRAWAL_ID, PENDING_TIMELOCK, POSTERS, PROCESSED_ORIGINS, PROCESSED_PAYOUTS,
+    SETTLEMENT_ATTESTATIONS, SIGNER_SET, SIGNER_SET_VERSION, SPV_CONFIG, SPV_TIP, STATE, TREASURY,
+    TX_ATTESTATIONS, WITHDRAWAL_ATTESTATIONS, WITHDRAWAL_CLAIMS,
+};
+use crate::treasury::credit_treasury;
+use crate::ContractError;
+
+// Version of the export format, bumped whenever the structure of a record changes
+pub const EXPORT_VERSION: u32 = 2;
+
+const DEFAULT_EXPORT_LIMIT: u32 = 50;
+const MAX_EXPORT_LIMIT: u32 = 200;
+
+// StateExport is a page of the canonical snapshot of the contract. Every page repeats the
+// config, counters and other singletons, while the records are split across pages in a
+// fixed order: by section, then by key within each section. Fill replies are only kept
+// within the tx that submits the fill, so there are none to export.
+#[cw_serde]
+pub struct StateExport {
+    pub version: u32,
+    // Height the page was exported at. Later pages should be queried at the same height
+    // for the snapshot to be consistent.
+    pub height: u64,
+    pub config: Config,
+    pub pending_timelock: Option<PendingTimelock>,
+    pub module_account: String,
+    pub next_tx_id: u64,
+    pub next_withdrawal_id: u64,
+    pub next_fee_withdrawal_id: u64,
+    pub signer_set: Option<SignerSet>,
+    pub signer_set_version: Option<u64>,
+    pub attestation_config: Option<AttestationConfig>,
+    pub spv_config: Option<SpvConfig>,
+    pub spv_tip: Option<HexBinary>,
+    pub records: Vec<ExportRecord>,
+    // Cursor to pass as `start_after` for the next page, None once the export is complete
+    pub next: Option<ExportCursor>,
+}
+
+// WithdrawalAttestation is the maker an attester confirmed delivered a withdrawal
+#[cw_serde]
+pub struct WithdrawalAttestation {
+    pub attester: Addr,
+    pub maker: Addr,
+}
+
+// ExportRecord is a single entry of the snapshot. Maker exposures are derived from the
+// fulfilled txs, and are only exported for reconciliation.
+#[cw_serde]
+pub enum ExportRecord {
+    Tx(Tx),
+    MakerExposure {
+        maker: Addr,
+        denom: String,
+        // Number of fulfilled txs awaiting settlement and their total amount
+        fills: u64,
+        outstanding: Uint128,
+    },
+    TxRecord(TxRecord),
+    Withdrawal {
+        withdrawal: Withdrawal,
+        claims: Vec<WithdrawalClaim>,
+        attestations: Vec<WithdrawalAttestation>,
+    },
+    Denom {
+        denom: String,
+        metadata: DenomMetadata,
+    },
+    DenomStats {
+        denom: String,
+        stats: DenomStats,
+    },
+    MakerStats {
+        maker: Addr,
+        denom: String,
+        stats: MakerStats,
+    },
+    Poster {
+        address: Addr,
+        scope: PosterScope,
+    },
+    Attester {
+        address: Addr,
+    },
+    TxAttestation {
+        origin_key: String,
+        attester: Addr,
+        tx: AttestedTx,
+    },
+    SettlementAttestation {
+        tx_id: u64,
+        attester: Addr,
+    },
+    AttestationConflict(AttestationConflict),
+    Treasury {
+        denom: String,
+        amount: Uint128,
+    },
+    FeeWithdrawal(FeeWithdrawal),
+    ProcessedOrigin {
+        key: String,
+        tx_id: u64,
+    },
+    ProcessedPayout {
+        key: String,
+        withdrawal_id: u64,
+    },
+    BlockHeader {
+        // Block hash in internal byte order
+        hash: HexBinary,
+        header: StoredHeader,
+    },
+    BestChain {
+        height: u64,
+        hash: HexBinary,
+    },
+}
+
+// ExportCursor is the key of the last record of a page
+#[cw_serde]
+pub enum ExportCursor {
+    Tx { id: u64 },
+    MakerExposure { maker: Addr, denom: String },
+    TxRecord { id: u64 },
+    Withdrawal { id: u64 },
+    Denom { denom: String },
+    DenomStats { denom: String },
+    MakerStats { maker: Addr, denom: String },
+    Poster { address: Addr },
+    Attester { address: Addr },
+    TxAttestation { origin_key: String, attester: Addr },
+    SettlementAttestation { tx_id: u64, attester: Addr },
+    AttestationConflict { origin_key: String },
+    Treasury { denom: String },
+    FeeWithdrawal { id: u64 },
+    ProcessedOrigin { key: String },
+    ProcessedPayout { key: String },
+    BlockHeader { hash: HexBinary },
+    BestChain { height: u64 },
+}
+
+// Sections of the export, in the order they are exported in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum ExportSection {
+    Tx,
+    MakerExposure,
+    TxRecord,
+    Withdrawal,
+    Denom,
+    DenomStats,
+    MakerStats,
+    Poster,
+    Attester,
+    TxAttestation,
+    SettlementAttestation,
+    AttestationConflict,
+    Treasury,
+    FeeWithdrawal,
+    ProcessedOrigin,
+    ProcessedPayout,
+    BlockHeader,
+    BestChain,
+}
+
+const SECTIONS: [ExportSection; 18] = [
+    ExportSection::Tx,
+    ExportSection::MakerExposure,
+    ExportSection::TxRecord,
+    ExportSection::Withdrawal,
+    ExportSection::Denom,
+    ExportSection::DenomStats,
+    ExportSection::MakerStats,
+    ExportSection::Poster,
+    ExportSection::Attester,
+    ExportSection::TxAttestation,
+    ExportSection::SettlementAttestation,
+    ExportSection::AttestationConflict,
+    ExportSection::Treasury,
+    ExportSection::FeeWithdrawal,
+    ExportSection::ProcessedOrigin,
+    ExportSection::ProcessedPayout,
+    ExportSection::BlockHeader,
+    ExportSection::BestChain,
+];
+
+impl ExportCursor {
+    fn section(&self) -> ExportSection {
+        match self {
+            ExportCursor::Tx { .. } => ExportSection::Tx,
+            ExportCursor::MakerExposure { .. } => ExportSection::MakerExposure,
+            ExportCursor::TxRecord { .. } => ExportSection::TxRecord,
+            ExportCursor::Withdrawal { .. } => ExportSection::Withdrawal,
+            ExportCursor::Denom { .. } => ExportSection::Denom,
+            ExportCursor::DenomStats { .. } => ExportSection::DenomStats,
+            ExportCursor::MakerStats { .. } => ExportSection::MakerStats,
+            ExportCursor::Poster { .. } => ExportSection::Poster,
+            ExportCursor::Attester { .. } => ExportSection::Attester,
+            ExportCursor::TxAttestation { .. } => ExportSection::TxAttestation,
+            ExportCursor::SettlementAttestation { .. } => ExportSection::SettlementAttestation,
+            ExportCursor::AttestationConflict { .. } => ExportSection::AttestationConflict,
+            ExportCursor::Treasury { .. } => ExportSection::Treasury,
+            ExportCursor::FeeWithdrawal { .. } => ExportSection::FeeWithdrawal,
+            ExportCursor::ProcessedOrigin { .. } => ExportSection::ProcessedOrigin,
+            ExportCursor::ProcessedPayout { .. } => ExportSection::ProcessedPayout,
+            ExportCursor::BlockHeader { .. } => ExportSection::BlockHeader,
+            ExportCursor::BestChain { .. } => ExportSection::BestChain,
+        }
+    }
+}
+
+impl ExportRecord {
+    fn cursor(&self) -> ExportCursor {
+        match self {
+            ExportRecord::Tx(tx) => ExportCursor::Tx { id: tx.id },
+            ExportRecord::MakerExposure { maker, denom, .. } => ExportCursor::MakerExposure {
+                maker: maker.clone(),
+                denom: denom.clone(),
+            },
+            ExportRecord::TxRecord(record) => ExportCursor::TxRecord { id: record.tx.id },
+            ExportRecord::Withdrawal { withdrawal, .. } => {
+                ExportCursor::Withdrawal { id: withdrawal.id }
+            }
+            ExportRecord::Denom { denom, .. } => ExportCursor::Denom {
+                denom: denom.clone(),
+            },
+            ExportRecord::DenomStats { denom, .. } => ExportCursor::DenomStats {
+                denom: denom.clone(),
+            },
+            ExportRecord::MakerStats { maker, denom, .. } => ExportCursor::MakerStats {
+                maker: maker.clone(),
+                denom: denom.clone(),
+            },
+            ExportRecord::Poster { address, .. } => ExportCursor::Poster {
+                address: address.clone(),
+            },
+            ExportRecord::Attester { address } => ExportCursor::Attester {
+                address: address.clone(),
+            },
+            ExportRecord::TxAttestation {
+                origin_key,
+                attester,
+                ..
+            } => ExportCursor::TxAttestation {
+                origin_key: origin_key.clone(),
+                attester: attester.clone(),
+            },
+            ExportRecord::SettlementAttestation { tx_id, attester } => {
+                ExportCursor::SettlementAttestation {
+                    tx_id: *tx_id,
+                    attester: attester.clone(),
+                }
+            }
+            ExportRecord::AttestationConflict(conflict) => ExportCursor::AttestationConflict {
+                origin_key: conflict.origin_key.clone(),
+            },
+            ExportRecord::Treasury { denom, .. } => ExportCursor::Treasury {
+                denom: denom.clone(),
+            },
+            ExportRecord::FeeWithdrawal(withdrawal) => {
+                ExportCursor::FeeWithdrawal { id: withdrawal.id }
+            }
+            ExportRecord::ProcessedOrigin { key, .. } => {
+                ExportCursor::ProcessedOrigin { key: key.clone() }
+            }
+            ExportRecord::ProcessedPayout { key, .. } => {
+                ExportCursor::ProcessedPayout { key: key.clone() }
+            }
+            ExportRecord::BlockHeader { hash, .. } => {
+                ExportCursor::BlockHeader { hash: hash.clone() }
+            }
+            ExportRecord::BestChain { height, .. } => ExportCursor::BestChain { height: *height },
+        }
+    }
+}
+
+// export_state returns a page of the snapshot, starting after the given cursor.
+pub fn export_state(
+    deps: Deps,
+    env: &Env,
+    start_after: Option<ExportCursor>,
+    limit: Option<u32>,
+) -> StdResult<StateExport> {
+    let limit = limit
+        .unwrap_or(DEFAULT_EXPORT_LIMIT)
+        .clamp(1, MAX_EXPORT_LIMIT) as usize;
+    let state: State = STATE.load(deps.storage)?;
+    let first = start_after
+        .as_ref()
+        .map_or(ExportSection::Tx, ExportCursor::section);
+
+    // Collect one record past the limit to know whether there is another page
+    let mut records = vec![];
+    for section in SECTIONS.into_iter().filter(|section| *section >= first) {
+        let after = start_after
+            .as_ref()
+            .filter(|cursor| cursor.section() == section);
+        let take = limit + 1 - records.len();
+        records.extend(export_section(deps, &state, section, after, take)?);
+        if records.len() > limit {
+            break;
+        }
+    }
+    let next = if records.len() > limit {
+        records.truncate(limit);
+        records.last().map(ExportRecord::cursor)
+    } else {
+        None
+    };
+
+    Ok(StateExport {
+        version: EXPORT_VERSION,
+        height: env.block.height,
+        config: CONFIG.load(deps.storage)?,
+        pending_timelock: PENDING_TIMELOCK.may_load(deps.storage)?,
+        module_account: state.module_account,
+        next_tx_id: state.next_id,
+        next_withdrawal_id: NEXT_WITHDRAWAL_ID
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        next_fee_withdrawal_id: NEXT_FEE_WITHDRAWAL_ID
+            .may_load(deps.storage)?
+            .unwrap_or_default(),
+        signer_set: SIGNER_SET.may_load(deps.storage)?,
+        signer_set_version: SIGNER_SET_VERSION.may_load(deps.storage)?,
+        attestation_config: ATTESTATION_CONFIG.may_load(deps.storage)?,
+        spv_config: SPV_CONFIG.may_load(deps.storage)?,
+        spv_tip: SPV_TIP.may_load(deps.storage)?,
+        records,
+        next,
+    })
+}
+
+// export_section returns up to `take` records of a section, after the cursor if it points
+// into the section.
+fn export_section(
+    deps: Deps,
+    state: &State,
+    section: ExportSection,
+    after: Option<&ExportCursor>,
+    take: usize,
+) -> StdResult<Vec<ExportRecord>> {
+    match section {
+        ExportSection::Tx => {
+            let after = match after {
+                Some(ExportCursor::Tx { id }) => Some(*id),
+                _ => None,
+            };
+            let mut txs: Vec<&Tx> = state
+                .pending_txs
+                .iter()
+                .chain(state.fulfilled_txs.iter())
+                .filter(|tx| after.is_none_or(|after| tx.id > after))
+                .collect();
+            txs.sort_by_key(|tx| tx.id);
+            Ok(txs
+                .into_iter()
+                .take(take)
+                .map(|tx| ExportRecord::Tx(tx.clone()))
+                .collect())
+        }
+        ExportSection::MakerExposure => {
+            let mut exposures: BTreeMap<(Addr, String), (u64, Uint128)> = BTreeMap::new();
+            for tx in state.fulfilled_txs.iter() {
+                if let Some(fill) = &tx.fill {
+                    let exposure = exposures
+                        .entry((fill.fulfiller_addr.clone(), tx.coin.denom.clone()))
+                        .or_default();
+                    exposure.0 += 1;
+                    exposure.1 += tx.coin.amount;
+                }
+            }
+            let after = match after {
+                Some(ExportCursor::MakerExposure { maker, denom }) => {
+                    Some((maker.clone(), denom.clone()))
+                }
+                _ => None,
+            };
+            Ok(exposures
+                .into_iter()
+                .filter(|(key, _)| after.as_ref().is_none_or(|after| key > after))
+                .take(take)
+                .map(
+                    |((maker, denom), (fills, outstanding))| ExportRecord::MakerExposure {
+                        maker,
+                        denom,
+                        fills,
+                        outstanding,
+                    },
+                )
+                .collect())
+        }
+        ExportSection::TxRecord => {
+            let min = match after {
+                Some(ExportCursor::TxRecord { id }) => Some(Bound::exclusive(*id)),
+                _ => None,
+            };
+            tx_records()
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(_, record)| ExportRecord::TxRecord(record)))
+                .collect()
+        }
+        ExportSection::Withdrawal => {
+            let min = match after {
+                Some(ExportCursor::Withdrawal { id }) => Some(Bound::exclusive(*id)),
+                _ => None,
+            };
+            withdrawals()
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    let (id, withdrawal) = item?;
+                    let claims = WITHDRAWAL_CLAIMS
+                        .prefix(id)
+                        .range(deps.storage, None, None, Order::Ascending)
+                        .map(|item| item.map(|(maker, payout)| WithdrawalClaim { maker, payout }))
+                        .collect::<StdResult<Vec<_>>>()?;
+                    let attestations = WITHDRAWAL_ATTESTATIONS
+                        .prefix(id)
+                        .range(deps.storage, None, None, Order::Ascending)
+                        .map(|item| {
+                            item.map(|(attester, maker)| WithdrawalAttestation { attester, maker })
+                        })
+                        .collect::<StdResult<Vec<_>>>()?;
+                    Ok(ExportRecord::Withdrawal {
+                        withdrawal,
+                        claims,
+                        attestations,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::Denom => {
+            let min = match after {
+                Some(ExportCursor::Denom { denom }) => Some(Bound::exclusive(denom.as_str())),
+                _ => None,
+            };
+            DENOM_REGISTRY
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(denom, metadata)| ExportRecord::Denom { denom, metadata }))
+                .collect()
+        }
+        ExportSection::DenomStats => {
+            let min = match after {
+                Some(ExportCursor::DenomStats { denom }) => Some(Bound::exclusive(denom.as_str())),
+                _ => None,
+            };
+            DENOM_STATS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(denom, stats)| ExportRecord::DenomStats { denom, stats }))
+                .collect()
+        }
+        ExportSection::MakerStats => {
+            let min = match after {
+                Some(ExportCursor::MakerStats { maker, denom }) => {
+                    Some(Bound::exclusive((maker, denom.as_str())))
+                }
+                _ => None,
+            };
+            MAKER_STATS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    item.map(|((maker, denom), stats)| ExportRecord::MakerStats {
+                        maker,
+                        denom,
+                        stats,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::Poster => {
+            let min = match after {
+                Some(ExportCursor::Poster { address }) => Some(Bound::exclusive(address)),
+                _ => None,
+            };
+            POSTERS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(address, scope)| ExportRecord::Poster { address, scope }))
+                .collect()
+        }
+        ExportSection::Attester => {
+            let min = match after {
+                Some(ExportCursor::Attester { address }) => Some(Bound::exclusive(address)),
+                _ => None,
+            };
+            ATTESTERS
+                .keys(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|address| ExportRecord::Attester { address }))
+                .collect()
+        }
+        ExportSection::TxAttestation => {
+            let min = match after {
+                Some(ExportCursor::TxAttestation {
+                    origin_key,
+                    attester,
+                }) => Some(Bound::exclusive((origin_key.as_str(), attester))),
+                _ => None,
+            };
+            TX_ATTESTATIONS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    item.map(|((origin_key, attester), tx)| ExportRecord::TxAttestation {
+                        origin_key,
+                        attester,
+                        tx,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::SettlementAttestation => {
+            let min = match after {
+                Some(ExportCursor::SettlementAttestation { tx_id, attester }) => {
+                    Some(Bound::exclusive((*tx_id, attester)))
+                }
+                _ => None,
+            };
+            SETTLEMENT_ATTESTATIONS
+                .keys(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    item.map(|(tx_id, attester)| ExportRecord::SettlementAttestation {
+                        tx_id,
+                        attester,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::AttestationConflict => {
+            let min = match after {
+                Some(ExportCursor::AttestationConflict { origin_key }) => {
+                    Some(Bound::exclusive(origin_key.as_str()))
+                }
+                _ => None,
+            };
+            ATTESTATION_CONFLICTS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(_, conflict)| ExportRecord::AttestationConflict(conflict)))
+                .collect()
+        }
+        ExportSection::Treasury => {
+            let min = match after {
+                Some(ExportCursor::Treasury { denom }) => Some(Bound::exclusive(denom.as_str())),
+                _ => None,
+            };
+            TREASURY
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(denom, amount)| ExportRecord::Treasury { denom, amount }))
+                .collect()
+        }
+        ExportSection::FeeWithdrawal => {
+            let min = match after {
+                Some(ExportCursor::FeeWithdrawal { id }) => Some(Bound::exclusive(*id)),
+                _ => None,
+            };
+            FEE_WITHDRAWALS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(_, withdrawal)| ExportRecord::FeeWithdrawal(withdrawal)))
+                .collect()
+        }
+        ExportSection::ProcessedOrigin => {
+            let min = match after {
+                Some(ExportCursor::ProcessedOrigin { key }) => Some(Bound::exclusive(key.as_str())),
+                _ => None,
+            };
+            PROCESSED_ORIGINS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(key, tx_id)| ExportRecord::ProcessedOrigin { key, tx_id }))
+                .collect()
+        }
+        ExportSection::ProcessedPayout => {
+            let min = match after {
+                Some(ExportCursor::ProcessedPayout { key }) => Some(Bound::exclusive(key.as_str())),
+                _ => None,
+            };
+            PROCESSED_PAYOUTS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    item.map(|(key, withdrawal_id)| ExportRecord::ProcessedPayout {
+                        key,
+                        withdrawal_id,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::BlockHeader => {
+            let min = match after {
+                Some(ExportCursor::BlockHeader { hash }) => Some(Bound::exclusive(hash.as_slice())),
+                _ => None,
+            };
+            BLOCK_HEADERS
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| {
+                    item.map(|(hash, header)| ExportRecord::BlockHeader {
+                        hash: HexBinary::from(hash),
+                        header,
+                    })
+                })
+                .collect()
+        }
+        ExportSection::BestChain => {
+            let min = match after {
+                Some(ExportCursor::BestChain { height }) => Some(Bound::exclusive(*height)),
+                _ => None,
+            };
+            BEST_CHAIN
+                .range(deps.storage, min, None, Order::Ascending)
+                .take(take)
+                .map(|item| item.map(|(height, hash)| ExportRecord::BestChain { height, hash }))
+                .collect()
+        }
+    }
+}
+
+// import_state seeds a freshly instantiated contract with a page exported from another
+// deployment. It is only reachable through migrate, so only the contract admin can call it.
+// Pages may be imported across several migrations, but a tx, withdrawal, treasury balance or
+// other keyed record already present is never overwritten, and once the last page is
+// imported the contract accepts no further imports. The funds backing the imported fees and
+// withdrawals must be sent to the contract beforehand, as the import fails if the
+// contract's balances no longer cover its obligations.
+pub fn import_state(
+    deps: DepsMut,
+    env: Env,
+    export: StateExport,
+) -> Result<Response, ContractError> {
+    if export.version != EXPORT_VERSION {
+        return Err(ContractError::UnsupportedExportVersion {
+            version: export.version,
+        });
+    }
+    if !IMPORT_OPEN.may_load(deps.storage)?.unwrap_or_default() {
+        return Err(ContractError::ImportClosed {});
+    }
+    if export.next.is_none() {
+        IMPORT_OPEN.remove(deps.storage);
+    }
+
+    CONFIG.save(deps.storage, &export.config)?;
+    if let Some(pending_timelock) = &export.pending_timelock {
+        PENDING_TIMELOCK.save(deps.storage, pending_timelock)?;
+    }
+    let mut state: State = STATE.load(deps.storage)?;
+    state.module_account = export.module_account;
+    state.next_id = state.next_id.max(export.next_tx_id);
+    let next_withdrawal_id = NEXT_WITHDRAWAL_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_WITHDRAWAL_ID.save(
+        deps.storage,
+        &next_withdrawal_id.max(export.next_withdrawal_id),
+    )?;
+    let next_fee_withdrawal_id = NEXT_FEE_WITHDRAWAL_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_FEE_WITHDRAWAL_ID.save(
+        deps.storage,
+        &next_fee_withdrawal_id.max(export.next_fee_withdrawal_id),
+    )?;
+    if let Some(signer_set) = &export.signer_set {
+        SIGNER_SET.save(deps.storage, signer_set)?;
+    }
+    // Versions must never go back, or signatures for a previous signer set would be valid again
+    if let Some(version) = export.signer_set_version {
+        let current = SIGNER_SET_VERSION.may_load(deps.storage)?;
+        SIGNER_SET_VERSION.save(deps.storage, &current.map_or(version, |c| c.max(version)))?;
+    }
+    if let Some(attestation_config) = &export.attestation_config {
+        ATTESTATION_CONFIG.save(deps.storage, attestation_config)?;
+    }
+    if let Some(spv_config) = &export.spv_config {
+        SPV_CONFIG.save(deps.storage, spv_config)?;
+    }
+    if let Some(spv_tip) = &export.spv_tip {
+        SPV_TIP.save(deps.storage, spv_tip)?;
+    }
+
+    let records = export.records.len();
+    for record in export.records {
+        import_record(deps.storage, &mut state, record)?;
+    }
+    STATE.save(deps.storage, &state)?;
+    assert_invariants(deps.as_ref(), &env)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "import_state")
+        .add_attribute("exported_height", export.height.to_string())
+        .add_attribute("records", records.to_string()))
+}
+
+fn import_conflict(record: String) -> ContractError {
+    ContractError::ImportConflict {
+        reason: format!("{record} already exists"),
+    }
+}
+
+// import_record writes a single record of the snapshot to the store
+fn import_record(
+    storage: &mut dyn Storage,
+    state: &mut State,
+    record: ExportRecord,
+) -> Result<(), ContractError> {
+    match record {
+        ExportRecord::Tx(tx) => {
+            if state
+                .pending_txs
+                .iter()
+                .chain(state.fulfilled_txs.iter())
+                .any(|existing| existing.id == tx.id)
+            {
+                return Err(import_conflict(format!("tx {}", tx.id)));
+            }
+            if tx.fill.is_some() {
+                state.fulfilled_txs.push(tx);
+            } else {
+                state.pending_txs.push(tx);
+            }
+        }
+        // Exposures are derived from the fulfilled txs
+        ExportRecord::MakerExposure { .. } => {}
+        ExportRecord::TxRecord(record) => {
+            if tx_records().has(storage, record.tx.id) {
+                return Err(import_conflict(format!("history of tx {}", record.tx.id)));
+            }
+            tx_records().save(storage, record.tx.id, &record)?;
+        }
+        ExportRecord::Withdrawal {
+            withdrawal,
+            claims,
+            attestations,
+        } => {
+            if withdrawals().has(storage, withdrawal.id) {
+                return Err(import_conflict(format!("withdrawal {}", withdrawal.id)));
+            }
+            for claim in claims {
+                WITHDRAWAL_CLAIMS.save(storage, (withdrawal.id, &claim.maker), &claim.payout)?;
+            }
+            for attestation in attestations {
+                WITHDRAWAL_ATTESTATIONS.save(
+                    storage,
+                    (withdrawal.id, &attestation.attester),
+                    &attestation.maker,
+                )?;
+            }
+            withdrawals().save(storage, withdrawal.id, &withdrawal)?;
+        }
+        ExportRecord::Denom { denom, metadata } => {
+            DENOM_REGISTRY.save(storage, &denom, &metadata)?;
+        }
+        ExportRecord::DenomStats { denom, stats } => {
+            if DENOM_STATS.has(storage, &denom) {
+                return Err(import_conflict(format!("stats of {denom}")));
+            }
+            DENOM_STATS.save(storage, &denom, &stats)?;
+        }
+        ExportRecord::MakerStats {
+            maker,
+            denom,
+            stats,
+        } => {
+            if MAKER_STATS.has(storage, (&maker, &denom)) {
+                return Err(import_conflict(format!("stats of {maker} in {denom}")));
+            }
+            MAKER_STATS.save(storage, (&maker, &denom), &stats)?;
+        }
+        ExportRecord::Poster { address, scope } => {
+            POSTERS.save(storage, &address, &scope)?;
+        }
+        ExportRecord::Attester { address } => {
+            ATTESTERS.save(storage, &address, &Empty {})?;
+        }
+        ExportRecord::TxAttestation {
+            origin_key,
+            attester,
+            tx,
+        } => {
+            TX_ATTESTATIONS.save(storage, (&origin_key, &attester), &tx)?;
+        }
+        ExportRecord::SettlementAttestation { tx_id, attester } => {
+            SETTLEMENT_ATTESTATIONS.save(storage, (tx_id, &attester), &Empty {})?;
+        }
+        ExportRecord::AttestationConflict(conflict) => {
+            ATTESTATION_CONFLICTS.save(storage, &conflict.origin_key, &conflict)?;
+        }
+        ExportRecord::Treasury { denom, amount } => {
+            if TREASURY.has(storage, &denom) {
+                return Err(import_conflict(format!("treasury of {denom}")));
+            }
+            credit_treasury(storage, &Coin::new(amount.u128(), denom))?;
+        }
+        ExportRecord::FeeWithdrawal(withdrawal) => {
+            if FEE_WITHDRAWALS.has(storage, withdrawal.id) {
+                return Err(import_conflict(format!("fee withdrawal {}", withdrawal.id)));
+            }
+            FEE_WITHDRAWALS.save(storage, withdrawal.id, &withdrawal)?;
+        }
+        ExportRecord::ProcessedOrigin { key, tx_id } => {
+            PROCESSED_ORIGINS.save(storage, &key, &tx_id)?;
+        }
+        ExportRecord::ProcessedPayout { key, withdrawal_id } => {
+            PROCESSED_PAYOUTS.save(storage, &key, &withdrawal_id)?;
+        }
+        ExportRecord::BlockHeader { hash, header } => {
+            BLOCK_HEADERS.save(storage, hash.as_slice(), &header)?;
+        }
+        ExportRecord::BestChain { height, hash } => {
+            BEST_CHAIN.save(storage, height, &hash)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{execute_msg, instantiate, query, reply};
+    use crate::execute::{add_pending_tx, move_pending_tx_to_fulfilled_tx, TxOrigin};
+    use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+    use crate::spv::SpvAnchor;
+    use crate::state::FulfillState;
+    use crate::state::{BitcoinNetwork, DestinationFormat, KeyType, SettlementMode, Signer};
+    use crate::testing::{mock_denom, mock_state};
+    use crate::treasury::credit_treasury;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+    };
+    use cosmwasm_std::{coin, coins, Binary, Reply, SubMsgResponse, SubMsgResult};
+
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+
+    // export_all pages through the whole snapshot
+    fn export_all(deps: Deps, env: &Env, limit: u32) -> Vec<StateExport> {
+        let mut pages = vec![];
+        let mut start_after = None;
+        loop {
+            let page = export_state(deps, env, start_after, Some(limit)).unwrap();
+            start_after = page.next.clone();
+            pages.push(page);
+            if start_after.is_none() {
+                return pages;
+            }
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_state() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        mock_state(deps.as_mut().storage);
+        mock_denom(deps.as_mut().storage, "token");
+        mock_denom(deps.as_mut().storage, "other");
+        credit_treasury(deps.as_mut().storage, &coin(5, "token")).unwrap();
+
+        for (denom, origin) in [("token", "deadbeef"), ("other", "cafebabe"), ("token", "")] {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("module_account", &[]),
+                "destination_addr".to_string(),
+                coin(100, denom),
+                (!origin.is_empty()).then(|| TxOrigin {
+                    chain: "bitcoin".to_string(),
+                    tx_hash: origin.to_string(),
+                    vout: 0,
+                }),
+                None,
+                None,
+            )
+            .unwrap();
+        }
+        move_pending_tx_to_fulfilled_tx(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 0,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+            FulfillState {
+                fulfiller_addr: Addr::unchecked("market_maker"),
+                protocol_fee: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+        // Paging through the export yields the same records regardless of the page size
+        let whole = export_state(deps.as_ref(), &env, None, Some(MAX_EXPORT_LIMIT)).unwrap();
+        assert!(whole.next.is_none());
+        let paged = export_all(deps.as_ref(), &env, 2);
+        assert_eq!(paged.len(), 8);
+        let records: Vec<ExportRecord> =
+            paged.iter().flat_map(|page| page.records.clone()).collect();
+        assert_eq!(records, whole.records);
+
+        assert_eq!(
+            records[..4],
+            [
+                ExportRecord::Tx(
+                    STATE.load(deps.as_ref().storage).unwrap().fulfilled_txs[0].clone()
+                ),
+                ExportRecord::Tx(STATE.load(deps.as_ref().storage).unwrap().pending_txs[0].clone()),
+                ExportRecord::Tx(STATE.load(deps.as_ref().storage).unwrap().pending_txs[1].clone()),
+                ExportRecord::MakerExposure {
+                    maker: Addr::unchecked("market_maker"),
+                    denom: "token".to_string(),
+                    fills: 1,
+                    outstanding: Uint128::from(100u128),
+                },
+            ]
+        );
+
+        // Only a freshly instantiated deployment can be seeded
+        let mut used = mock_dependencies_with_balance(&[coin(5, "token")]);
+        mock_state(used.as_mut().storage);
+        let err = import_state(used.as_mut(), env.clone(), whole.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ImportClosed {}));
+
+        // A deployment holding none of the fees cannot be seeded with them
+        let mut unfunded = mock_dependencies();
+        mock_state(unfunded.as_mut().storage);
+        IMPORT_OPEN.save(unfunded.as_mut().storage, &true).unwrap();
+        let err = import_state(unfunded.as_mut(), env.clone(), whole.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::InvariantViolation { .. }));
+
+        // Importing the same txs or treasury balance twice is rejected
+        let mut twice = mock_dependencies_with_balance(&[coin(10, "token")]);
+        mock_state(twice.as_mut().storage);
+        IMPORT_OPEN.save(twice.as_mut().storage, &true).unwrap();
+        import_state(twice.as_mut(), env.clone(), paged[0].clone()).unwrap();
+        let err = import_state(twice.as_mut(), env.clone(), paged[0].clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ImportConflict { .. }));
+        let mut treasury = paged[0].clone();
+        treasury.records = vec![ExportRecord::Treasury {
+            denom: "token".to_string(),
+            amount: Uint128::new(5),
+        }];
+        import_state(twice.as_mut(), env.clone(), treasury.clone()).unwrap();
+        let err = import_state(twice.as_mut(), env.clone(), treasury).unwrap_err();
+        assert!(matches!(err, ContractError::ImportConflict { .. }));
+
+        // A new deployment seeded page by page exports the same snapshot
+        let mut seeded = mock_dependencies_with_balance(&[coin(5, "token")]);
+        mock_state(seeded.as_mut().storage);
+        IMPORT_OPEN.save(seeded.as_mut().storage, &true).unwrap();
+        for page in paged.clone() {
+            import_state(seeded.as_mut(), env.clone(), page).unwrap();
+        }
+        assert_eq!(
+            export_state(seeded.as_ref(), &env, None, Some(MAX_EXPORT_LIMIT)).unwrap(),
+            whole
+        );
+
+        // The last page closes the import, and an unknown version is rejected
+        let err = import_state(seeded.as_mut(), env.clone(), paged[0].clone()).unwrap_err();
+        assert!(matches!(err, ContractError::ImportClosed {}));
+        let mut page = paged[0].clone();
+        page.version = EXPORT_VERSION + 1;
+        let err = import_state(seeded.as_mut(), env, page).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::UnsupportedExportVersion { .. }
+        ));
+    }
+
+    fn instantiate_contract(deps: DepsMut, env: &Env) {
+        instantiate(
+            deps,
+            env.clone(),
+            mock_info("admin", &[]),
+            InstantiateMsg {
+                module_account: "module_account".to_string(),
+                admin: None,
+                history_retention_blocks: None,
+                protocol_fee_bps: Some(100),
+                withdrawal_timelock_seconds: Some(3_600),
+                foreign_destination_formats: Some(vec![DestinationFormat::Hex {
+                    prefix: "0x".to_string(),
+                    length: 20,
+                }]),
+                outbound_timeout_seconds: None,
+                destination_routes: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn run(deps: DepsMut, env: &Env, sender: &str, funds: &[Coin], msg: ExecuteMsg) {
+        execute_msg(deps, env.clone(), mock_info(sender, funds), msg).unwrap();
+    }
+
+    fn origin(tx_hash: &str) -> TxOrigin {
+        TxOrigin {
+            chain: "bitcoin".to_string(),
+            tx_hash: tx_hash.to_string(),
+            vout: 0,
+        }
+    }
+
+    #[test]
+    fn test_imported_state_answers_queries_alike() {
+        let mut deps = mock_dependencies_with_balance(&coins(10_000, "token"));
+        let env = mock_env();
+        instantiate_contract(deps.as_mut(), &env);
+
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::SetDenom {
+                denom: "token".to_string(),
+                metadata: DenomMetadata {
+                    symbol: "BTC".to_string(),
+                    decimals: 8,
+                    origin_chain: "bitcoin".to_string(),
+                    enabled: true,
+                    settlement: SettlementMode::Module,
+                },
+            },
+        );
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::SetPoster {
+                address: "poster".to_string(),
+                scope: PosterScope::default(),
+            },
+        );
+        for tx_hash in ["aa", "bb", "cc"] {
+            run(
+                deps.as_mut(),
+                &env,
+                "module_account",
+                &[],
+                ExecuteMsg::AddTx {
+                    destination_addr: "destination_addr".to_string(),
+                    output_coin: coin(1_000, "token"),
+                    origin: Some(origin(tx_hash)),
+                    owner: None,
+                    signatures: None,
+                },
+            );
+        }
+        // One tx is settled into the history, another awaits settlement
+        for tx_id in [1, 2] {
+            run(
+                deps.as_mut(),
+                &env,
+                "market_maker",
+                &coins(1_000, "token"),
+                ExecuteMsg::FulfillTx { tx_id },
+            );
+            reply(
+                deps.as_mut(),
+                env.clone(),
+                Reply {
+                    id: tx_id,
+                    result: SubMsgResult::Ok(SubMsgResponse {
+                        events: vec![],
+                        data: None,
+                    }),
+                },
+            )
+            .unwrap();
+        }
+        run(
+            deps.as_mut(),
+            &env,
+            "module_account",
+            &[],
+            ExecuteMsg::RemoveFulfilledTx { tx_id: 1 },
+        );
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::WithdrawFees {
+                to: "recipient".to_string(),
+                amount: coin(5, "token"),
+            },
+        );
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::UpdateConfig {
+                admin: None,
+                history_retention_blocks: None,
+                protocol_fee_bps: None,
+                withdrawal_timelock_seconds: Some(60),
+                foreign_destination_formats: None,
+                outbound_timeout_seconds: None,
+                destination_routes: None,
+            },
+        );
+        for threshold in [1, 0, 1] {
+            run(
+                deps.as_mut(),
+                &env,
+                "admin",
+                &[],
+                ExecuteMsg::UpdateSignerSet {
+                    signers: vec![Signer {
+                        key_type: KeyType::Secp256k1,
+                        pubkey: Binary::from(vec![2; 33]),
+                    }],
+                    threshold,
+                },
+            );
+        }
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::ConfigureSpv {
+                config: SpvConfig {
+                    origin_chain: "bitcoin".to_string(),
+                    network: BitcoinNetwork::Mainnet,
+                    confirmations: 6,
+                    deposit_script_pubkey: HexBinary::from_hex("51").unwrap(),
+                },
+                anchor: Some(SpvAnchor {
+                    header: HexBinary::from_hex(GENESIS_HEADER).unwrap(),
+                    height: 0,
+                }),
+            },
+        );
+        // Both withdrawals are claimed, the first one is then paid and the other still open
+        for (id, tx_hash) in [(0, "feed"), (1, "beef")] {
+            run(
+                deps.as_mut(),
+                &env,
+                "owner",
+                &coins(500, "token"),
+                ExecuteMsg::RequestWithdrawal {
+                    destination_chain: "ethereum".to_string(),
+                    destination_addr: "0xabcdef0123456789abcdef0123456789abcdef01".to_string(),
+                },
+            );
+            run(
+                deps.as_mut(),
+                &env,
+                "market_maker",
+                &[],
+                ExecuteMsg::ClaimWithdrawal {
+                    id,
+                    payout: TxOrigin {
+                        chain: "ethereum".to_string(),
+                        tx_hash: tx_hash.to_string(),
+                        vout: 0,
+                    },
+                },
+            );
+        }
+        run(
+            deps.as_mut(),
+            &env,
+            "admin",
+            &[],
+            ExecuteMsg::ConfigureAttestation {
+                attesters: vec!["attester_a".to_string(), "attester_b".to_string()],
+                threshold: 2,
+            },
+        );
+        run(
+            deps.as_mut(),
+            &env,
+            "attester_a",
+            &[],
+            ExecuteMsg::AttestTx {
+                destination_addr: "destination_addr".to_string(),
+                output_coin: coin(1_000, "token"),
+                origin: origin("dd"),
+                owner: None,
+            },
+        );
+        // Attesters disagreeing on the deposit leave a conflict behind
+        run(
+            deps.as_mut(),
+            &env,
+            "attester_b",
+            &[],
+            ExecuteMsg::AttestTx {
+                destination_addr: "other_addr".to_string(),
+                output_coin: coin(1_000, "token"),
+                origin: origin("dd"),
+                owner: None,
+            },
+        );
+        run(
+            deps.as_mut(),
+            &env,
+            "attester_a",
+            &[],
+            ExecuteMsg::AttestSettlement { tx_id: 2 },
+        );
+        for (attester, id) in [("attester_a", 0), ("attester_b", 0), ("attester_a", 1)] {
+            run(
+                deps.as_mut(),
+                &env,
+                attester,
+                &[],
+                ExecuteMsg::AttestWithdrawal {
+                    id,
+                    maker: "market_maker".to_string(),
+                },
+            );
+        }
+
+        // Every section of the export has records to carry over
+        let pages = export_all(deps.as_ref(), &env, 3);
+        let sections: Vec<ExportSection> = pages
+            .iter()
+            .flat_map(|page| page.records.iter().map(|record| record.cursor().section()))
+            .collect();
+        let missing: Vec<_> = SECTIONS.iter().filter(|s| !sections.contains(s)).collect();
+        assert!(missing.is_empty(), "{missing:?}");
+
+        // A fresh contract holding the same funds is seeded page by page
+        let mut seeded = mock_dependencies_with_balance(&coins(10_000, "token"));
+        instantiate_contract(seeded.as_mut(), &env);
+        for page in pages {
+            import_state(seeded.as_mut(), env.clone(), page).unwrap();
+        }
+
+        let queries = [
+            QueryMsg::GetPendingTxs {},
+            QueryMsg::GetFulfilledTxs {},
+            QueryMsg::Stats {},
+            QueryMsg::Config {},
+            QueryMsg::Treasury {},
+            QueryMsg::CheckInvariants {},
+            QueryMsg::Denom {
+                denom: "token".to_string(),
+            },
+            QueryMsg::Posters {
+                start_after: None,
+                limit: None,
+            },
+            QueryMsg::AttestationConfig {},
+            QueryMsg::SpvStatus {},
+            QueryMsg::SignerSet {},
+            QueryMsg::TxAttestations {
+                origin: origin("dd"),
+            },
+            QueryMsg::AttestationConflicts {
+                start_after: None,
+                limit: None,
+            },
+            QueryMsg::TxsByFulfiller {
+                fulfiller: "market_maker".to_string(),
+                start_after: None,
+                limit: None,
+            },
+            QueryMsg::TxsByDestination {
+                destination: "destination_addr".to_string(),
+                start_after: None,
+                limit: None,
+            },
+            QueryMsg::Withdrawal { id: 0 },
+            QueryMsg::Withdrawal { id: 1 },
+            QueryMsg::ExportState {
+                start_after: None,
+                limit: Some(MAX_EXPORT_LIMIT),
+            },
+        ];
+        for msg in queries {
+            let expected = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+            let actual = query(seeded.as_ref(), env.clone(), msg.clone()).unwrap();
+            assert_eq!(actual, expected, "{msg:?}");
+        }
+    }
+}
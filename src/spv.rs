@@ -0,0 +1,941 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    DepsMut, Env, HexBinary, MessageInfo, Response, StdResult, Storage, Uint128, Uint256,
+};
+use sha2::{Digest, Sha256};
+
+use crate::events::{tx_event, TX_SETTLED_EVENT};
+use crate::execute::{settle_fulfilled_tx, tx_not_found, TxStatus};
+use crate::state::{
+    BitcoinNetwork, SpvConfig, State, StoredHeader, BEST_CHAIN, BLOCK_HEADERS, CONFIG, SPV_CONFIG,
+    SPV_TIP, STATE,
+};
+use crate::tokenfactory::settlement_mint;
+use crate::ContractError;
+
+const HEADER_LEN: usize = 80;
+const RETARGET_INTERVAL: u64 = 2016;
+// Two weeks, the expected duration of a difficulty period
+const TARGET_TIMESPAN: u64 = 14 * 24 * 60 * 60;
+// Number of ancestors whose median time a new header's time must exceed
+const MEDIAN_TIME_SPAN: usize = 11;
+
+// SpvAnchor is the trusted header the header chain is built on
+#[cw_serde]
+pub struct SpvAnchor {
+    pub header: HexBinary,
+    pub height: u64,
+}
+
+fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(data)).into()
+}
+
+// display_hash converts a hash in internal byte order to the reversed hex encoding used
+// by block explorers and node RPCs
+pub fn display_hash(hash: &[u8]) -> String {
+    let mut reversed = hash.to_vec();
+    reversed.reverse();
+    HexBinary::from(reversed).to_hex()
+}
+
+fn pow_limit(network: &BitcoinNetwork) -> Uint256 {
+    match network {
+        BitcoinNetwork::Mainnet => Uint256::MAX >> 32,
+        BitcoinNetwork::Regtest => Uint256::MAX >> 1,
+    }
+}
+
+// BlockHeader is a parsed 80 byte Bitcoin block header
+pub struct BlockHeader {
+    pub prev_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub hash: [u8; 32],
+}
+
+impl BlockHeader {
+    pub fn parse(raw: &[u8]) -> Result<Self, ContractError> {
+        if raw.len() != HEADER_LEN {
+            return Err(ContractError::InvalidBlockHeader {
+                reason: format!("expected {HEADER_LEN} bytes, got {}", raw.len()),
+            });
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(raw[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        Ok(BlockHeader {
+            prev_hash: raw[4..36].try_into().unwrap_or_default(),
+            merkle_root: raw[36..68].try_into().unwrap_or_default(),
+            time: u32_at(68),
+            bits: u32_at(72),
+            hash: sha256d(raw),
+        })
+    }
+}
+
+// compact_to_target decodes the compact `nBits` encoding of a proof-of-work target
+pub fn compact_to_target(bits: u32) -> Result<Uint256, ContractError> {
+    let exponent = bits >> 24;
+    let mantissa = bits & 0x007f_ffff;
+    let negative = mantissa != 0 && bits & 0x0080_0000 != 0;
+    let overflow = mantissa != 0
+        && (exponent > 34
+            || (mantissa > 0xff && exponent > 33)
+            || (mantissa > 0xffff && exponent > 32));
+    if negative || overflow {
+        return Err(ContractError::InvalidBlockHeader {
+            reason: format!("invalid target bits {bits:#010x}"),
+        });
+    }
+
+    let mantissa = Uint256::from(mantissa);
+    Ok(if exponent <= 3 {
+        mantissa >> (8 * (3 - exponent))
+    } else {
+        mantissa << (8 * (exponent - 3))
+    })
+}
+
+// target_to_compact encodes a proof-of-work target in the compact `nBits` encoding
+pub fn target_to_compact(target: Uint256) -> u32 {
+    let bytes = target.to_be_bytes();
+    let mut size = 32 - bytes.iter().take_while(|byte| **byte == 0).count();
+
+    let mut mantissa = [0u8; 4];
+    for (i, byte) in mantissa[1..].iter_mut().enumerate() {
+        *byte = bytes.get(32 - size + i).copied().unwrap_or_default();
+    }
+    let mut compact = u32::from_be_bytes(mantissa);
+    // The sign bit is set, so shift the mantissa into the next byte
+    if compact & 0x0080_0000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+    compact | ((size as u32) << 24)
+}
+
+// block_work is the expected number of hashes needed to meet the target, 2^256 / (target + 1)
+fn block_work(target: Uint256) -> Uint256 {
+    (Uint256::MAX - target) / (target + Uint256::one()) + Uint256::one()
+}
+
+// retarget computes the target of a new difficulty period from the duration of the
+// previous one, bounded to a factor of four in either direction
+pub fn retarget(network: &BitcoinNetwork, bits: u32, timespan: u64) -> Result<u32, ContractError> {
+    let timespan = timespan.clamp(TARGET_TIMESPAN / 4, TARGET_TIMESPAN * 4);
+    let target = compact_to_target(bits)?.multiply_ratio(timespan, TARGET_TIMESPAN);
+    Ok(target_to_compact(target.min(pow_limit(network))))
+}
+
+fn expected_bits(
+    network: &BitcoinNetwork,
+    parent: &StoredHeader,
+    parent_header: &BlockHeader,
+) -> Result<u32, ContractError> {
+    match network {
+        BitcoinNetwork::Regtest => Ok(parent_header.bits),
+        BitcoinNetwork::Mainnet if (parent.height + 1).is_multiple_of(RETARGET_INTERVAL) => {
+            let timespan = parent_header.time.saturating_sub(parent.period_start_time);
+            retarget(network, parent_header.bits, timespan as u64)
+        }
+        BitcoinNetwork::Mainnet => Ok(parent_header.bits),
+    }
+}
+
+fn check_proof_of_work(header: &BlockHeader) -> Result<Uint256, ContractError> {
+    let target = compact_to_target(header.bits)?;
+    if Uint256::from_le_bytes(header.hash) > target {
+        return Err(ContractError::InvalidBlockHeader {
+            reason: format!(
+                "block {} does not meet its target",
+                display_hash(&header.hash)
+            ),
+        });
+    }
+    Ok(block_work(target))
+}
+
+// median_time_past returns the median time of the last 11 blocks up to and including
+// `parent`, or of as many as are known above the anchor
+fn median_time_past(
+    storage: &dyn Storage,
+    parent_header: &BlockHeader,
+) -> Result<u32, ContractError> {
+    let mut times = vec![parent_header.time];
+    let mut prev_hash = parent_header.prev_hash;
+    while times.len() < MEDIAN_TIME_SPAN {
+        let Some(stored) = BLOCK_HEADERS.may_load(storage, &prev_hash)? else {
+            break;
+        };
+        let header = BlockHeader::parse(stored.header.as_slice())?;
+        times.push(header.time);
+        prev_hash = header.prev_hash;
+    }
+    times.sort_unstable();
+    Ok(times[times.len() / 2])
+}
+
+// update_best_chain makes `hash` the tip of the best chain, rewriting the heights of the
+// blocks that changed in a reorg
+fn update_best_chain(
+    storage: &mut dyn Storage,
+    hash: &[u8],
+    stored: &StoredHeader,
+) -> Result<(), ContractError> {
+    if let Some(tip) = SPV_TIP.may_load(storage)? {
+        let tip = BLOCK_HEADERS.load(storage, tip.as_slice())?;
+        for height in stored.height + 1..=tip.height {
+            BEST_CHAIN.remove(storage, height);
+        }
+    }
+
+    let mut hash = HexBinary::from(hash);
+    let mut height = stored.height;
+    loop {
+        if BEST_CHAIN.may_load(storage, height)?.as_ref() == Some(&hash) {
+            break;
+        }
+        BEST_CHAIN.save(storage, height, &hash)?;
+        let header = BLOCK_HEADERS.load(storage, hash.as_slice())?;
+        let parent = BlockHeader::parse(header.header.as_slice())?.prev_hash;
+        if height == 0 || !BLOCK_HEADERS.has(storage, &parent) {
+            break;
+        }
+        hash = HexBinary::from(parent.as_slice());
+        height -= 1;
+    }
+    Ok(())
+}
+
+// store_headers validates and stores headers extending the known header tree. Headers
+// already known are skipped, so relayers may resubmit overlapping ranges.
+pub fn store_headers(
+    storage: &mut dyn Storage,
+    config: &SpvConfig,
+    headers: &[HexBinary],
+) -> Result<(), ContractError> {
+    for raw in headers {
+        let header = BlockHeader::parse(raw.as_slice())?;
+        if BLOCK_HEADERS.has(storage, &header.hash) {
+            continue;
+        }
+
+        let parent = BLOCK_HEADERS
+            .may_load(storage, &header.prev_hash)?
+            .ok_or_else(|| ContractError::UnknownBlock {
+                hash: display_hash(&header.prev_hash),
+            })?;
+        let parent_header = BlockHeader::parse(parent.header.as_slice())?;
+
+        let expected = expected_bits(&config.network, &parent, &parent_header)?;
+        if header.bits != expected {
+            return Err(ContractError::InvalidBlockHeader {
+                reason: format!(
+                    "block {} has target bits {:#010x}, expected {expected:#010x}",
+                    display_hash(&header.hash),
+                    header.bits
+                ),
+            });
+        }
+        let work = check_proof_of_work(&header)?;
+        let median_time = median_time_past(storage, &parent_header)?;
+        if header.time <= median_time {
+            return Err(ContractError::InvalidBlockHeader {
+                reason: format!(
+                    "block {} has time {}, not after the median time past {median_time}",
+                    display_hash(&header.hash),
+                    header.time
+                ),
+            });
+        }
+
+        let height = parent.height + 1;
+        let stored = StoredHeader {
+            header: raw.clone(),
+            height,
+            chain_work: parent.chain_work + work,
+            period_start_time: if height.is_multiple_of(RETARGET_INTERVAL) {
+                header.time
+            } else {
+                parent.period_start_time
+            },
+        };
+        BLOCK_HEADERS.save(storage, &header.hash, &stored)?;
+
+        let tip = SPV_TIP.load(storage)?;
+        let tip = BLOCK_HEADERS.load(storage, tip.as_slice())?;
+        if stored.chain_work > tip.chain_work {
+            update_best_chain(storage, &header.hash, &stored)?;
+            SPV_TIP.save(storage, &HexBinary::from(header.hash.as_slice()))?;
+        }
+    }
+    Ok(())
+}
+
+// configure_spv is called by the admin to enable SPV settlement. The header chain is
+// anchored at a trusted header once, later calls may only change the parameters.
+pub fn configure_spv(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    config: SpvConfig,
+    anchor: Option<SpvAnchor>,
+) -> Result<Response, ContractError> {
+    let contract_config = CONFIG.load(deps.storage)?;
+    if info.sender != contract_config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let anchored = SPV_TIP.may_load(deps.storage)?.is_some();
+    match anchor {
+        Some(_) if anchored => return Err(ContractError::SpvAlreadyAnchored {}),
+        None if !anchored => {
+            return Err(ContractError::InvalidBlockHeader {
+                reason: "an anchor header is required".to_string(),
+            })
+        }
+        Some(anchor) => {
+            let header = BlockHeader::parse(anchor.header.as_slice())?;
+            // Retargeting needs the first block of the period, so mainnet anchors start one
+            if config.network == BitcoinNetwork::Mainnet
+                && !anchor.height.is_multiple_of(RETARGET_INTERVAL)
+            {
+                return Err(ContractError::InvalidBlockHeader {
+                    reason: format!(
+                        "anchor height {} is not the start of a difficulty period",
+                        anchor.height
+                    ),
+                });
+            }
+            let work = check_proof_of_work(&header)?;
+            let stored = StoredHeader {
+                header: anchor.header,
+                height: anchor.height,
+                chain_work: work,
+                period_start_time: header.time,
+            };
+            BLOCK_HEADERS.save(deps.storage, &header.hash, &stored)?;
+            update_best_chain(deps.storage, &header.hash, &stored)?;
+            SPV_TIP.save(deps.storage, &HexBinary::from(header.hash.as_slice()))?;
+        }
+        None => {}
+    }
+
+    SPV_CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new()
+        .add_attribute("action", "configure_spv")
+        .add_attribute("origin_chain", config.origin_chain)
+        .add_attribute("confirmations", config.confirmations.to_string()))
+}
+
+// submit_headers can be called by anyone to extend the header chain
+pub fn submit_headers(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    headers: Vec<HexBinary>,
+) -> Result<Response, ContractError> {
+    let config = load_spv_config(deps.storage)?;
+    store_headers(deps.storage, &config, &headers)?;
+    let tip = SPV_TIP.load(deps.storage)?;
+    let height = BLOCK_HEADERS.load(deps.storage, tip.as_slice())?.height;
+    Ok(Response::new()
+        .add_attribute("action", "submit_headers")
+        .add_attribute("tip", display_hash(tip.as_slice()))
+        .add_attribute("tip_height", height.to_string()))
+}
+
+fn load_spv_config(storage: &dyn Storage) -> Result<SpvConfig, ContractError> {
+    SPV_CONFIG
+        .may_load(storage)?
+        .ok_or(ContractError::SpvNotConfigured {})
+}
+
+struct TxReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> TxReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ContractError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or(ContractError::InvalidMerkleProof {
+                reason: "transaction is truncated".to_string(),
+            })?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, ContractError> {
+        let mut read = |len: usize| -> Result<u64, ContractError> {
+            let mut bytes = [0u8; 8];
+            bytes[..len].copy_from_slice(self.take(len)?);
+            Ok(u64::from_le_bytes(bytes))
+        };
+        Ok(match read(1)? {
+            0xfd => read(2)?,
+            0xfe => read(4)?,
+            0xff => read(8)?,
+            n => n,
+        })
+    }
+
+    fn skip_script(&mut self) -> Result<(), ContractError> {
+        let len = self.varint()?;
+        self.take(len as usize)?;
+        Ok(())
+    }
+}
+
+// TxOutput is an output of a transaction on the origin chain
+struct TxOutput {
+    value: u64,
+    script_pubkey: Vec<u8>,
+}
+
+// parse_outputs parses a transaction in the legacy serialization, which is what txids
+// commit to, and returns its outputs
+fn parse_outputs(raw_tx: &[u8]) -> Result<Vec<TxOutput>, ContractError> {
+    let mut reader = TxReader {
+        data: raw_tx,
+        offset: 0,
+    };
+    reader.take(4)?;
+    let inputs = reader.varint()?;
+    if inputs == 0 {
+        return Err(ContractError::InvalidMerkleProof {
+            reason: "transaction must be serialized without witness data".to_string(),
+        });
+    }
+    for _ in 0..inputs {
+        reader.take(36)?;
+        reader.skip_script()?;
+        reader.take(4)?;
+    }
+    let count = reader.varint()?;
+    let mut outputs = vec![];
+    for _ in 0..count {
+        let value = u64::from_le_bytes(reader.take(8)?.try_into().unwrap_or_default());
+        let len = reader.varint()?;
+        let script_pubkey = reader.take(len as usize)?.to_vec();
+        outputs.push(TxOutput {
+            value,
+            script_pubkey,
+        });
+    }
+    reader.take(4)?;
+    if reader.offset != raw_tx.len() {
+        return Err(ContractError::InvalidMerkleProof {
+            reason: "transaction has trailing bytes".to_string(),
+        });
+    }
+    Ok(outputs)
+}
+
+// merkle_root computes the root of the block's transaction tree from a txid, its index
+// in the block and the sibling hashes on its path
+fn merkle_root(txid: [u8; 32], index: u32, proof: &[HexBinary]) -> Result<[u8; 32], ContractError> {
+    let mut hash = txid;
+    let mut index = index as u64;
+    for sibling in proof {
+        let sibling: [u8; 32] =
+            sibling
+                .as_slice()
+                .try_into()
+                .map_err(|_| ContractError::InvalidMerkleProof {
+                    reason: "proof hashes must be 32 bytes".to_string(),
+                })?;
+        let mut node = Vec::with_capacity(64);
+        if index & 1 == 1 {
+            node.extend_from_slice(&sibling);
+            node.extend_from_slice(&hash);
+        } else {
+            node.extend_from_slice(&hash);
+            node.extend_from_slice(&sibling);
+        }
+        hash = sha256d(&node);
+        index >>= 1;
+    }
+    if index != 0 {
+        return Err(ContractError::InvalidMerkleProof {
+            reason: "tx index exceeds the proof depth".to_string(),
+        });
+    }
+    Ok(hash)
+}
+
+// settle_with_proof settles a fulfilled tx once its origin deposit is proven to be
+// included in a block with enough confirmations on the best known chain, paying at least
+// the tx amount to the custody address. The proof is
+// self-authenticating, so anyone may relay it and it is accepted in attestation mode.
+#[allow(clippy::too_many_arguments)]
+pub fn settle_with_proof(
+    deps: DepsMut,
+    env: Env,
+    _info: MessageInfo,
+    tx_id: u64,
+    headers: Vec<HexBinary>,
+    block_hash: HexBinary,
+    raw_tx: HexBinary,
+    tx_index: u32,
+    merkle_proof: Vec<HexBinary>,
+) -> Result<Response, ContractError> {
+    let config = load_spv_config(deps.storage)?;
+    store_headers(deps.storage, &config, &headers)?;
+
+    let state: State = STATE.load(deps.storage)?;
+    let tx = state
+        .fulfilled_txs
+        .iter()
+        .find(|tx| tx.id == tx_id)
+        .ok_or_else(|| tx_not_found(deps.storage, &state, tx_id))?;
+    let origin = match &tx.origin {
+        Some(origin) if origin.chain == config.origin_chain => origin,
+        _ => {
+            return Err(ContractError::OriginMismatch {
+                reason: format!("tx {tx_id} has no origin on {}", config.origin_chain),
+            })
+        }
+    };
+
+    // The block must be on the best chain and buried deep enough
+    let block = BLOCK_HEADERS
+        .may_load(deps.storage, block_hash.as_slice())?
+        .ok_or_else(|| ContractError::UnknownBlock {
+            hash: display_hash(block_hash.as_slice()),
+        })?;
+    if BEST_CHAIN.may_load(deps.storage, block.height)?.as_ref() != Some(&block_hash) {
+        return Err(ContractError::UnknownBlock {
+            hash: display_hash(block_hash.as_slice()),
+        });
+    }
+    let tip = SPV_TIP.load(deps.storage)?;
+    let tip_height = BLOCK_HEADERS.load(deps.storage, tip.as_slice())?.height;
+    let confirmations = tip_height - block.height + 1;
+    if confirmations < config.confirmations as u64 {
+        return Err(ContractError::InsufficientConfirmations {
+            confirmations,
+            required: config.confirmations,
+        });
+    }
+
+    // A 64 byte tx could be passed off as an inner node of the merkle tree
+    if raw_tx.len() == 64 {
+        return Err(ContractError::InvalidMerkleProof {
+            reason: "transaction can not be 64 bytes".to_string(),
+        });
+    }
+    let outputs = parse_outputs(raw_tx.as_slice())?;
+    let txid = sha256d(raw_tx.as_slice());
+    let header = BlockHeader::parse(block.header.as_slice())?;
+    if merkle_root(txid, tx_index, &merkle_proof)? != header.merkle_root {
+        return Err(ContractError::InvalidMerkleProof {
+            reason: "proof does not match the block's merkle root".to_string(),
+        });
+    }
+
+    if !display_hash(&txid).eq_ignore_ascii_case(&origin.tx_hash) {
+        return Err(ContractError::OriginMismatch {
+            reason: format!(
+                "proven tx {} is not the origin deposit",
+                display_hash(&txid)
+            ),
+        });
+    }
+    let output =
+        outputs
+            .get(origin.vout as usize)
+            .ok_or_else(|| ContractError::OriginMismatch {
+                reason: format!("proven tx has no output {}", origin.vout),
+            })?;
+    if output.script_pubkey != config.deposit_script_pubkey.as_slice() {
+        return Err(ContractError::OriginMismatch {
+            reason: format!("output {} does not pay to the deposit address", origin.vout),
+        });
+    }
+    if Uint128::from(output.value) < tx.coin.amount {
+        return Err(ContractError::OriginMismatch {
+            reason: format!(
+                "output {} carries {} satoshis, less than the tx amount {}",
+                origin.vout, output.value, tx.coin.amount
+            ),
+        });
+    }
+
+    let tx = settle_fulfilled_tx(deps.storage, &env, tx_id)?;
+    let fulfiller = tx.fill.as_ref().map(|fill| &fill.fulfiller_addr);
+    Ok(Response::new()
+        .add_messages(settlement_mint(&env, &tx))
+        .add_attribute("action", "settle_with_proof")
+        .add_attribute("tx_id", tx_id.to_string())
+        .add_attribute("block_hash", display_hash(block_hash.as_slice()))
+        .add_attribute("confirmations", confirmations.to_string())
+        .add_event(tx_event(
+            TX_SETTLED_EVENT,
+            &tx,
+            TxStatus::Settled,
+            fulfiller,
+        )))
+}
+
+// spv_tip returns the hash and height of the best known block, if the chain is anchored
+pub fn spv_tip(storage: &dyn Storage) -> StdResult<Option<(HexBinary, StoredHeader)>> {
+    match SPV_TIP.may_load(storage)? {
+        Some(hash) => {
+            let header = BLOCK_HEADERS.load(storage, hash.as_slice())?;
+            Ok(Some((hash, header)))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{Fill, Tx, TxOrigin};
+    use crate::state::SettlementMode;
+    use crate::testing::mock_state;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, Addr, Uint128};
+
+    const GENESIS_HEADER: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c";
+    const BLOCK_1_HEADER: &str = "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e36299";
+    const BLOCK_1_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000";
+    const BLOCK_1_COINBASE_TXID: &str =
+        "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098";
+    // The scriptPubKey of the coinbase output
+    const BLOCK_1_SCRIPT: &str = "410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac";
+    const REGTEST_BITS: u32 = 0x207f_ffff;
+
+    fn setup(deps: DepsMut, origin_tx_hash: &str) {
+        let tx = Tx {
+            id: 1,
+            destination_addr: "destination_addr".to_string(),
+            coin: coin(100, "token"),
+            added_height: 1,
+            fill: Some(Fill {
+                fulfiller_addr: Addr::unchecked("market_maker"),
+                amount: Uint128::from(100u128),
+                height: 2,
+            }),
+            origin: Some(TxOrigin {
+                chain: "bitcoin".to_string(),
+                tx_hash: origin_tx_hash.to_string(),
+                vout: 0,
+            }),
+            owner: None,
+            amendments: 0,
+            settlement: SettlementMode::Module,
+            payee: Some(Addr::unchecked("market_maker")),
+        };
+        mock_state(deps.storage);
+        STATE
+            .update(deps.storage, |mut state| -> StdResult<_> {
+                state.fulfilled_txs.push(tx);
+                state.next_id = 2;
+                Ok(state)
+            })
+            .unwrap();
+    }
+
+    fn spv_config(network: BitcoinNetwork, confirmations: u32, deposit_script: &str) -> SpvConfig {
+        SpvConfig {
+            origin_chain: "bitcoin".to_string(),
+            network,
+            confirmations,
+            deposit_script_pubkey: HexBinary::from_hex(deposit_script).unwrap(),
+        }
+    }
+
+    // mine_header builds a regtest header on top of `prev`, which only needs a few attempts
+    fn mine_header(prev: &[u8; 32], merkle_root: &[u8; 32], time: u32) -> HexBinary {
+        let target = compact_to_target(REGTEST_BITS).unwrap();
+        for nonce in 0u32.. {
+            let mut raw = vec![];
+            raw.extend_from_slice(&1u32.to_le_bytes());
+            raw.extend_from_slice(prev);
+            raw.extend_from_slice(merkle_root);
+            raw.extend_from_slice(&time.to_le_bytes());
+            raw.extend_from_slice(&REGTEST_BITS.to_le_bytes());
+            raw.extend_from_slice(&nonce.to_le_bytes());
+            if Uint256::from_le_bytes(sha256d(&raw)) <= target {
+                return HexBinary::from(raw);
+            }
+        }
+        unreachable!()
+    }
+
+    fn mine_chain(prev: &[u8; 32], len: usize, time: u32) -> Vec<HexBinary> {
+        let mut prev = *prev;
+        let mut headers = vec![];
+        for i in 0..len {
+            let header = mine_header(&prev, &[0; 32], time + i as u32);
+            prev = sha256d(header.as_slice());
+            headers.push(header);
+        }
+        headers
+    }
+
+    fn hash_of(header: &HexBinary) -> [u8; 32] {
+        sha256d(header.as_slice())
+    }
+
+    // raw_tx serializes a tx spending a single input into two outputs
+    fn raw_tx(seed: u8) -> Vec<u8> {
+        let mut raw = vec![];
+        raw.extend_from_slice(&2u32.to_le_bytes());
+        raw.push(1);
+        raw.extend_from_slice(&[seed; 32]);
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        raw.extend_from_slice(&[1, 0x51]);
+        raw.extend_from_slice(&u32::MAX.to_le_bytes());
+        raw.push(2);
+        for _ in 0..2 {
+            raw.extend_from_slice(&1_000u64.to_le_bytes());
+            raw.extend_from_slice(&[1, 0x51]);
+        }
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        raw
+    }
+
+    #[test]
+    fn test_compact_target_and_retarget() {
+        assert_eq!(
+            compact_to_target(0x1d00ffff).unwrap(),
+            Uint256::from(0xffffu32) << 208
+        );
+        assert_eq!(
+            target_to_compact(Uint256::from(0xffffu32) << 208),
+            0x1d00ffff
+        );
+        assert_eq!(target_to_compact(Uint256::from(0x80u32)), 0x02008000);
+        assert!(compact_to_target(0x04923456).is_err());
+
+        // Difficulty adjustments from the Bitcoin Core test suite
+        let mainnet = BitcoinNetwork::Mainnet;
+        let cases = [
+            (1261130161u64, 1262152739u64, 0x1d00ffff, 0x1d00d86a),
+            (1231006505, 1233061996, 0x1d00ffff, 0x1d00ffff),
+            (1279008237, 1279297671, 0x1c05a3f4, 0x1c0168fd),
+            (1263163443, 1269211443, 0x1c387f6f, 0x1d00e1fd),
+        ];
+        for (first, last, bits, expected) in cases {
+            assert_eq!(retarget(&mainnet, bits, last - first).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_settle_with_mainnet_proof() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        setup(deps.as_mut(), BLOCK_1_COINBASE_TXID);
+
+        let anchor = SpvAnchor {
+            header: HexBinary::from_hex(GENESIS_HEADER).unwrap(),
+            height: 0,
+        };
+        let err = configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("market_maker", &[]),
+            spv_config(BitcoinNetwork::Mainnet, 1, BLOCK_1_SCRIPT),
+            Some(anchor.clone()),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            spv_config(BitcoinNetwork::Mainnet, 1, BLOCK_1_SCRIPT),
+            Some(anchor.clone()),
+        )
+        .unwrap();
+        let err = configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            spv_config(BitcoinNetwork::Mainnet, 1, BLOCK_1_SCRIPT),
+            Some(anchor),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::SpvAlreadyAnchored {}));
+
+        // A header whose hash does not meet the target is rejected
+        let mut invalid = HexBinary::from_hex(BLOCK_1_HEADER).unwrap().to_vec();
+        invalid[76] ^= 1;
+        let err = submit_headers(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            vec![HexBinary::from(invalid)],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidBlockHeader { .. }));
+
+        let block_1 = HexBinary::from_hex(BLOCK_1_HEADER).unwrap();
+        let block_hash = HexBinary::from(hash_of(&block_1).as_slice());
+        assert_eq!(
+            display_hash(block_hash.as_slice()),
+            "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048"
+        );
+
+        let res = settle_with_proof(
+            deps.as_mut(),
+            env,
+            mock_info("relayer", &[]),
+            1,
+            vec![block_1],
+            block_hash.clone(),
+            HexBinary::from_hex(BLOCK_1_COINBASE).unwrap(),
+            0,
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(res.events[0].ty, TX_SETTLED_EVENT);
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert!(state.fulfilled_txs.is_empty());
+        assert_eq!(SPV_TIP.load(deps.as_ref().storage).unwrap(), block_hash);
+    }
+
+    #[test]
+    fn test_settle_with_regtest_proof() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // Two txs in the block, the deposit is the second
+        let other = raw_tx(1);
+        let deposit = raw_tx(2);
+        let other_txid = sha256d(&other);
+        let deposit_txid = sha256d(&deposit);
+        let root = sha256d(&[other_txid, deposit_txid].concat());
+        setup(deps.as_mut(), &display_hash(&deposit_txid));
+
+        let anchor = mine_header(&[0; 32], &[0; 32], 1_700_000_000);
+        configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            spv_config(BitcoinNetwork::Regtest, 3, "51"),
+            Some(SpvAnchor {
+                header: anchor.clone(),
+                height: 100,
+            }),
+        )
+        .unwrap();
+
+        // Headers must be timestamped after the median time of the blocks before them
+        let stale = mine_header(&hash_of(&anchor), &root, 1_700_000_000);
+        let err = submit_headers(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            vec![stale],
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InvalidBlockHeader { .. }));
+
+        let block = mine_header(&hash_of(&anchor), &root, 1_700_000_001);
+        let block_hash = HexBinary::from(hash_of(&block).as_slice());
+        let confirmations = mine_chain(&hash_of(&block), 2, 1_700_000_002);
+
+        let settle =
+            |deps: DepsMut, headers: Vec<HexBinary>, index: u32, raw: &[u8], sibling: &[u8; 32]| {
+                settle_with_proof(
+                    deps,
+                    env.clone(),
+                    mock_info("relayer", &[]),
+                    1,
+                    headers,
+                    block_hash.clone(),
+                    HexBinary::from(raw),
+                    index,
+                    vec![HexBinary::from(sibling.as_slice())],
+                )
+            };
+
+        // Not enough blocks have been built on top of the deposit yet
+        let err = settle(deps.as_mut(), vec![block.clone()], 1, &deposit, &other_txid).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InsufficientConfirmations {
+                confirmations: 1,
+                required: 3
+            }
+        ));
+
+        // A competing chain with more work reorgs the deposit out of the best chain
+        let fork = mine_chain(&hash_of(&anchor), 2, 1_700_000_100);
+        submit_headers(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            fork.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            BEST_CHAIN.load(deps.as_ref().storage, 101).unwrap(),
+            HexBinary::from(hash_of(&fork[0]).as_slice())
+        );
+        let err = settle(deps.as_mut(), vec![], 1, &deposit, &other_txid).unwrap_err();
+        assert!(matches!(err, ContractError::UnknownBlock { .. }));
+
+        // Once the deposit's chain has the most work again, the deposit can be proven
+        submit_headers(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("relayer", &[]),
+            confirmations,
+        )
+        .unwrap();
+        assert_eq!(
+            BEST_CHAIN.load(deps.as_ref().storage, 101).unwrap(),
+            block_hash
+        );
+
+        // The proof must be for the position of the deposit in the block
+        let err = settle(deps.as_mut(), vec![], 0, &deposit, &other_txid).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidMerkleProof { .. }));
+
+        // Only the origin deposit settles the tx
+        let err = settle(deps.as_mut(), vec![], 0, &other, &deposit_txid).unwrap_err();
+        assert!(matches!(err, ContractError::OriginMismatch { .. }));
+
+        // The deposit must pay the custody address at least the tx amount
+        configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            spv_config(BitcoinNetwork::Regtest, 3, "52"),
+            None,
+        )
+        .unwrap();
+        let err = settle(deps.as_mut(), vec![], 1, &deposit, &other_txid).unwrap_err();
+        assert!(matches!(err, ContractError::OriginMismatch { .. }));
+        configure_spv(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            spv_config(BitcoinNetwork::Regtest, 3, "51"),
+            None,
+        )
+        .unwrap();
+        let mut state = STATE.load(deps.as_ref().storage).unwrap();
+        state.fulfilled_txs[0].coin.amount = Uint128::from(1_001u128);
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let err = settle(deps.as_mut(), vec![], 1, &deposit, &other_txid).unwrap_err();
+        assert!(matches!(err, ContractError::OriginMismatch { .. }));
+        state.fulfilled_txs[0].coin.amount = Uint128::from(1_000u128);
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+
+        settle(deps.as_mut(), vec![], 1, &deposit, &other_txid).unwrap();
+        let state = STATE.load(deps.as_ref().storage).unwrap();
+        assert!(state.fulfilled_txs.is_empty());
+    }
+}
@@ -1,12 +1,188 @@
-use crate::execute::Tx;
+use crate::execute::{Tx, TxOrigin, TxOwner, TxStatus};
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, Coin, Empty, HexBinary, Timestamp, Uint128, Uint256};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 pub const CONFIG_KEY: &[u8] = b"config";
 
 pub const STATE: Item<State> = Item::new("state");
 
+pub const CONFIG: Item<Config> = Item::new("config");
+
+// Set on instantiation and cleared by the last page of a state import, so state is only ever
+// imported into a freshly instantiated contract
+pub const IMPORT_OPEN: Item<bool> = Item::new("import_open");
+
+#[cw_serde]
+pub struct Config {
+    // Account allowed to update the config and withdraw protocol fees
+    pub admin: Addr,
+    // Number of blocks settled and expired txs are retained in the tx history
+    pub history_retention_blocks: u64,
+    // Portion of each fill, in basis points, kept by the contract as a protocol fee
+    pub protocol_fee_bps: u64,
+    // Number of seconds a queued fee withdrawal must wait before it can be executed
+    pub withdrawal_timelock_seconds: u64,
+    // Formats accepted for the destinations of outbound withdrawals. Inbound txs to foreign
+    // destinations are accepted by the destination routes instead.
+    #[serde(default)]
+    pub foreign_destination_formats: Vec<DestinationFormat>,
+    // Number of seconds market makers have to deliver a withdrawal before it can be refunded
+    #[serde(default = "default_outbound_timeout_seconds")]
+    pub outbound_timeout_seconds: u64,
+    // Routes delivering funds to foreign destinations, the first route matching a
+    // destination is taken
+    #[serde(default)]
+    pub destination_routes: Vec<DestinationRoute>,
+}
+
+pub const DEFAULT_OUTBOUND_TIMEOUT_SECONDS: u64 = 86_400;
+
+fn default_outbound_timeout_seconds() -> u64 {
+    DEFAULT_OUTBOUND_TIMEOUT_SECONDS
+}
+
+// DestinationFormat is a format of foreign destination addresses, for destinations paid
+// out through a connecting bridge
+#[cw_serde]
+pub enum DestinationFormat {
+    // A bech32 or bech32m address with the given human readable prefix
+    Bech32 { prefix: String },
+    // A hex encoded address of `length` bytes following the given prefix, e.g. "0x"
+    Hex { prefix: String, length: u32 },
+}
+
+// DestinationRoute delivers funds to the foreign destinations in `format` through `action`
+#[cw_serde]
+pub struct DestinationRoute {
+    pub format: DestinationFormat,
+    pub action: RouteAction,
+}
+
+// RouteAction is how funds are delivered to a destination matching a route. ICS-20
+// transfers are not offered, since a timed out or failed transfer returns the funds to the
+// contract after the tx was already fulfilled or expired, and nothing would deliver them.
+#[cw_serde]
+pub enum RouteAction {
+    // A call of the connecting bridge contract, with the funds attached
+    Bridge { contract: String },
+}
+
+// DenomMetadata describes a denom orders may be placed in
+#[cw_serde]
+pub struct DenomMetadata {
+    // Symbol to display amounts with, e.g. "BTC"
+    pub symbol: String,
+    // Number of decimals between the base denom and the display symbol
+    pub decimals: u8,
+    // Chain the denom is bridged from
+    pub origin_chain: String,
+    // Disabled denoms stay registered but no new orders are accepted in them
+    pub enabled: bool,
+    #[serde(default)]
+    pub settlement: SettlementMode,
+}
+
+// SettlementMode is how the bridged asset is paid out once a tx is settled or expires
+#[cw_serde]
+#[derive(Default)]
+pub enum SettlementMode {
+    // The module account sends the bridged funds
+    #[default]
+    Module,
+    // The contract is the tokenfactory admin of the denom and mints the bridged funds, to
+    // the market maker on settlement or to the destination when the tx expires unfilled
+    Mint,
+    // The module account hands the cleared funds of a tx that expires unfilled to the
+    // contract, which delivers them to the destination the same way a fill does
+    Forward,
+}
+
+impl SettlementMode {
+    // is_module returns whether funds are paid out by the module account, the default that
+    // stored txs leave out
+    pub fn is_module(&self) -> bool {
+        *self == SettlementMode::Module
+    }
+}
+
+// DENOM_REGISTRY holds the denoms orders are accepted in, keyed by denom
+pub const DENOM_REGISTRY: Map<&str, DenomMetadata> = Map::new("denom_registry");
+
+// Protocol fees held by the contract per denom, excluding queued withdrawals
+pub const TREASURY: Map<&str, Uint128> = Map::new("treasury");
+
+#[cw_serde]
+pub struct FeeWithdrawal {
+    pub id: u64,
+    pub to: Addr,
+    pub amount: Coin,
+    pub release_at: Timestamp,
+}
+
+// PosterScope restricts the txs a poster may add, expire and settle. An empty list
+// places no restriction on that dimension.
+#[cw_serde]
+#[derive(Default)]
+pub struct PosterScope {
+    pub origin_chains: Vec<String>,
+    pub denoms: Vec<String>,
+}
+
+// Accounts authorized to post txs in addition to the module account, with their scope
+pub const POSTERS: Map<&Addr, PosterScope> = Map::new("posters");
+
+// Origin deposits that already fund a tx, keyed by `TxOrigin::key`, mapped to the tx id
+pub const PROCESSED_ORIGINS: Map<&str, u64> = Map::new("processed_origins");
+
+// While the threshold is non-zero, txs are only added and settled once that many
+// registered attesters have attested to them
+#[cw_serde]
+pub struct AttestationConfig {
+    pub threshold: u32,
+}
+
+pub const ATTESTATION_CONFIG: Item<AttestationConfig> = Item::new("attestation_config");
+pub const ATTESTERS: Map<&Addr, Empty> = Map::new("attesters");
+
+// The tx each attester attested to for an origin deposit, keyed by `TxOrigin::key`
+#[cw_serde]
+pub struct AttestedTx {
+    pub destination_addr: String,
+    pub output_coin: Coin,
+    pub origin: TxOrigin,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<TxOwner>,
+}
+
+// Attestations are only kept until they reach the threshold and the tx is added or settled
+pub const TX_ATTESTATIONS: Map<(&str, &Addr), AttestedTx> = Map::new("tx_attestations");
+pub const SETTLEMENT_ATTESTATIONS: Map<(u64, &Addr), Empty> = Map::new("settlement_attestations");
+
+// Recorded whenever attesters disagree on the tx funded by an origin deposit
+#[cw_serde]
+pub struct AttestationConflict {
+    pub origin_key: String,
+    pub height: u64,
+    pub attestations: Vec<(Addr, AttestedTx)>,
+}
+
+pub const ATTESTATION_CONFLICTS: Map<&str, AttestationConflict> = Map::new("attestation_conflicts");
+
+pub const FEE_WITHDRAWALS: Map<u64, FeeWithdrawal> = Map::new("fee_withdrawals");
+pub const NEXT_FEE_WITHDRAWAL_ID: Item<u64> = Item::new("next_fee_withdrawal_id");
+
+// PendingTimelock is a shorter fee withdrawal timelock the admin has set. It only replaces
+// the current one once the current one has passed, so lowering it cannot speed up a
+// withdrawal queued right after.
+#[cw_serde]
+pub struct PendingTimelock {
+    pub seconds: u64,
+    pub effective_at: Timestamp,
+}
+
+pub const PENDING_TIMELOCK: Item<PendingTimelock> = Item::new("pending_timelock");
+
 #[cw_serde]
 pub struct State {
     pub module_account: String,
@@ -18,6 +194,244 @@ pub struct State {
 #[cw_serde]
 pub struct FulfillState {
     pub fulfiller_addr: Addr,
+    // The protocol fee kept by the contract from the fill
+    #[serde(default)]
+    pub protocol_fee: Uint128,
 }
 
 pub const FULFILL_REPLY_STATES: Map<u64, FulfillState> = Map::new("fulfill_reply_states");
+
+// Aggregate metrics per denom, maintained as part of each execute path
+#[cw_serde]
+#[derive(Default)]
+pub struct DenomStats {
+    pub added_count: u64,
+    pub added_volume: Uint128,
+    pub filled_count: u64,
+    pub filled_volume: Uint128,
+    pub settled_count: u64,
+    pub settled_volume: Uint128,
+    pub expired_count: u64,
+    pub expired_volume: Uint128,
+    #[serde(default)]
+    pub cancelled_count: u64,
+    #[serde(default)]
+    pub cancelled_volume: Uint128,
+    // Sum of the blocks elapsed between adding and filling each filled tx
+    pub total_fill_blocks: u64,
+}
+
+pub const DENOM_STATS: Map<&str, DenomStats> = Map::new("denom_stats");
+
+// Aggregate metrics per market maker and denom
+#[cw_serde]
+#[derive(Default)]
+pub struct MakerStats {
+    pub filled_count: u64,
+    pub filled_volume: Uint128,
+    pub settled_count: u64,
+    pub settled_volume: Uint128,
+    // Protocol fees the contract kept from the txs the market maker filled
+    pub fee_revenue: Uint128,
+}
+
+pub const MAKER_STATS: Map<(&Addr, &str), MakerStats> = Map::new("maker_stats");
+
+// TxRecord mirrors every tx known to the contract along with its latest status, so
+// txs can be looked up by fulfiller and destination. Records of settled and expired
+// txs are retained for `Config::history_retention_blocks` before being pruned.
+#[cw_serde]
+pub struct TxRecord {
+    pub tx: Tx,
+    pub status: TxStatus,
+    pub updated_height: u64,
+    // Height after which the record may be pruned, or u64::MAX while the tx is open.
+    // It is computed with the retention window at the time the record is written.
+    pub prune_height: u64,
+}
+
+pub struct TxRecordIndexes<'a> {
+    pub fulfiller: MultiIndex<'a, String, TxRecord, u64>,
+    pub destination: MultiIndex<'a, String, TxRecord, u64>,
+    pub prune_height: MultiIndex<'a, u64, TxRecord, u64>,
+}
+
+impl<'a> IndexList<TxRecord> for TxRecordIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TxRecord>> + '_> {
+        let v: Vec<&dyn Index<TxRecord>> =
+            vec![&self.fulfiller, &self.destination, &self.prune_height];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn tx_records<'a>() -> IndexedMap<'a, u64, TxRecord, TxRecordIndexes<'a>> {
+    let indexes = TxRecordIndexes {
+        fulfiller: MultiIndex::new(
+            |_pk, record| {
+                record
+                    .tx
+                    .fill
+                    .as_ref()
+                    .map(|fill| fill.fulfiller_addr.to_string())
+                    .unwrap_or_default()
+            },
+            "tx_records",
+            "tx_records__fulfiller",
+        ),
+        destination: MultiIndex::new(
+            |_pk, record| record.tx.destination_addr.clone(),
+            "tx_records",
+            "tx_records__destination",
+        ),
+        prune_height: MultiIndex::new(
+            |_pk, record| record.prune_height,
+            "tx_records",
+            "tx_records__prune_height",
+        ),
+    };
+    IndexedMap::new("tx_records", indexes)
+}
+
+// KeyType is the signature scheme of an off-chain signer's public key
+#[cw_serde]
+pub enum KeyType {
+    Secp256k1,
+    Ed25519,
+}
+
+#[cw_serde]
+pub struct Signer {
+    pub key_type: KeyType,
+    pub pubkey: Binary,
+}
+
+// SignerSet is the off-chain signer set authorizing signed orders. Any relayer can submit
+// an order carrying signatures from at least `threshold` of the signers. The version is
+// bumped on every rotation and is part of the signed payload.
+#[cw_serde]
+pub struct SignerSet {
+    pub version: u64,
+    pub threshold: u32,
+    pub signers: Vec<Signer>,
+}
+
+pub const SIGNER_SET: Item<SignerSet> = Item::new("signer_set");
+// The version of the latest signer set update. It outlives the signer set, so the versions
+// issued after signed orders are disabled and enabled again are never reused.
+pub const SIGNER_SET_VERSION: Item<u64> = Item::new("signer_set_version");
+
+// BitcoinNetwork selects the proof-of-work rules headers are checked against
+#[cw_serde]
+pub enum BitcoinNetwork {
+    // Difficulty is retargeted every 2016 blocks
+    Mainnet,
+    // Minimal difficulty that is never retargeted, for local test networks
+    Regtest,
+}
+
+// SpvConfig enables settlement of txs whose origin is on `origin_chain` by proving the
+// origin deposit was included in a block with enough confirmations.
+#[cw_serde]
+pub struct SpvConfig {
+    pub origin_chain: String,
+    pub network: BitcoinNetwork,
+    // Number of blocks, including the one containing the deposit, required on the best chain
+    pub confirmations: u32,
+    // The scriptPubKey of the custody address origin deposits must pay to. The deposit
+    // output must also carry at least the tx's amount in satoshis.
+    pub deposit_script_pubkey: HexBinary,
+}
+
+pub const SPV_CONFIG: Item<SpvConfig> = Item::new("spv_config");
+
+// StoredHeader is a validated block header along with its position in the header tree
+#[cw_serde]
+pub struct StoredHeader {
+    pub header: HexBinary,
+    pub height: u64,
+    // Work of the chain from the anchor up to and including this header
+    pub chain_work: Uint256,
+    // Timestamp of the first block of this header's difficulty period
+    pub period_start_time: u32,
+}
+
+// BLOCK_HEADERS are keyed by the block hash in internal byte order
+pub const BLOCK_HEADERS: Map<&[u8], StoredHeader> = Map::new("block_headers");
+// BEST_CHAIN maps heights to the hashes of the blocks on the chain with the most work
+pub const BEST_CHAIN: Map<u64, HexBinary> = Map::new("best_chain");
+pub const SPV_TIP: Item<HexBinary> = Item::new("spv_tip");
+
+#[cw_serde]
+pub enum WithdrawalStatus {
+    // Waiting for a market maker to deliver the funds on the destination chain
+    Open,
+    // Delivered, the locked funds were released to the market maker
+    Paid,
+    // Not delivered in time, the locked funds were returned to the owner
+    Refunded,
+}
+
+impl WithdrawalStatus {
+    // index_key is the key withdrawals are indexed by their status under
+    pub fn index_key(&self) -> u8 {
+        match self {
+            WithdrawalStatus::Open => 0,
+            WithdrawalStatus::Paid => 1,
+            WithdrawalStatus::Refunded => 2,
+        }
+    }
+}
+
+// Withdrawal is an outbound order: funds locked on this chain by their owner, to be
+// delivered on an external chain by a market maker, who then claims the locked funds.
+#[cw_serde]
+pub struct Withdrawal {
+    pub id: u64,
+    pub owner: Addr,
+    pub coin: Coin,
+    pub destination_chain: String,
+    pub destination_addr: String,
+    pub created_at: Timestamp,
+    // After this time the owner may be refunded if the withdrawal wasn't delivered
+    pub expires_at: Timestamp,
+    pub status: WithdrawalStatus,
+    // The market maker that delivered the withdrawal and its payout on the destination chain
+    pub maker: Option<Addr>,
+    pub payout: Option<TxOrigin>,
+}
+
+pub struct WithdrawalIndexes<'a> {
+    pub owner: MultiIndex<'a, String, Withdrawal, u64>,
+    pub status: MultiIndex<'a, u8, Withdrawal, u64>,
+}
+
+impl<'a> IndexList<Withdrawal> for WithdrawalIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Withdrawal>> + '_> {
+        let v: Vec<&dyn Index<Withdrawal>> = vec![&self.owner, &self.status];
+        Box::new(v.into_iter())
+    }
+}
+
+pub fn withdrawals<'a>() -> IndexedMap<'a, u64, Withdrawal, WithdrawalIndexes<'a>> {
+    let indexes = WithdrawalIndexes {
+        owner: MultiIndex::new(
+            |_pk, withdrawal| withdrawal.owner.to_string(),
+            "withdrawals",
+            "withdrawals__owner",
+        ),
+        status: MultiIndex::new(
+            |_pk, withdrawal| withdrawal.status.index_key(),
+            "withdrawals",
+            "withdrawals__status",
+        ),
+    };
+    IndexedMap::new("withdrawals", indexes)
+}
+
+pub const NEXT_WITHDRAWAL_ID: Item<u64> = Item::new("next_withdrawal_id");
+// Payouts market makers claim to have delivered withdrawals with, by withdrawal and maker
+pub const WITHDRAWAL_CLAIMS: Map<(u64, &Addr), TxOrigin> = Map::new("withdrawal_claims");
+// The maker each attester confirmed delivered a withdrawal
+pub const WITHDRAWAL_ATTESTATIONS: Map<(u64, &Addr), Addr> = Map::new("withdrawal_attestations");
+// Payouts already used to release a withdrawal, keyed by `TxOrigin::key`
+pub const PROCESSED_PAYOUTS: Map<&str, u64> = Map::new("processed_payouts");
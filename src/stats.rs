@@ -0,0 +1,102 @@
+use cosmwasm_std::{Decimal, StdResult, Storage, Uint128};
+
+use crate::execute::Tx;
+use crate::state::{DenomStats, MakerStats, DENOM_STATS, MAKER_STATS};
+
+// record_added accounts for a tx newly added to the pending transactions store.
+pub fn record_added(storage: &mut dyn Storage, tx: &Tx) -> StdResult<()> {
+    DENOM_STATS.update(storage, &tx.coin.denom, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.added_count += 1;
+        stats.added_volume += tx.coin.amount;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+// record_filled accounts for a tx a market maker has successfully fulfilled, along with
+// the protocol fee kept from the fill. The tx must already carry its fill.
+pub fn record_filled(storage: &mut dyn Storage, tx: &Tx, protocol_fee: Uint128) -> StdResult<()> {
+    let Some(fill) = &tx.fill else {
+        return Ok(());
+    };
+
+    DENOM_STATS.update(storage, &tx.coin.denom, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.filled_count += 1;
+        stats.filled_volume += fill.amount;
+        stats.total_fill_blocks += fill.height.saturating_sub(tx.added_height);
+        Ok(stats)
+    })?;
+    MAKER_STATS.update(
+        storage,
+        (&fill.fulfiller_addr, &tx.coin.denom),
+        |stats| -> StdResult<_> {
+            let mut stats = stats.unwrap_or_default();
+            stats.filled_count += 1;
+            stats.filled_volume += fill.amount;
+            stats.fee_revenue += protocol_fee;
+            Ok(stats)
+        },
+    )?;
+    Ok(())
+}
+
+// record_expired accounts for a pending tx that was removed without being fulfilled.
+pub fn record_expired(storage: &mut dyn Storage, tx: &Tx) -> StdResult<()> {
+    DENOM_STATS.update(storage, &tx.coin.denom, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.expired_count += 1;
+        stats.expired_volume += tx.coin.amount;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+// record_cancelled accounts for a pending tx its owner withdrew from the order book.
+pub fn record_cancelled(storage: &mut dyn Storage, tx: &Tx) -> StdResult<()> {
+    DENOM_STATS.update(storage, &tx.coin.denom, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.cancelled_count += 1;
+        stats.cancelled_volume += tx.coin.amount;
+        Ok(stats)
+    })?;
+    Ok(())
+}
+
+// record_settled accounts for a fulfilled tx whose bridged funds have been paid out
+// to the market maker that fulfilled it.
+pub fn record_settled(storage: &mut dyn Storage, tx: &Tx) -> StdResult<()> {
+    DENOM_STATS.update(storage, &tx.coin.denom, |stats| -> StdResult<_> {
+        let mut stats = stats.unwrap_or_default();
+        stats.settled_count += 1;
+        stats.settled_volume += tx.coin.amount;
+        Ok(stats)
+    })?;
+
+    let Some(fill) = &tx.fill else {
+        return Ok(());
+    };
+    MAKER_STATS.update(
+        storage,
+        (&fill.fulfiller_addr, &tx.coin.denom),
+        |stats| -> StdResult<_> {
+            let mut stats: MakerStats = stats.unwrap_or_default();
+            stats.settled_count += 1;
+            stats.settled_volume += tx.coin.amount;
+            Ok(stats)
+        },
+    )?;
+    Ok(())
+}
+
+// average_fill_blocks returns the mean number of blocks a filled tx spent pending.
+pub fn average_fill_blocks(stats: &DenomStats) -> Option<Decimal> {
+    if stats.filled_count == 0 {
+        return None;
+    }
+    Some(Decimal::from_ratio(
+        stats.total_fill_blocks,
+        stats.filled_count,
+    ))
+}
@@ -0,0 +1,181 @@
+#[cfg(all(test, feature = "bench"))]
+mod tests {
+    use cosmwasm_std::{to_json_vec, Coin};
+    use osmosis_std::types::cosmwasm::wasm::v1::{
+        MsgMigrateContract, MsgMigrateContractResponse, QueryContractInfoRequest,
+        QueryContractInfoResponse,
+    };
+    use osmosis_test_tube::{Account, Module, OsmosisTestApp, Runner, SigningAccount, Wasm};
+
+    use crate::bench::{measure, Operation, VM_ORDER_BOOK_SIZES};
+    use crate::execute::{Tx, TxStatus};
+    use crate::msg::{ExecuteMsg, MigrateMsg, QueryMsg};
+    use crate::snapshot::{ExportCursor, ExportRecord, StateExport};
+    use crate::state::{SettlementMode, TxRecord};
+    use crate::test_tube::init::initialize::default_init;
+
+    const DENOM: &str = "ufoo";
+    const AMOUNT: u128 = 100_000;
+    const IMPORT_PAGE: usize = 1_000;
+
+    // seed_orders imports `open_orders` pending txs through migrations, as posting them one by
+    // one rewrites the growing order book each time
+    fn seed_orders(
+        app: &OsmosisTestApp,
+        contract: &str,
+        admin: &SigningAccount,
+        destination: &str,
+        open_orders: usize,
+    ) {
+        let wasm = Wasm::new(app);
+        let code_id = app
+            .query::<_, QueryContractInfoResponse>(
+                "/cosmwasm.wasm.v1.Query/ContractInfo",
+                &QueryContractInfoRequest {
+                    address: contract.to_string(),
+                },
+            )
+            .unwrap()
+            .contract_info
+            .unwrap()
+            .code_id;
+
+        let mut imported = 0;
+        while imported < open_orders {
+            let mut export: StateExport = wasm
+                .query(
+                    contract,
+                    &QueryMsg::ExportState {
+                        start_after: None,
+                        limit: Some(0),
+                    },
+                )
+                .unwrap();
+            let page = IMPORT_PAGE.min(open_orders - imported);
+            let first_id = export.next_tx_id;
+            let txs: Vec<Tx> = (first_id..first_id + page as u64)
+                .map(|id| Tx {
+                    id,
+                    destination_addr: destination.to_string(),
+                    coin: Coin::new(AMOUNT, DENOM),
+                    added_height: 0,
+                    fill: None,
+                    origin: None,
+                    owner: None,
+                    amendments: 0,
+                    settlement: SettlementMode::Module,
+                    payee: None,
+                })
+                .collect();
+            // Each tx comes with the history record a posted tx leaves
+            let records = txs.iter().map(|tx| {
+                ExportRecord::TxRecord(TxRecord {
+                    tx: tx.clone(),
+                    status: TxStatus::Pending,
+                    updated_height: 0,
+                    prune_height: u64::MAX,
+                })
+            });
+            export.records = txs
+                .iter()
+                .cloned()
+                .map(ExportRecord::Tx)
+                .chain(records)
+                .collect();
+            export.next_tx_id = first_id + page as u64;
+            // The last page closes the import
+            export.next = (imported + page < open_orders).then(|| ExportCursor::Tx {
+                id: export.next_tx_id - 1,
+            });
+
+            app.execute::<_, MsgMigrateContractResponse>(
+                MsgMigrateContract {
+                    sender: admin.address(),
+                    contract: contract.to_string(),
+                    code_id,
+                    msg: to_json_vec(&MigrateMsg::ImportState { export }).unwrap(),
+                },
+                "/cosmwasm.wasm.v1.MsgMigrateContract",
+                admin,
+            )
+            .unwrap();
+            imported += page;
+        }
+    }
+
+    // Chain gas of each operation next to the storage gas bench::measure accounts for. Run with
+    // the contract built for wasm32-unknown-unknown:
+    //
+    //     cargo test --features bench,test-tube -- --ignored test_chain_gas_report --nocapture
+    #[test]
+    #[ignore]
+    fn test_chain_gas_report() {
+        for open_orders in VM_ORDER_BOOK_SIZES {
+            let (app, contract, admin, module_account) = default_init();
+            let contract = contract.as_str();
+            let wasm = Wasm::new(&app);
+            let destination = app.init_account(&[]).unwrap();
+            let maker = app
+                .init_account(&[
+                    Coin::new(1_000_000_000_000, "uosmo"),
+                    Coin::new(1_000_000_000, DENOM),
+                ])
+                .unwrap();
+
+            let add_tx = ExecuteMsg::AddTx {
+                destination_addr: destination.address(),
+                output_coin: Coin::new(AMOUNT, DENOM),
+                origin: None,
+                owner: None,
+                signatures: None,
+            };
+            let funds = [Coin::new(AMOUNT, DENOM)];
+            wasm.execute(contract, &add_tx, &[], &module_account)
+                .unwrap();
+            wasm.execute(
+                contract,
+                &ExecuteMsg::FulfillTx { tx_id: 1 },
+                &funds,
+                &maker,
+            )
+            .unwrap();
+            seed_orders(&app, contract, &admin, &destination.address(), open_orders);
+
+            let operations = [
+                (Operation::Add, add_tx, &[][..], &module_account),
+                (
+                    Operation::Fill,
+                    ExecuteMsg::FulfillTx { tx_id: 2 },
+                    &funds[..],
+                    &maker,
+                ),
+                (
+                    Operation::Remove,
+                    ExecuteMsg::RemoveTx { tx_id: 3 },
+                    &[][..],
+                    &module_account,
+                ),
+                (
+                    Operation::Settle,
+                    ExecuteMsg::RemoveFulfilledTx { tx_id: 1 },
+                    &[][..],
+                    &module_account,
+                ),
+            ];
+            for (operation, msg, funds, sender) in operations {
+                let gas = wasm
+                    .execute(contract, &msg, funds, sender)
+                    .unwrap()
+                    .gas_info
+                    .gas_used;
+                let storage = measure(operation, open_orders).unwrap();
+                println!(
+                    "{operation:?} with {open_orders} open orders: {gas} gas, {} in storage",
+                    storage.gas
+                );
+                // The chain charges the same store access, and wasm execution on top
+                assert!(gas >= storage.gas);
+            }
+        }
+    }
+}
@@ -4,7 +4,8 @@ pub mod initialize {
     use cosmwasm_std::{Addr, Coin};
     use osmosis_test_tube::{Account, Module, OsmosisTestApp, SigningAccount, Wasm};
 
-    use crate::msg::InstantiateMsg;
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+    use crate::state::{DenomMetadata, SettlementMode};
 
     const ADMIN_BALANCE_AMOUNT: u128 = 340282366920938463463374607431768211455u128;
     const DENOM_BASE: &str = "uatom";
@@ -50,6 +51,13 @@ pub mod initialize {
                 code_id,
                 &InstantiateMsg {
                     module_account: module_account.address(),
+                    admin: None,
+                    history_retention_blocks: None,
+                    protocol_fee_bps: None,
+                    withdrawal_timelock_seconds: None,
+                    foreign_destination_formats: None,
+                    outbound_timeout_seconds: None,
+                    destination_routes: None,
                 },
                 Some(admin.address().as_str()),
                 Some("cl-vault"),
@@ -58,6 +66,24 @@ pub mod initialize {
             )
             .unwrap();
 
+        // Register the denom the tests place orders in
+        wasm.execute(
+            &contract.data.address,
+            &ExecuteMsg::SetDenom {
+                denom: "ufoo".to_string(),
+                metadata: DenomMetadata {
+                    symbol: "FOO".to_string(),
+                    decimals: 6,
+                    origin_chain: "foo".to_string(),
+                    enabled: true,
+                    settlement: SettlementMode::Module,
+                },
+            },
+            &[],
+            &admin,
+        )
+        .unwrap();
+
         (
             app,
             Addr::unchecked(contract.data.address),
@@ -1,2 +1,3 @@
+mod bench;
 mod init;
 mod test;
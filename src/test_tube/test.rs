@@ -26,6 +26,9 @@ mod tests {
         let add_tx = ExecuteMsg::AddTx {
             destination_addr: dest.address(),
             output_coin: Coin::new(1_000_000, "ufoo"),
+            origin: None,
+            owner: None,
+            signatures: None,
         };
         wasm.execute(contract_address.as_str(), &add_tx, &[], &module_account)
             .unwrap();
@@ -85,7 +88,18 @@ mod tests {
 
         // Check the fulfilled tx
         assert_eq!(fulfilled_txs.txs[0].id, 1);
-        assert_eq!(fulfilled_txs.txs[0].destination_addr, bob.address());
+        assert_eq!(fulfilled_txs.txs[0].destination_addr, dest.address());
+        assert_eq!(
+            fulfilled_txs.txs[0].fill.as_ref().unwrap().fulfiller_addr,
+            bob.address()
+        );
+        assert_eq!(
+            fulfilled_txs.txs[0]
+                .payee
+                .as_ref()
+                .map(|payee| payee.to_string()),
+            Some(bob.address())
+        );
         assert_eq!(fulfilled_txs.txs[0].coin, Coin::new(1_000_000, "ufoo"));
     }
 
@@ -102,6 +116,9 @@ mod tests {
         let add_tx = ExecuteMsg::AddTx {
             destination_addr: dest.address(),
             output_coin: Coin::new(1_000_000, "ufoo"),
+            origin: None,
+            owner: None,
+            signatures: None,
         };
         wasm.execute(contract_address.as_str(), &add_tx, &[], &module_account)
             .unwrap();
@@ -0,0 +1,40 @@
+use cosmwasm_std::{Addr, Storage};
+
+use crate::state::{Config, DenomMetadata, SettlementMode, State, CONFIG, DENOM_REGISTRY, STATE};
+
+// mock_config returns the contract config used by the unit tests.
+pub fn mock_config() -> Config {
+    Config {
+        admin: Addr::unchecked("admin"),
+        history_retention_blocks: 100,
+        protocol_fee_bps: 0,
+        withdrawal_timelock_seconds: 86_400,
+        foreign_destination_formats: vec![],
+        outbound_timeout_seconds: 3_600,
+        destination_routes: vec![],
+    }
+}
+
+// mock_state saves an empty order book and the mock config, as the unit tests start from.
+pub fn mock_state(storage: &mut dyn Storage) {
+    let state = State {
+        module_account: "module_account".to_string(),
+        pending_txs: vec![],
+        fulfilled_txs: vec![],
+        next_id: 0,
+    };
+    STATE.save(storage, &state).unwrap();
+    CONFIG.save(storage, &mock_config()).unwrap();
+}
+
+// mock_denom registers an enabled denom orders can be placed in.
+pub fn mock_denom(storage: &mut dyn Storage, denom: &str) {
+    let metadata = DenomMetadata {
+        symbol: denom.to_uppercase(),
+        decimals: 6,
+        origin_chain: "bitcoin".to_string(),
+        enabled: true,
+        settlement: SettlementMode::Module,
+    };
+    DENOM_REGISTRY.save(storage, denom, &metadata).unwrap();
+}
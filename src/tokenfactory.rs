@@ -0,0 +1,267 @@
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response};
+use osmosis_std::types::osmosis::tokenfactory::v1beta1::{MsgCreateDenom, MsgMint};
+
+use crate::destination::payout;
+use crate::execute::Tx;
+use crate::state::{DenomMetadata, SettlementMode, CONFIG, DENOM_REGISTRY};
+use crate::ContractError;
+
+// contract_mints returns whether the denom is a tokenfactory denom created by the contract
+// through `CreateBridgedDenom`, the only denoms it can mint
+pub fn contract_mints(env: &Env, denom: &str) -> bool {
+    denom.starts_with(&format!("factory/{}/", env.contract.address))
+}
+
+fn mint_msg(env: &Env, coin: &Coin, to: &Addr) -> CosmosMsg {
+    MsgMint {
+        sender: env.contract.address.to_string(),
+        amount: Some(coin.clone().into()),
+        mint_to_address: to.to_string(),
+    }
+    .into()
+}
+
+// settlement_mint returns the message minting a settled tx's bridged funds to the market
+// maker that fulfilled it, if the tx is settled by minting.
+pub fn settlement_mint(env: &Env, tx: &Tx) -> Option<CosmosMsg> {
+    match &tx.fill {
+        Some(fill) if tx.settlement == SettlementMode::Mint => {
+            Some(mint_msg(env, &tx.coin, &fill.fulfiller_addr))
+        }
+        _ => None,
+    }
+}
+
+// expiry_mint returns the messages minting an expired tx's bridged funds to its destination,
+// if the tx is settled by minting. Funds for foreign destinations are minted to the
+// contract and delivered through the destination's route, the same way a fill is.
+pub fn expiry_mint(deps: Deps, env: &Env, tx: &Tx) -> Result<Vec<CosmosMsg>, ContractError> {
+    if tx.settlement != SettlementMode::Mint {
+        return Ok(vec![]);
+    }
+    let payout = payout(deps, &tx.destination_addr, tx.coin.clone())?;
+    if payout.route == "bank" {
+        let destination = Addr::unchecked(&tx.destination_addr);
+        return Ok(vec![mint_msg(env, &tx.coin, &destination)]);
+    }
+    Ok(vec![
+        mint_msg(env, &tx.coin, &env.contract.address),
+        payout.msg,
+    ])
+}
+
+// create_bridged_denom is called by the admin to create a tokenfactory denom administered
+// by the contract, and registers it to be minted on settlement.
+pub fn create_bridged_denom(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    subdenom: String,
+    metadata: DenomMetadata,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let denom = format!("factory/{}/{}", env.contract.address, subdenom);
+    if DENOM_REGISTRY.has(deps.storage, &denom) {
+        return Err(ContractError::DenomAlreadyRegistered { denom });
+    }
+    DENOM_REGISTRY.save(
+        deps.storage,
+        &denom,
+        &DenomMetadata {
+            settlement: SettlementMode::Mint,
+            ..metadata
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_message(MsgCreateDenom {
+            sender: env.contract.address.to_string(),
+            subdenom,
+        })
+        .add_attribute("action", "create_bridged_denom")
+        .add_attribute("denom", denom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::destination::BridgeExecuteMsg;
+    use crate::execute::{add_pending_tx, remove_fulfilled_tx, remove_pending_tx, Fill};
+    use crate::state::{DestinationFormat, DestinationRoute, RouteAction, State, STATE};
+    use crate::testing::mock_config;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coin, to_json_binary, Uint128, WasmMsg};
+
+    fn btc_metadata() -> DenomMetadata {
+        DenomMetadata {
+            symbol: "BTC".to_string(),
+            decimals: 8,
+            origin_chain: "bitcoin".to_string(),
+            enabled: true,
+            settlement: SettlementMode::Module,
+        }
+    }
+
+    #[test]
+    fn test_mint_bridged_denom() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let module_info = mock_info("module_account", &[]);
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+
+        let err = create_bridged_denom(
+            deps.as_mut(),
+            env.clone(),
+            module_info.clone(),
+            "btc".to_string(),
+            btc_metadata(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        let res = create_bridged_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "btc".to_string(),
+            btc_metadata(),
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        let denom = format!("factory/{}/btc", env.contract.address);
+        let metadata = DENOM_REGISTRY.load(deps.as_ref().storage, &denom).unwrap();
+        assert_eq!(metadata.settlement, SettlementMode::Mint);
+
+        for _ in 0..2 {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                module_info.clone(),
+                "destination_addr".to_string(),
+                coin(100, &denom),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        // An expired tx is minted to its destination
+        let res = remove_pending_tx(deps.as_mut(), env.clone(), module_info.clone(), 0).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            mint_msg(
+                &env,
+                &coin(100, &denom),
+                &Addr::unchecked("destination_addr")
+            )
+        );
+
+        // A settled tx is minted to the market maker that fulfilled it, as recorded on the tx
+        // even once the denom is no longer registered
+        DENOM_REGISTRY.remove(deps.as_mut().storage, &denom);
+        let mut state = STATE.load(deps.as_ref().storage).unwrap();
+        let mut tx = state.pending_txs.remove(0);
+        assert_eq!(tx.settlement, SettlementMode::Mint);
+        tx.fill = Some(Fill {
+            fulfiller_addr: Addr::unchecked("market_maker"),
+            amount: Uint128::from(100u128),
+            height: env.block.height,
+        });
+        state.fulfilled_txs.push(tx);
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+
+        let res = remove_fulfilled_tx(deps.as_mut(), env.clone(), module_info, 1).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            mint_msg(&env, &coin(100, &denom), &Addr::unchecked("market_maker"))
+        );
+    }
+
+    #[test]
+    fn test_mint_expiry_to_foreign_destination() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let module_info = mock_info("module_account", &[]);
+        let destination = "0xABCDEF0123456789ABCDEF0123456789ABCDEF01";
+
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let mut config = mock_config();
+        config.destination_routes = vec![DestinationRoute {
+            format: DestinationFormat::Hex {
+                prefix: "0x".to_string(),
+                length: 20,
+            },
+            action: RouteAction::Bridge {
+                contract: "bridge".to_string(),
+            },
+        }];
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        create_bridged_denom(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            "btc".to_string(),
+            btc_metadata(),
+        )
+        .unwrap();
+        let denom = format!("factory/{}/btc", env.contract.address);
+
+        for _ in 0..2 {
+            add_pending_tx(
+                deps.as_mut(),
+                env.clone(),
+                module_info.clone(),
+                destination.to_string(),
+                coin(100, &denom),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        }
+
+        // The funds are minted to the contract and sent on through the destination's route
+        let res = remove_pending_tx(deps.as_mut(), env.clone(), module_info.clone(), 0).unwrap();
+        let messages: Vec<CosmosMsg> = res.messages.into_iter().map(|msg| msg.msg).collect();
+        assert_eq!(
+            messages,
+            vec![
+                mint_msg(&env, &coin(100, &denom), &env.contract.address),
+                WasmMsg::Execute {
+                    contract_addr: "bridge".to_string(),
+                    msg: to_json_binary(&BridgeExecuteMsg::Transfer {
+                        destination_addr: destination.to_string(),
+                    })
+                    .unwrap(),
+                    funds: vec![coin(100, &denom)],
+                }
+                .into(),
+            ]
+        );
+
+        // Without a route the expiry fails rather than dropping the funds
+        config.destination_routes = vec![];
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        let err = remove_pending_tx(deps.as_mut(), env, module_info, 1).unwrap_err();
+        assert!(matches!(err, ContractError::NoDestinationRoute { .. }));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, Deps, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage,
+    Uint128,
+};
+
+use crate::msg::TreasuryBalance;
+use crate::state::{
+    Config, FeeWithdrawal, PendingTimelock, CONFIG, FEE_WITHDRAWALS, NEXT_FEE_WITHDRAWAL_ID,
+    PENDING_TIMELOCK, TREASURY,
+};
+use crate::ContractError;
+
+// credit_treasury accounts for protocol fees the contract now holds.
+pub fn credit_treasury(storage: &mut dyn Storage, coin: &Coin) -> StdResult<()> {
+    if coin.amount.is_zero() {
+        return Ok(());
+    }
+    TREASURY.update(storage, &coin.denom, |balance| -> StdResult<_> {
+        Ok(balance.unwrap_or_default() + coin.amount)
+    })?;
+    Ok(())
+}
+
+// load_config loads the config with any pending timelock reduction that has come into
+// effect applied to it.
+pub fn load_config(storage: &mut dyn Storage, env: &Env) -> StdResult<Config> {
+    let mut config = CONFIG.load(storage)?;
+    if let Some(pending) = PENDING_TIMELOCK.may_load(storage)? {
+        if env.block.time >= pending.effective_at {
+            config.withdrawal_timelock_seconds = pending.seconds;
+            CONFIG.save(storage, &config)?;
+            PENDING_TIMELOCK.remove(storage);
+        }
+    }
+    Ok(config)
+}
+
+// set_withdrawal_timelock changes the fee withdrawal timelock. A longer timelock applies
+// at once, while a shorter one only applies once the current timelock has passed.
+pub fn set_withdrawal_timelock(
+    storage: &mut dyn Storage,
+    env: &Env,
+    config: &mut Config,
+    seconds: u64,
+) -> StdResult<()> {
+    if seconds < config.withdrawal_timelock_seconds {
+        PENDING_TIMELOCK.save(
+            storage,
+            &PendingTimelock {
+                seconds,
+                effective_at: env
+                    .block
+                    .time
+                    .plus_seconds(config.withdrawal_timelock_seconds),
+            },
+        )?;
+    } else {
+        config.withdrawal_timelock_seconds = seconds;
+        PENDING_TIMELOCK.remove(storage);
+    }
+    Ok(())
+}
+
+// withdraw_fees is called by the admin to queue a withdrawal of protocol fees.
+// The amount is reserved from the treasury immediately, but can only be sent
+// once the configured timelock has passed.
+pub fn withdraw_fees(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+    amount: Coin,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage, &env)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let to = deps.api.addr_validate(&to)?;
+
+    // Reserve the amount from the treasury
+    let balance = TREASURY
+        .may_load(deps.storage, &amount.denom)?
+        .unwrap_or_default();
+    if amount.amount.is_zero() || balance < amount.amount {
+        return Err(ContractError::InsufficientTreasury {
+            denom: amount.denom,
+        });
+    }
+    TREASURY.save(deps.storage, &amount.denom, &(balance - amount.amount))?;
+
+    let id = NEXT_FEE_WITHDRAWAL_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    NEXT_FEE_WITHDRAWAL_ID.save(deps.storage, &(id + 1))?;
+
+    let withdrawal = FeeWithdrawal {
+        id,
+        to,
+        amount,
+        release_at: env
+            .block
+            .time
+            .plus_seconds(config.withdrawal_timelock_seconds),
+    };
+    FEE_WITHDRAWALS.save(deps.storage, id, &withdrawal)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "withdraw_fees")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_attribute("to", withdrawal.to)
+        .add_attribute("amount", withdrawal.amount.to_string())
+        .add_attribute("release_at", withdrawal.release_at.to_string()))
+}
+
+// execute_fee_withdrawal is called by the admin to send a queued withdrawal
+// once its timelock has passed.
+pub fn execute_fee_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage, &env)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let withdrawal = FEE_WITHDRAWALS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::FeeWithdrawalNotFound { id })?;
+    if env.block.time < withdrawal.release_at {
+        return Err(ContractError::FeeWithdrawalLocked {
+            id,
+            release_at: withdrawal.release_at,
+        });
+    }
+    FEE_WITHDRAWALS.remove(deps.storage, id);
+
+    Ok(Response::new()
+        .add_attribute("action", "execute_fee_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string())
+        .add_message(BankMsg::Send {
+            to_address: withdrawal.to.to_string(),
+            amount: vec![withdrawal.amount],
+        }))
+}
+
+// cancel_fee_withdrawal is called by the admin to drop a queued withdrawal,
+// returning its amount to the treasury.
+pub fn cancel_fee_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let config = load_config(deps.storage, &env)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let withdrawal = FEE_WITHDRAWALS
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::FeeWithdrawalNotFound { id })?;
+    FEE_WITHDRAWALS.remove(deps.storage, id);
+    credit_treasury(deps.storage, &withdrawal.amount)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_fee_withdrawal")
+        .add_attribute("withdrawal_id", id.to_string()))
+}
+
+// reconcile_treasury compares the protocol fees the contract accounts for, including
+// queued withdrawals, against the actual bank balances of the contract per denom.
+pub fn reconcile_treasury(deps: Deps, contract_addr: &Addr) -> StdResult<Vec<TreasuryBalance>> {
+    let mut accounted: BTreeMap<String, (Uint128, Uint128)> = BTreeMap::new();
+    for item in TREASURY.range(deps.storage, None, None, Order::Ascending) {
+        let (denom, amount) = item?;
+        accounted.entry(denom).or_default().0 += amount;
+    }
+    for item in FEE_WITHDRAWALS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, withdrawal) = item?;
+        accounted.entry(withdrawal.amount.denom).or_default().1 += withdrawal.amount.amount;
+    }
+
+    accounted
+        .into_iter()
+        .map(|(denom, (available, queued))| {
+            let bank_balance = deps.querier.query_balance(contract_addr, &denom)?.amount;
+            let expected = available + queued;
+            Ok(TreasuryBalance {
+                denom,
+                available,
+                queued,
+                bank_balance,
+                surplus: bank_balance.saturating_sub(expected),
+                deficit: expected.saturating_sub(bank_balance),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute::{add_pending_tx, fulfill_pending_tx, update_config};
+    use crate::state::{State, STATE};
+    use crate::testing::{mock_config, mock_denom};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+    };
+    use cosmwasm_std::{coin, coins, CosmosMsg};
+
+    #[test]
+    fn test_fulfill_credits_protocol_fee() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("module_account", &[]);
+
+        // Initialize state with a 1% protocol fee
+        let state = State {
+            module_account: "module_account".to_string(),
+            pending_txs: vec![],
+            fulfilled_txs: vec![],
+            next_id: 0,
+        };
+        STATE.save(deps.as_mut().storage, &state).unwrap();
+        let mut config = mock_config();
+        config.protocol_fee_bps = 100;
+        CONFIG.save(deps.as_mut().storage, &config).unwrap();
+        mock_denom(deps.as_mut().storage, "token");
+
+        add_pending_tx(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            "destination_addr".to_string(),
+            coin(1_000, "token"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let maker_info = mock_info("market_maker", &coins(1_000, "token"));
+        let response = fulfill_pending_tx(deps.as_mut(), env, maker_info, 0).unwrap();
+
+        // The destination receives the amount less the protocol fee
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "destination_addr".to_string(),
+                amount: coins(990, "token"),
+            })
+        );
+        assert_eq!(
+            TREASURY.load(deps.as_ref().storage, "token").unwrap(),
+            Uint128::from(10u128)
+        );
+    }
+
+    #[test]
+    fn test_fee_withdrawal_timelock() {
+        let mut deps = mock_dependencies_with_balance(&coins(100, "token"));
+        let mut env = mock_env();
+        let admin_info = mock_info("admin", &[]);
+
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        credit_treasury(deps.as_mut().storage, &coin(100, "token")).unwrap();
+
+        // Only the admin can withdraw fees, and never more than the treasury holds
+        let err = withdraw_fees(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("someone", &[]),
+            "recipient".to_string(),
+            coin(40, "token"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let err = withdraw_fees(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            "recipient".to_string(),
+            coin(101, "token"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::InsufficientTreasury { .. }));
+
+        withdraw_fees(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            "recipient".to_string(),
+            coin(40, "token"),
+        )
+        .unwrap();
+
+        // The queued amount is still held by the contract, so there is no drift
+        let balances = reconcile_treasury(deps.as_ref(), &env.contract.address).unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].available, Uint128::from(60u128));
+        assert_eq!(balances[0].queued, Uint128::from(40u128));
+        assert_eq!(balances[0].surplus, Uint128::zero());
+        assert_eq!(balances[0].deficit, Uint128::zero());
+
+        // The withdrawal cannot be executed before the timelock passes
+        let err =
+            execute_fee_withdrawal(deps.as_mut(), env.clone(), admin_info.clone(), 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::FeeWithdrawalLocked { id: 0, .. }
+        ));
+
+        env.block.time = env.block.time.plus_seconds(86_400);
+        let response = execute_fee_withdrawal(deps.as_mut(), env, admin_info, 0).unwrap();
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "recipient".to_string(),
+                amount: coins(40, "token"),
+            })
+        );
+        assert!(FEE_WITHDRAWALS
+            .may_load(deps.as_ref().storage, 0)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_lowered_timelock_waits_for_current_one() {
+        let mut deps = mock_dependencies_with_balance(&coins(100, "token"));
+        let mut env = mock_env();
+        let admin_info = mock_info("admin", &[]);
+
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        credit_treasury(deps.as_mut().storage, &coin(100, "token")).unwrap();
+        let set_timelock = |deps: DepsMut, env: Env, seconds| {
+            update_config(
+                deps,
+                env,
+                mock_info("admin", &[]),
+                None,
+                None,
+                None,
+                Some(seconds),
+                None,
+                None,
+                None,
+            )
+        };
+        let withdraw = |deps: DepsMut, env: Env| {
+            withdraw_fees(
+                deps,
+                env,
+                mock_info("admin", &[]),
+                "recipient".to_string(),
+                coin(10, "token"),
+            )
+        };
+
+        // Dropping the timelock does not let a withdrawal through in the same block
+        set_timelock(deps.as_mut(), env.clone(), 0).unwrap();
+        withdraw(deps.as_mut(), env.clone()).unwrap();
+        let err =
+            execute_fee_withdrawal(deps.as_mut(), env.clone(), admin_info.clone(), 0).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::FeeWithdrawalLocked { id: 0, .. }
+        ));
+        assert_eq!(
+            PENDING_TIMELOCK.load(deps.as_ref().storage).unwrap(),
+            PendingTimelock {
+                seconds: 0,
+                effective_at: env.block.time.plus_seconds(86_400),
+            }
+        );
+
+        // Once the current timelock has passed, the shorter one applies
+        env.block.time = env.block.time.plus_seconds(86_400);
+        withdraw(deps.as_mut(), env.clone()).unwrap();
+        execute_fee_withdrawal(deps.as_mut(), env.clone(), admin_info, 1).unwrap();
+        assert_eq!(
+            CONFIG
+                .load(deps.as_ref().storage)
+                .unwrap()
+                .withdrawal_timelock_seconds,
+            0
+        );
+
+        // Raising the timelock applies at once
+        set_timelock(deps.as_mut(), env.clone(), 3_600).unwrap();
+        withdraw(deps.as_mut(), env.clone()).unwrap();
+        let release_at = FEE_WITHDRAWALS
+            .load(deps.as_ref().storage, 2)
+            .unwrap()
+            .release_at;
+        assert_eq!(release_at, env.block.time.plus_seconds(3_600));
+    }
+
+    #[test]
+    fn test_cancel_fee_withdrawal() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let admin_info = mock_info("admin", &[]);
+
+        CONFIG.save(deps.as_mut().storage, &mock_config()).unwrap();
+        credit_treasury(deps.as_mut().storage, &coin(100, "token")).unwrap();
+
+        withdraw_fees(
+            deps.as_mut(),
+            env.clone(),
+            admin_info.clone(),
+            "recipient".to_string(),
+            coin(100, "token"),
+        )
+        .unwrap();
+        assert_eq!(
+            TREASURY.load(deps.as_ref().storage, "token").unwrap(),
+            Uint128::zero()
+        );
+
+        cancel_fee_withdrawal(deps.as_mut(), env.clone(), admin_info, 0).unwrap();
+        assert_eq!(
+            TREASURY.load(deps.as_ref().storage, "token").unwrap(),
+            Uint128::from(100u128)
+        );
+
+        // With nothing held by the contract, the accounted fees show up as a deficit
+        let balances = reconcile_treasury(deps.as_ref(), &env.contract.address).unwrap();
+        assert_eq!(balances[0].deficit, Uint128::from(100u128));
+    }
+}